@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+use actix_web::{
+    web, Error, Scope,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    middleware::{Next, from_fn}
+};
+use chrono::Duration;
+use short_uuid::ShortUuid;
+use uuid::Uuid;
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::security_headers::SecurityHeaders,
+        repos::{
+            CertModel,
+            CertStore,
+            CreationError,
+            KvStore,
+            WebhookRepo
+        },
+        services::{
+            admin,
+            cache,
+            cert_cache,
+            email,
+            kinds::{
+                self,
+                DEFAULT_CERT_KIND
+            },
+            webhooks::{
+                self,
+                WebhookEvent
+            }
+        },
+        types::{
+            errors::Errors,
+            requests::{
+                BulkCertRow,
+                BulkIssuanceQuery
+            },
+            responses::success::{
+                BulkIssuanceResponse,
+                BulkRowResult,
+                CertificateResponse
+            }
+        }
+    },
+    configs::ApiSettings,
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger
+    }
+};
+
+const BULK_PAYLOAD_LIMIT: usize = 1024 * 1024; // 1 Mb
+const MAX_BULK_ROWS: usize = 1000;
+
+async fn not_found() -> Result<(), Errors> {
+    Err(Errors::PageNotFound {
+        endpoints: Some(&[
+            ("POST", "/api/v1/certs/bulk"),
+        ])
+    })
+}
+
+/// Rejects the requests without the administrative token before the up to 1 Mb body is read
+async fn require_admin(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if !admin::is_admin_request(request.request()) {
+        return Ok(request.error_response(Errors::Unauthorized).map_into_right_body());
+    }
+
+    Ok(next.call(request).await?.map_into_left_body())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OnDuplicate {
+    Skip,
+    Update
+}
+
+/// Issues a certificate to every attendee from the CSV body with the "email,name,title" header
+/// Every row is processed on its own, so one invalid row doesn't reject the whole list
+/// The scope checks the administrative token before the handler
+#[actix_web::post("")]
+#[allow(clippy::too_many_arguments)] // The extractors of the handler
+pub async fn bulk_issue_endpoint(
+    query: Result<web::Query<BulkIssuanceQuery>, Error>,
+    body: web::Bytes,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<BulkIssuanceResponse>, Errors> {
+    let place_name = "POST /api/v1/certs/bulk";
+
+    let query = query
+        .log_with_place_on_error(place_name)
+        .map_err(|_| Errors::BadRequest { what_invalid: "query" })?;
+
+    let on_duplicate = match query.on_duplicate.as_deref().unwrap_or("skip") {
+        "skip" => OnDuplicate::Skip,
+        "update" => OnDuplicate::Update,
+        _ => {
+            return Err(Errors::BadRequest { what_invalid: "on_duplicate (expected skip or update)" });
+        }
+    };
+    let is_public = query.public.unwrap_or(false);
+
+    // The kind follows the catalogue rules of the regular creation, the cap is checked for every row
+    let kind = kinds::find_active_kind(&settings, query.kind.as_deref().unwrap_or(DEFAULT_CERT_KIND), clock.now())
+        .ok_or(Errors::KindUnavailable)?;
+
+    // Read the CSV header
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(body.as_ref());
+
+    let headers = reader.headers()
+        .log_with_place_on_error(place_name)
+        .map_err(|_| Errors::BadRequest { what_invalid: "CSV header" })?
+        .clone();
+
+    for column in ["email", "name", "title"] {
+        if !headers.iter().any(|header| header == column) {
+            return Err(Errors::BadRequest { what_invalid: "CSV header (expected email, name and title columns)" });
+        }
+    }
+
+    let records: Vec<_> = reader.records().collect();
+    if records.len() > MAX_BULK_ROWS {
+        return Err(Errors::BadRequest { what_invalid: "CSV length (too many rows)" });
+    }
+
+    let mut results = Vec::with_capacity(records.len());
+    let mut seen_emails = HashSet::new();
+
+    for (index, record) in records.into_iter().enumerate() {
+        // The header is the first line
+        let row = index as u64 + 2;
+
+        // Clean and validate the row
+        let row_unclear = match record.and_then(|record| record.deserialize::<BulkCertRow>(Some(&headers))) {
+            Ok(row_unclear) => row_unclear,
+            Err(_) => {
+                results.push(BulkRowResult::failed(row, None, "invalid CSV row"));
+                continue;
+            }
+        };
+        let cert_row = row_unclear.trim();
+
+        if cert_row.validate().is_err() {
+            results.push(BulkRowResult::failed(row, Some(cert_row.email), "invalid field values"));
+            continue;
+        }
+
+        if !seen_emails.insert(cert_row.email.to_lowercase()) {
+            results.push(BulkRowResult::failed(row, Some(cert_row.email), "email is repeated in the file"));
+            continue;
+        }
+
+        // Check if the attendee already has a certificate of the kind the bulk issuance gives
+        let existing_option = match cert_repo.find_certs_by_email(cert_row.email.clone()).await {
+            Ok(existing) => existing.into_iter().find(|cert| cert.kind == kind.id),
+            Err(_) => {
+                results.push(BulkRowResult::failed(row, Some(cert_row.email), "DB error"));
+                continue;
+            }
+        };
+
+        if let Some(existing) = existing_option {
+            if on_duplicate == OnDuplicate::Skip {
+                results.push(BulkRowResult::success(row, cert_row.email, "skipped", &existing.id));
+                continue;
+            }
+
+            // Update the name and the title of the existing certificate
+            if cert_repo.update_cert_names(existing.id, cert_row.name.clone(), cert_row.title.clone()).await.is_err() {
+                results.push(BulkRowResult::failed(row, Some(cert_row.email), "DB error"));
+                continue;
+            }
+
+            // Stop serving the old names from the cache
+            let _ = cert_cache::invalidate_cert(redis.as_ref(), &existing.id).await;
+
+            // Notify the webhook subscribers
+            let _ = webhooks::dispatch_event(
+                redis.as_ref(),
+                webhook_repo.as_ref(),
+                WebhookEvent::CertUpdated,
+                serde_json::to_value(CertificateResponse::new(
+                    &existing.id,
+                    cert_row.name.clone(),
+                    cert_row.title.clone(),
+                    existing.kind.clone(),
+                    existing.expires_at,
+                    clock.now()
+                )).unwrap_or_default()
+            ).await;
+
+            results.push(BulkRowResult::success(row, cert_row.email, "updated", &existing.id));
+            continue;
+        }
+
+        // Create and save certificate to the data base
+        let cert_uuid = Uuid::new_v4();
+        let created_at = clock.now();
+        let expires_at = kind.validity(&settings).map(|validity| created_at + validity);
+        let creation_result = cert_repo.create_cert(CertModel {
+            id: cert_uuid,
+            email: cert_row.email.clone(),
+            name: cert_row.name.clone(),
+            title: cert_row.title.clone(),
+            kind: kind.id.clone(),
+            created_at,
+            is_public,
+            updated_at: None,
+            expires_at
+        }, settings.certs_per_email_limit, kind.cap).await;
+
+        match creation_result {
+            Ok(_) => {},
+            Err(CreationError::QuotaExceeded) => {
+                results.push(BulkRowResult::failed(row, Some(cert_row.email), "email has the maximum number of certificates"));
+                continue;
+            },
+            Err(CreationError::KindCapReached) => {
+                results.push(BulkRowResult::failed(row, Some(cert_row.email), "kind has the maximum number of certificates"));
+                continue;
+            },
+            Err(CreationError::Another( .. )) => {
+                results.push(BulkRowResult::failed(row, Some(cert_row.email), "DB error"));
+                continue;
+            }
+        };
+
+        // Update the count of certificates in the Redis storage
+        let _ = redis.increase_by_one(
+            cache::get_key("stats:users_count"),
+            Duration::days(1)
+        ).await;
+
+        let certificate = CertificateResponse::new(
+            &cert_uuid,
+            cert_row.name.clone(),
+            cert_row.title.clone(),
+            kind.id.clone(),
+            expires_at,
+            created_at
+        );
+
+        // Notify the webhook subscribers
+        let _ = webhooks::dispatch_event(
+            redis.as_ref(),
+            webhook_repo.as_ref(),
+            WebhookEvent::CertCreated,
+            serde_json::to_value(&certificate).unwrap_or_default()
+        ).await;
+
+        // Tell the new holder about the certificate
+        let _ = email::send_cert_ready(
+            redis.as_ref(),
+            &cert_row.email,
+            &ShortUuid::from_uuid(&cert_uuid).to_string(),
+            &cert_row.name, &cert_row.title
+        )
+            .await
+            .log_with_place_on_error(place_name);
+
+        results.push(BulkRowResult::success(row, cert_row.email, "created", &cert_uuid));
+    }
+
+    Ok(web::Json(
+        BulkIssuanceResponse::new(results)
+    ))
+}
+
+pub fn bulk_scope() -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = Error, InitError = ()>> {
+    web::scope("/certs/bulk")
+        // The administrative endpoints are not for the browsers of the other sites
+        .wrap(from_fn(require_admin))
+        .wrap(SecurityHeaders::default())
+        .app_data(web::PayloadConfig::default().limit(BULK_PAYLOAD_LIMIT))
+        .service(bulk_issue_endpoint)
+        .default_service(web::route().to(not_found))
+}
@@ -0,0 +1,112 @@
+use actix_web::{Error, web};
+use chrono::{DateTime, Utc};
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::verification::find_bound_cert, 
+        repos::{
+            CertModel, 
+            CertStore, 
+            KvStore, 
+            WebhookRepo
+        }, 
+        services::{
+            codes, 
+            webhooks::{
+                self, 
+                WebhookEvent
+            }
+        }, 
+        types::{
+            errors::Errors, 
+            requests::UpdateVisibilityRequest, 
+            responses::success::{
+                CertVisibilityResponse, 
+                CertificateResponse
+            }
+        }
+    }, 
+    utils::{
+        clock::Clock, 
+        log_error::ResultLogger
+    }
+};
+
+/// Lists the certificate in the public gallery or hides it and notifies the webhook subscribers
+pub async fn change_visibility(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    webhook_repo: &WebhookRepo,
+    cert: CertModel, public: bool,
+    now: DateTime<Utc>
+) -> Result<(), Errors> {
+    // Change the visibility
+    let update_count = cert_repo.set_cert_visibility(cert.id, cert.email.clone(), public)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    if update_count == 0 {
+        return Err(Errors::ResourceNotFound { what: "certificate" });
+    }
+
+    // Notify the webhook subscribers
+    let _ = webhooks::dispatch_event(
+        redis, 
+        webhook_repo, 
+        WebhookEvent::CertUpdated, 
+        serde_json::to_value(CertificateResponse::new(
+            &cert.id, 
+            cert.name, 
+            cert.title, 
+            cert.kind, 
+            cert.expires_at, 
+            now
+        )).unwrap_or_default()
+    ).await;
+
+    Ok(())
+}
+
+#[actix_web::patch("/cert/visibility")]
+pub async fn update_visibility_endpoint(
+    body: Result<web::Json<UpdateVisibilityRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>, 
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<CertVisibilityResponse>, Errors> {
+    let place_name = "PATCH /api/v1/cert/visibility";
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            // Verify and consume the code from request body
+            let now = clock.now();
+            let cert_id = codes::verify_and_consume_code(
+                redis.as_ref(), 
+                &body.email, &body.token, &body.code, 
+                "visibility", now
+            ).await?;
+
+            // Receive the certificate the code was requested for
+            let cert = find_bound_cert(cert_repo.as_ref(), cert_id, &body.email).await?;
+
+            let cert_id = cert.id;
+            change_visibility(redis.as_ref(), cert_repo.as_ref(), webhook_repo.as_ref(), cert, body.public, now).await?;
+
+            Ok(web::Json(
+                CertVisibilityResponse::new(&cert_id, body.public)
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
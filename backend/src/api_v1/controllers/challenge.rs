@@ -0,0 +1,47 @@
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use crate::{
+    api_v1::{
+        repos::KvStore,
+        services::challenge,
+        types::{
+            errors::Errors,
+            responses::success::ChallengeResponse
+        }
+    },
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger
+    }
+};
+
+/// Checks the proof-of-work solution from the request body
+/// The solution is consumed, so the client needs a new challenge for every request
+pub async fn check_challenge(
+    redis: &dyn KvStore,
+    challenge: &str, solution: &str,
+    now: DateTime<Utc>
+) -> Result<(), Errors> {
+    let is_solved = challenge::verify_solution(redis, challenge, solution, now)
+        .await
+        .log_with_place_on_error("challenge verification")
+        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+    if !is_solved {
+        return Err(Errors::InvalidChallenge);
+    }
+
+    Ok(())
+}
+
+#[actix_web::get("/challenge")]
+pub async fn challenge_endpoint(
+    redis: web::Data<dyn KvStore>,
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<ChallengeResponse>, Errors> {
+    let challenge = challenge::issue_challenge(redis.as_ref(), clock.now()).await;
+
+    Ok(web::Json(
+        ChallengeResponse::new(challenge)
+    ))
+}
@@ -1,15 +1,21 @@
-use actix_web::{web, Error, HttpRequest};
+use actix_web::{web, Error, middleware::from_fn};
 use chrono::Duration;
 use validator::Validate;
 use crate::{
     api_v1::{
+        controllers::{
+            challenge::check_challenge,
+            idempotency::idempotency_keys
+        },
         repos::{
-            CertRepo, 
-            RedisRepo
+            CertStore, 
+            KvStore
         }, 
         services::{
+            client_ip::ClientIp, 
             codes, 
             email, 
+            ip_limits,
             rate_limits
         }, 
         types::{
@@ -21,18 +27,22 @@ use crate::{
             responses::success::CodeSentResponse
         }
     }, 
+    configs::ApiSettings, 
     utils::{
+        clock::Clock, 
         log_error::ResultLogger, 
         uuid::get_uuid
     }
 };
 
-#[actix_web::post("/send_code")]
+#[actix_web::post("/send_code", wrap = "from_fn(idempotency_keys)")]
 pub async fn send_code_endpoint(
-    request: HttpRequest,
+    client_ip: ClientIp,
     body: Result<web::Json<SendCodeRequest>, Error>,
-    redis: web::Data<RedisRepo>,
-    cert_repo: web::Data<CertRepo>
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>, 
+    clock: web::Data<dyn Clock>, 
+    settings: web::Data<ApiSettings>
 ) -> Result<web::Json<CodeSentResponse>, Errors> {
     let place_name = "POST /api/v1/send_code";
 
@@ -47,23 +57,24 @@ pub async fn send_code_endpoint(
                 return Err(Errors::BadRequest { what_invalid: "email field value" });
             }
 
-            // Receive user's IP
-            let user_ip = match request.connection_info().realip_remote_addr() {
-                Some(ip) => ip.to_string(),
-                None => {
-                    return Err(Errors::InternalServer { what: "IP address" });
-                },
-            };
+            // Check the proof-of-work solution before any counters change
+            check_challenge(
+                redis.as_ref(),
+                &body.challenge, &body.solution,
+                clock.now()
+            ).await?;
 
-            // Check rate limit by the IP address
-            if !rate_limits::check_rate_counter(
+            // Check rate limits by the IP address and its networks
+            let ip_keys = ip_limits::get_ip_rate_keys(&settings, client_ip.0);
+
+            if let Some(exceeded) = ip_limits::find_exceeded_key(
                 redis.as_ref(), 
-                "code", &user_ip,
+                "code", &ip_keys,
                 5
             ).await {
                 let ttl = rate_limits::get_rate_time(
                     redis.as_ref(),
-                    "code", &user_ip
+                    "code", &exceeded.key
                 )
                     .await
                     .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
@@ -94,17 +105,24 @@ pub async fn send_code_endpoint(
             }
 
             // Check special cases that depends on purposes
+            let mut bound_cert_id = None;
+
             match body.purpose {
                 SendCodePurposes::ConfirmCreation => {
-                    let cert_to_check = cert_repo.find_cert_by_email(body.email.clone())
+                    let certs_count = cert_repo.count_by_email(body.email.clone())
                         .await
                         .map_err(|_| Errors::InternalServer { what: "DB" })?;
 
-                    if cert_to_check.is_some() {
-                        return Err(Errors::AlreadyExists { what: "certificate with this email" });
+                    let limit = settings.certs_per_email_limit;
+                    if certs_count >= limit {
+                        return Err(Errors::CertQuotaExceeded { limit });
                     }
                 },
-                SendCodePurposes::ConfirmDeletion { ref id } => {
+                SendCodePurposes::ConfirmDeletion { ref id } 
+                | SendCodePurposes::ConfirmVisibility { ref id } 
+                | SendCodePurposes::ConfirmSession { ref id } 
+                | SendCodePurposes::ConfirmTransfer { ref id } 
+                | SendCodePurposes::ConfirmRenewal { ref id } => {
                     if let Some(uuid) = get_uuid(id) {
                         let cert_to_check = cert_repo.find_cert_by_id(uuid)
                             .await
@@ -114,19 +132,23 @@ pub async fn send_code_endpoint(
                             if cert.email != body.email {
                                 return Err(Errors::InvalidEmail);
                             }
+
+                            bound_cert_id = Some(cert.id);
                         } else {
                             return Err(Errors::ResourceNotFound { what: "certificate with this ID" });
                         }
                     } else {
                         return Err(Errors::BadRequest { what_invalid: "id field value" });
                     };
-                }
+                },
+                // Personal data belongs to the email address itself, so there is nothing to check
+                SendCodePurposes::ConfirmExport | SendCodePurposes::ConfirmErasure => {}
             };
 
-            // Update rate limit by the IP address
-            let _ = rate_limits::increate_rate_counter(
+            // Update rate limits by the IP address and its networks
+            let _ = ip_limits::increase_rate_counters(
                 redis.as_ref(), 
-                "code", &user_ip, 
+                "code", &ip_keys, 
                 Duration::minutes(10)
             ).await;
 
@@ -146,7 +168,9 @@ pub async fn send_code_endpoint(
                 redis.as_ref(), 
                 &body.email, 
                 &body.purpose.to_string(), 
-                &email_code, &email_token
+                bound_cert_id.as_ref(), 
+                &email_code, &email_token, 
+                clock.now()
             )
                 .await
                 .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
@@ -166,6 +190,54 @@ pub async fn send_code_endpoint(
                     )
                         .await
                         .map_err(|_| Errors::InternalServer { what: "broker" })?;
+                },
+                SendCodePurposes::ConfirmVisibility { .. } => {
+                    email::send_manage_code(
+                        redis.as_ref(), &body.email, &email_code, 
+                        "зміна видимості в публічній галереї"
+                    )
+                        .await
+                        .map_err(|_| Errors::InternalServer { what: "broker" })?;
+                },
+                SendCodePurposes::ConfirmExport => {
+                    email::send_manage_code(
+                        redis.as_ref(), &body.email, &email_code, 
+                        "експорт персональних даних"
+                    )
+                        .await
+                        .map_err(|_| Errors::InternalServer { what: "broker" })?;
+                },
+                SendCodePurposes::ConfirmErasure => {
+                    email::send_manage_code(
+                        redis.as_ref(), &body.email, &email_code, 
+                        "повне видалення персональних даних"
+                    )
+                        .await
+                        .map_err(|_| Errors::InternalServer { what: "broker" })?;
+                },
+                SendCodePurposes::ConfirmSession { .. } => {
+                    email::send_manage_code(
+                        redis.as_ref(), &body.email, &email_code, 
+                        "вхід для керування сертифікатом"
+                    )
+                        .await
+                        .map_err(|_| Errors::InternalServer { what: "broker" })?;
+                },
+                SendCodePurposes::ConfirmTransfer { .. } => {
+                    email::send_manage_code(
+                        redis.as_ref(), &body.email, &email_code, 
+                        "передача сертифіката на іншу пошту"
+                    )
+                        .await
+                        .map_err(|_| Errors::InternalServer { what: "broker" })?;
+                },
+                SendCodePurposes::ConfirmRenewal { .. } => {
+                    email::send_manage_code(
+                        redis.as_ref(), &body.email, &email_code, 
+                        "продовження терміну дії сертифіката"
+                    )
+                        .await
+                        .map_err(|_| Errors::InternalServer { what: "broker" })?;
                 }
             }
 
@@ -1,22 +1,28 @@
-use actix_web::{Error, web};
-use chrono::{Duration, Utc};
+use actix_web::{Error, middleware::from_fn, web};
+use chrono::Duration;
 use uuid::Uuid;
-use validator::Validate;
+use validator::ValidateArgs;
 use crate::{
     api_v1::{
+        controllers::idempotency::idempotency_keys, 
         repos::{
             CertModel, 
-            CertRepo, 
+            CertStore, 
             CreationError, 
-            RedisRepo
+            KvStore, 
+            WebhookRepo
         }, 
         services::{
             cache, 
-            codes::{
+            codes, 
+            kinds::{
                 self, 
-                VerificationResult
+                KindsContext
             }, 
-            rate_limits
+            webhooks::{
+                self, 
+                WebhookEvent
+            }
         }, 
         types::{
             errors::Errors, 
@@ -24,14 +30,21 @@ use crate::{
             responses::success::CertificateResponse
         }
     }, 
-    utils::log_error::ResultLogger
+    configs::ApiSettings, 
+    utils::{
+        clock::Clock, 
+        log_error::ResultLogger
+    }
 };
 
-#[actix_web::post("/cert")]
+#[actix_web::post("/cert", wrap = "from_fn(idempotency_keys)")]
 pub async fn create_cert_endpoint(
     body: Result<web::Json<CreateCertRequest>, Error>,
-    redis: web::Data<RedisRepo>,
-    cert_repo: web::Data<CertRepo>
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>, 
+    clock: web::Data<dyn Clock>, 
+    settings: web::Data<ApiSettings>
 ) -> Result<web::Json<CertificateResponse>, Errors> {
     let place_name = "POST /api/v1/cert";
 
@@ -41,121 +54,88 @@ pub async fn create_cert_endpoint(
             let body = body_unclear.trim();
 
             if body
-                .validate()
+                .validate_with_args(&KindsContext { now: clock.now(), settings: settings.clone().into_inner() })
                 .log_with_place_on_error(place_name)
                 .is_err() {
                 return Err(Errors::BadRequest { what_invalid: "field values" });
             }
 
-            // Verify the code from request body
-            let verification_result = codes::verify_email_code(
-                redis.as_ref(), 
-                &body.email,
-                &body.token, 
-                &body.code
-            ).await;
-
-            match verification_result {
-                VerificationResult::Ok { purpose } => {
-                    if purpose != "create" {
-                        // When created code has the wrong purpose
-                        return Err(Errors::InvalidRoute { correct_route: "DELETE /api/v1/cert" });
-                    }
+            // The kind could leave the catalogue after the validation, then it is unavailable too
+            let kind = kinds::find_active_kind(&settings, &body.kind, clock.now())
+                .ok_or(Errors::KindUnavailable)?;
 
-                    // Delete code from the Redis storage
-                    codes::remove_code_from_storage(redis.as_ref(), &body.email)
-                        .await
-                        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+            // The capped kinds stop being issued when the cap is reached, the code stays for another kind
+            // The check keeps the code unused, the creation below checks the cap again under the lock
+            let remaining = kinds::get_remaining(cert_repo.as_ref(), &kind)
+                .await
+                .map_err(|_| Errors::InternalServer { what: "DB" })?;
 
-                    // Reset the rate counter by the email address
-                    let _ = rate_limits::reset_rate_counter(
-                        redis.as_ref(), 
-                        "code", &body.email
-                    ).await;
-
-                    // Create and save certificate to the data base
-                    let cert_uuid = Uuid::new_v4();
-                    let creation_result = cert_repo.create_cert(CertModel {
-                        id: cert_uuid.clone(),
-                        email: body.email,
-                        name: body.name.clone(),
-                        title: body.title.clone()
-                    }).await;
-
-                    match creation_result {
-                        Ok(_) => {},
-                        Err(CreationError::UniqueErr) => {
-                            return Err(Errors::AlreadyExists { what: "certificate with this email" });
-                        },
-                        Err(CreationError::Another( .. )) => {
-                            return Err(Errors::InternalServer { what: "DB" });
-                        }
-                    };
-
-                    // Update the count of certificates in the Redis storage
-                    let _ = redis.increase_by_one(
-                        cache::get_key("stats:users_count"), 
-                        Duration::days(1)
-                    ).await;
+            if remaining == Some(0) {
+                return Err(Errors::KindUnavailable);
+            }
 
-                    // Return the certificate data
-                    Ok(web::Json(CertificateResponse::new(
-                        &cert_uuid, 
-                        body.name.to_string(), 
-                        body.title.to_string()
-                    )))
+            // Verify and consume the code from request body
+            codes::verify_and_consume_code(
+                redis.as_ref(), 
+                &body.email, &body.token, &body.code, 
+                "create", clock.now()
+            ).await?;
+
+            // Create and save certificate to the data base
+            let limit = settings.certs_per_email_limit;
+            let cert_uuid = Uuid::new_v4();
+            let created_at = clock.now();
+            let expires_at = kind.validity(&settings).map(|validity| created_at + validity);
+            let creation_result = cert_repo.create_cert(CertModel {
+                id: cert_uuid,
+                email: body.email,
+                name: body.name.clone(),
+                title: body.title.clone(),
+                kind: kind.id.clone(),
+                created_at,
+                is_public: body.public,
+                updated_at: None,
+                expires_at
+            }, limit, kind.cap).await;
+
+            match creation_result {
+                Ok(_) => {},
+                Err(CreationError::QuotaExceeded) => {
+                    return Err(Errors::CertQuotaExceeded { limit });
                 },
-                VerificationResult::InvalidToken => Err(Errors::InvalidToken),
-                VerificationResult::NotFound => Err(Errors::ResourceNotFound { what: "code record" }),
-                VerificationResult::UnknownError( .. ) => Err(Errors::InternalServer { what: "code verification" }),
-                VerificationResult::InvalidCode => {
-                    if rate_limits::check_rate_counter(
-                        redis.as_ref(), 
-                        "token_tries", &body.token, 
-                        5
-                    ).await {
-                        // Invalid code, but there are some tries left
-
-                        rate_limits::increate_rate_counter(
-                            redis.as_ref(), 
-                            "token_tries", &body.token, 
-                            Duration::days(1)
-                        )
-                            .await
-                            .map_err(|_| Errors::InvalidCode)?;
-
-                        Err(Errors::InvalidCode)
-                    } else {
-                        // Invalid code, but there is no tries left
-
-                        let block_duration = Duration::minutes(15);
-                        let block_timestamp = Utc::now() + block_duration;
-
-                        // Make the code inaccesible to confirm
-                        let _ = codes::remove_code_from_storage(
-                            &redis, &body.email
-                        ).await;
-
-                        // Block the email address for the code sending
-                        let _ = redis.set_value(
-                            rate_limits::get_key("code", &body.email), 
-                            10000, 
-                            block_duration.clone(), true
-                        ).await;
+                Err(CreationError::KindCapReached) => {
+                    return Err(Errors::KindUnavailable);
+                },
+                Err(CreationError::Another( .. )) => {
+                    return Err(Errors::InternalServer { what: "DB" });
+                }
+            };
+
+            // Update the count of certificates in the Redis storage
+            let _ = redis.increase_by_one(
+                cache::get_key("stats:users_count"), 
+                Duration::days(1)
+            ).await;
 
-                        // Remove the tries counter from the Redis storage
-                        let _ = rate_limits::reset_rate_counter(
-                            &redis,
-                            "token_tries", &body.token
-                        ).await;
+            let certificate = CertificateResponse::new(
+                &cert_uuid, 
+                body.name.to_string(), 
+                body.title.to_string(), 
+                kind.id, 
+                expires_at, 
+                created_at
+            );
+
+            // Notify the webhook subscribers
+            let _ = webhooks::dispatch_event(
+                redis.as_ref(), 
+                webhook_repo.as_ref(), 
+                WebhookEvent::CertCreated, 
+                serde_json::to_value(&certificate).unwrap_or_default()
+            ).await;
 
-                        Err(Errors::TriesOut { 
-                            how_much: block_duration.num_seconds() as u32, 
-                            timestamp: block_timestamp.timestamp() as u64
-                        })
-                    }
-                },
-            }
+            // Return the certificate data
+            Ok(web::Json(certificate))
         },
         Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
     }
@@ -1,14 +1,16 @@
-use actix_web::{web, Error, HttpRequest};
+use actix_web::{web, Error};
 use chrono::Duration;
-use short_uuid::ShortUuid;
 use crate::{
     api_v1::{
+        controllers::challenge::check_challenge,
         repos::{
-            CertRepo, 
-            RedisRepo
+            CertStore, 
+            KvStore
         }, 
         services::{
+            client_ip::ClientIp, 
             email::send_forgot_cert, 
+            ip_limits,
             rate_limits
         }, 
         types::{
@@ -17,15 +19,21 @@ use crate::{
             responses::success::CertEmailResponse
         }
     }, 
-    utils::log_error::ResultLogger
+    configs::ApiSettings,
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger
+    }
 };
 
 #[actix_web::post("/cert/forgot")]
 pub async fn forgot_cert_endpoint(
-    request: HttpRequest,
+    client_ip: ClientIp,
     body: Result<web::Json<ForgotCertRequest>, Error>,
-    redis: web::Data<RedisRepo>,
-    cert_repo: web::Data<CertRepo>
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
 ) -> Result<web::Json<CertEmailResponse>, Errors> {
     let place_name = "POST /api/v1/cert/forgot";
 
@@ -33,23 +41,24 @@ pub async fn forgot_cert_endpoint(
         Ok(unclear_body) => {
             let body = unclear_body.trim();
 
-            // Receive user's IP address
-            let user_ip = match request.connection_info().realip_remote_addr() {
-                Some(ip) => ip.to_string(),
-                None => {
-                    return Err(Errors::InternalServer { what: "IP address" });
-                },
-            };
+            // Check the proof-of-work solution before any counters change
+            check_challenge(
+                redis.as_ref(),
+                &body.challenge, &body.solution,
+                clock.now()
+            ).await?;
 
-            // Check rate limit by the IP address
-            if !rate_limits::check_rate_counter(
+            // Check rate limits by the IP address and its networks
+            let ip_keys = ip_limits::get_ip_rate_keys(&settings, client_ip.0);
+
+            if let Some(exceeded) = ip_limits::find_exceeded_key(
                 redis.as_ref(), 
-                "forgot", &user_ip,
+                "forgot", &ip_keys,
                 3
             ).await {
                 let ttl = rate_limits::get_rate_time(
                     redis.as_ref(),
-                    "forgot", &user_ip
+                    "forgot", &exceeded.key
                 )
                     .await
                     .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
@@ -79,16 +88,16 @@ pub async fn forgot_cert_endpoint(
                 })
             }
 
-            // Receive a certificate by the email address
-            let find_option = cert_repo.find_cert_by_email(body.email.clone())
+            // Receive all certificates of the email address
+            let certificates = cert_repo.find_certs_by_email(body.email.clone())
                 .await
                 .map_err(|_| Errors::InternalServer { what: "DB" })?;
 
-            if let Some(certificate) = find_option {
-                // Update rate limit by the IP address
-                let _ = rate_limits::increate_rate_counter(
+            if !certificates.is_empty() {
+                // Update rate limits by the IP address and its networks
+                let _ = ip_limits::increase_rate_counters(
                     redis.as_ref(), 
-                    "forgot", &user_ip, 
+                    "forgot", &ip_keys, 
                     Duration::minutes(10)
                 ).await;
 
@@ -101,9 +110,9 @@ pub async fn forgot_cert_endpoint(
 
                 // Send an email letter
                 send_forgot_cert(
-                    &redis, 
+                    redis.as_ref(), 
                     &body.email, 
-                    &ShortUuid::from_uuid(&certificate.id).to_string()
+                    &certificates
                 )
                     .await
                     .map_err(|_| Errors::InternalServer { what: "broker" })?;
@@ -0,0 +1,249 @@
+use actix_web::{web, Error};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use sha2::{Digest, Sha256};
+use short_uuid::ShortUuid;
+use uuid::Uuid;
+use crate::{
+    api_v1::{
+        repos::{
+            CertModel, 
+            CertStore, 
+            KvStore
+        }, 
+        services::{
+            cache, 
+            client_ip::ClientIp, 
+            ip_limits, 
+            rate_limits, 
+            search
+        }, 
+        types::{
+            errors::Errors, 
+            requests::{
+                CertListQuery, 
+                CertSearchQuery
+            }, 
+            responses::success::{
+                CertHighlights, 
+                CertListResponse, 
+                CertSearchResponse, 
+                CertSearchResult, 
+                CertificateResponse, 
+                DailyCertResponse
+            }
+        }
+    }, 
+    configs::ApiSettings, 
+    utils::{
+        clock::Clock, 
+        log_error::ResultLogger, 
+        uuid::get_uuid
+    }
+};
+
+const DEFAULT_PAGE_LIMIT: u64 = 20;
+const MAX_PAGE_LIMIT: u64 = 50;
+/// Deep pages aren't useful to people, but make scraping easier
+const MAX_SEARCH_OFFSET: u64 = 200;
+const SEARCH_RATE_LIMIT: u64 = 30;
+
+/// Turns the creation time and ID of the last certificate on the page into the cursor of the next page
+fn encode_cursor(cert: &CertModel) -> String {
+    format!("{}_{}", cert.created_at.timestamp_micros(), ShortUuid::from_uuid(&cert.id))
+}
+
+/// Parses the cursor produced by encode_cursor
+fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let (micros, id) = cursor.trim().split_once("_")?;
+    let created_at = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+
+    Some((created_at, get_uuid(id)?))
+}
+
+fn to_response(cert: &CertModel, now: DateTime<Utc>) -> CertificateResponse {
+    CertificateResponse::new(&cert.id, cert.name.clone(), cert.title.clone(), cert.kind.clone(), cert.expires_at, now)
+}
+
+#[actix_web::get("/certs")]
+pub async fn list_certs_endpoint(
+    query: Result<web::Query<CertListQuery>, Error>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<CertListResponse>, Errors> {
+    let place_name = "GET /api/v1/certs";
+    let now = clock.now();
+
+    let query = query
+        .log_with_place_on_error(place_name)
+        .map_err(|_| Errors::BadRequest { what_invalid: "query" })?;
+
+    let limit = query.limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+
+    match query.order.as_deref().unwrap_or("newest") {
+        "newest" => {
+            let cursor = match &query.cursor {
+                Some(cursor) => Some(
+                    decode_cursor(cursor).ok_or(Errors::BadRequest { what_invalid: "cursor" })?
+                ),
+                None => None
+            };
+
+            let certs = cert_repo.find_public_certs_newest(cursor, limit)
+                .await
+                .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+            // A full page means there may be more certificates
+            let next_cursor = if certs.len() as u64 == limit {
+                certs.last().map(encode_cursor)
+            } else {
+                None
+            };
+
+            Ok(web::Json(
+                CertListResponse::new(certs.iter().map(|cert| to_response(cert, now)).collect(), next_cursor)
+            ))
+        },
+        "random" => {
+            let certs = cert_repo.find_public_certs_from_id(Uuid::new_v4(), limit)
+                .await
+                .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+            Ok(web::Json(
+                CertListResponse::new(certs.iter().map(|cert| to_response(cert, now)).collect(), None)
+            ))
+        },
+        _ => Err(Errors::BadRequest { what_invalid: "order (expected newest or random)" })
+    }
+}
+
+#[actix_web::get("/certs/daily")]
+pub async fn daily_cert_endpoint(
+    cert_repo: web::Data<dyn CertStore>,
+    redis: web::Data<dyn KvStore>, 
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<DailyCertResponse>, Errors> {
+    let now = clock.now();
+    let today = now.date_naive();
+    let cache_key = format!("certs:daily:{}", today);
+
+    // Receive the cached pick of the day if it's still public
+    if let Ok(Some(cached_id)) = cache::get_cache::<String>(redis.as_ref(), cache_key.clone()).await
+        && let Some(uuid) = get_uuid(&cached_id)
+        && let Ok(Some(cert)) = cert_repo.find_cert_by_id(uuid).await
+        && cert.is_public {
+        return Ok(web::Json(
+            DailyCertResponse::new(today.to_string(), to_response(&cert, now))
+        ));
+    }
+
+    // The same date always gives the same pivot, so every replica picks the same certificate
+    let digest = Sha256::digest(format!("pupsik-of-the-day:{}", today).as_bytes());
+    let mut pivot_bytes = [0u8; 16];
+    pivot_bytes.copy_from_slice(&digest[..16]);
+
+    let certs = cert_repo.find_public_certs_from_id(Uuid::from_bytes(pivot_bytes), 1)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    let cert = certs
+        .first()
+        .ok_or(Errors::ResourceNotFound { what: "public certificate" })?;
+
+    // Cache the pick until the end of the day
+    let end_of_day = (today + Duration::days(1)).and_time(NaiveTime::MIN).and_utc();
+    let _ = cache::set_cache(
+        redis.as_ref(), 
+        &cache_key, 
+        cert.id.to_string(), 
+        end_of_day - now
+    ).await;
+
+    Ok(web::Json(
+        DailyCertResponse::new(today.to_string(), to_response(cert, now))
+    ))
+}
+
+#[actix_web::get("/certs/search")]
+pub async fn search_certs_endpoint(
+    client_ip: ClientIp,
+    query: Result<web::Query<CertSearchQuery>, Error>,
+    cert_repo: web::Data<dyn CertStore>,
+    redis: web::Data<dyn KvStore>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertSearchResponse>, Errors> {
+    let place_name = "GET /api/v1/certs/search";
+
+    let query = query
+        .log_with_place_on_error(place_name)
+        .map_err(|_| Errors::BadRequest { what_invalid: "query" })?;
+
+    let (normalized, variant) = search::get_query_variants(&query.q)
+        .ok_or(Errors::BadRequest { what_invalid: "q (expected 2 to 100 characters)" })?;
+
+    let limit = query.limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let offset = query.offset.unwrap_or(0);
+
+    if offset > MAX_SEARCH_OFFSET {
+        return Err(Errors::BadRequest { what_invalid: "offset (too deep page)" });
+    }
+
+    // Check rate limits by the IP address and its networks
+    let ip_keys = ip_limits::get_ip_rate_keys(&settings, client_ip.0);
+
+    if let Some(exceeded) = ip_limits::find_exceeded_key(
+        redis.as_ref(), 
+        "search", &ip_keys,
+        SEARCH_RATE_LIMIT
+    ).await {
+        let ttl = rate_limits::get_rate_time(
+            redis.as_ref(),
+            "search", &exceeded.key
+        )
+            .await
+            .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+        return Err(Errors::IPRateLimit { 
+            how_much: ttl.0.num_seconds() as u32,
+            timestamp: ttl.1.timestamp() as u64
+        })
+    }
+
+    // Update rate limits by the IP address and its networks
+    let _ = ip_limits::increase_rate_counters(
+        redis.as_ref(), 
+        "search", &ip_keys, 
+        Duration::minutes(1)
+    ).await;
+
+    let hits = cert_repo.search_public_certs(&normalized, &variant, limit, offset)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    // A full page means there may be more results
+    let next_offset = if hits.len() as u64 == limit && offset + limit <= MAX_SEARCH_OFFSET {
+        Some(offset + limit)
+    } else {
+        None
+    };
+
+    let query_variants = [normalized.as_str(), variant.as_str()];
+    let results = hits
+        .into_iter()
+        .map(|hit| {
+            let highlights = CertHighlights {
+                name: search::find_highlights(&hit.name, &query_variants),
+                title: search::find_highlights(&hit.title, &query_variants)
+            };
+
+            CertSearchResult::new(&hit.id, hit.name, hit.title, hit.rank, highlights)
+        })
+        .collect();
+
+    Ok(web::Json(
+        CertSearchResponse::new(normalized, results, next_offset)
+    ))
+}
@@ -1,20 +1,77 @@
-use actix_web::web;
+use std::time::SystemTime;
+use actix_web::{
+    HttpMessage,
+    HttpRequest,
+    HttpResponse,
+    HttpResponseBuilder,
+    http::header::{
+        self,
+        EntityTag,
+        HttpDate,
+        IfModifiedSince,
+        IfNoneMatch
+    },
+    web
+};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
 use crate::{
     api_v1::{
-        repos::CertRepo, 
+        repos::{
+            CertStore,
+            KvStore
+        },
+        services::cert_cache,
         types::{
-            errors::Errors, 
+            errors::Errors,
             responses::success::CertificateResponse
         }
-    }, 
-    utils::uuid::get_uuid
+    },
+    utils::{
+        clock::Clock,
+        uuid::get_uuid
+    }
 };
 
+/// Browsers and the proxy cache (nginx) revalidate with the ETag after a minute
+/// The proxy isn't purged, so a deleted or erased certificate is served for a minute at most
+const CERT_CACHE_CONTROL: &str = "public, max-age=60";
+
+/// Returns true if the client already has the current version of the certificate
+/// If-None-Match wins over If-Modified-Since as RFC 9110 requires
+fn is_not_modified(request: &HttpRequest, etag: &EntityTag, modified_at: &DateTime<Utc>) -> bool {
+    if let Some(if_none_match) = request.get_header::<IfNoneMatch>() {
+        return match if_none_match {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(etag))
+        };
+    }
+
+    if let Some(IfModifiedSince(since)) = request.get_header::<IfModifiedSince>() {
+        // HTTP dates have the second precision
+        return modified_at.timestamp() <= DateTime::<Utc>::from(SystemTime::from(since)).timestamp();
+    }
+
+    false
+}
+
+fn with_cache_headers(mut builder: HttpResponseBuilder, etag: &EntityTag, modified_at: &DateTime<Utc>) -> HttpResponseBuilder {
+    builder
+        .insert_header(header::ETag(etag.clone()))
+        .insert_header(header::LastModified(HttpDate::from(SystemTime::from(*modified_at))))
+        .insert_header((header::CACHE_CONTROL, CERT_CACHE_CONTROL));
+
+    builder
+}
+
 #[actix_web::get("/cert/{uuid}")]
 pub async fn get_cert_endpoint(
+    request: HttpRequest,
     path: web::Path<(String,)>,
-    cert_repo: web::Data<CertRepo>
-) -> Result<web::Json<CertificateResponse>, Errors> {
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>
+) -> Result<HttpResponse, Errors> {
     // Parse a UUID object from the request body
     let uuid = match get_uuid(&path.0) {
         Some(uuid) => uuid,
@@ -23,17 +80,40 @@ pub async fn get_cert_endpoint(
         }
     };
 
-    // Receive a certificate by the parsed UUID
-    let find_option = cert_repo.find_cert_by_id(uuid)
+    // Receive a certificate by the parsed UUID, the cached copy is used if there is one
+    let find_option = cert_cache::get_cert(redis.as_ref(), cert_repo.as_ref(), uuid)
         .await
         .map_err(|_| Errors::InternalServer { what: "DB" })?;
 
-    if let Some(certificate) = find_option {
-        // Return the certificate data
-        Ok(web::Json(
-            CertificateResponse::new(&uuid, certificate.name, certificate.title)
-        ))
-    } else {
-        Err(Errors::ResourceNotFound { what: "certificate" })
+    let Some(certificate) = find_option else {
+        return Err(Errors::ResourceNotFound { what: "certificate" });
+    };
+
+    // The expiration changes the status, so the certificate counts as modified at that time
+    let now = clock.now();
+    let modified_at = match certificate.expires_at {
+        Some(expires_at) if expires_at <= now => certificate.modified_at.max(expires_at),
+        _ => certificate.modified_at
+    };
+
+    // The strong ETag is the hash of the exact response body
+    let body = serde_json::to_vec(&CertificateResponse::new(
+        &uuid, certificate.name, certificate.title, certificate.kind, certificate.expires_at, now
+    ))
+        .map_err(|_| Errors::InternalServer { what: "serialization" })?;
+    let etag = EntityTag::new_strong(hex::encode(&Sha256::digest(&body)[..16]));
+
+    if is_not_modified(&request, &etag, &modified_at) {
+        return Ok(
+            with_cache_headers(HttpResponse::NotModified(), &etag, &modified_at)
+                .finish()
+        );
     }
+
+    // Return the certificate data
+    Ok(
+        with_cache_headers(HttpResponse::Ok(), &etag, &modified_at)
+            .content_type("application/json")
+            .body(body)
+    )
 }
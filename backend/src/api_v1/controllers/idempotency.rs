@@ -0,0 +1,108 @@
+use actix_web::{
+    Error, HttpResponse,
+    body::{self, BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::{
+        StatusCode,
+        header::{self, HeaderName, HeaderValue}
+    },
+    middleware::Next,
+    web::{self, Bytes}
+};
+use crate::api_v1::{
+    controllers::BODY_PAYLOAD_LIMIT,
+    repos::KvStore,
+    services::idempotency::{self, IdempotencyState, StoredResponse},
+    types::errors::Errors
+};
+
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Marks the replayed responses, so the client can tell them from the new ones
+const IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Replays the first response to the retries with the same Idempotency-Key header
+/// Only the successful responses are stored, after an error the request can be retried with the same key
+/// A key sent with another body is rejected, the requests without the header aren't affected
+pub async fn idempotency_keys(
+    mut request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(idempotency_key) = request.headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|value| value.to_str().unwrap_or_default().trim().to_string()) else {
+        return Ok(next.call(request).await?.map_into_boxed_body());
+    };
+
+    if !idempotency::is_valid_key(&idempotency_key) {
+        return Ok(request.error_response(Errors::BadRequest { what_invalid: "idempotency key" }));
+    }
+
+    // The errors are rendered as responses here, the request doesn't reach the outer middleware otherwise
+    let Some(redis) = request.app_data::<web::Data<dyn KvStore>>().cloned() else {
+        return Ok(request.error_response(Errors::InternalServer { what: "cache storage" }));
+    };
+
+    // The body is read here to get the fingerprint and then given back to the endpoint
+    let Ok(body) = request.extract::<Bytes>().await else {
+        return Ok(request.error_response(Errors::PayloadTooLarge { bytes_limit: BODY_PAYLOAD_LIMIT }));
+    };
+    let endpoint = format!("{} {}", request.method(), request.path());
+    let fingerprint = idempotency::get_fingerprint(&endpoint, &body);
+    let email = idempotency::get_request_email(&body);
+
+    let Ok(state) = idempotency::begin_request(redis.as_ref(), &endpoint, &idempotency_key, &fingerprint, email.as_deref()).await else {
+        return Ok(request.error_response(Errors::InternalServer { what: "cache storage" }));
+    };
+
+    match state {
+        IdempotencyState::Started => {},
+        IdempotencyState::InProgress => return Ok(request.error_response(Errors::IdempotencyKeyInProgress)),
+        IdempotencyState::Mismatch => return Ok(request.error_response(Errors::IdempotencyKeyReused)),
+        IdempotencyState::Completed(stored) => {
+            let mut response = HttpResponse::build(StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK));
+            response.insert_header((IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true")));
+
+            if let Some(content_type) = stored.content_type {
+                response.insert_header((header::CONTENT_TYPE, content_type));
+            }
+
+            return Ok(request.into_response(response.body(stored.body)));
+        }
+    }
+
+    request.set_payload(Payload::from(body));
+    let response = next.call(request).await;
+
+    let response = match response {
+        Ok(response) if response.status().is_success() => response,
+        response => {
+            let _ = idempotency::abort_request(redis.as_ref(), &endpoint, &idempotency_key).await;
+            return Ok(response?.map_into_boxed_body());
+        }
+    };
+
+    // The body is read to store it and then sent as is
+    let (http_request, response) = response.into_parts();
+    let (response, response_body) = response.into_parts();
+    let response_body = body::to_bytes(response_body)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "response body" })?;
+
+    let stored = StoredResponse {
+        status: response.status().as_u16(),
+        content_type: response.headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body: String::from_utf8_lossy(&response_body).to_string()
+    };
+
+    if idempotency::complete_request(redis.as_ref(), &endpoint, &idempotency_key, &fingerprint, email.as_deref(), stored)
+        .await
+        .is_err() {
+        let _ = idempotency::abort_request(redis.as_ref(), &endpoint, &idempotency_key).await;
+    }
+
+    Ok(ServiceResponse::new(http_request, response.set_body(BoxBody::new(response_body))))
+}
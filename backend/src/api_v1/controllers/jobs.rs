@@ -0,0 +1,112 @@
+use actix_web::{web, Error, HttpRequest, Scope, body::MessageBody, dev::{ServiceFactory, ServiceRequest, ServiceResponse}};
+use crate::{
+    api_v1::{
+        controllers::security_headers::SecurityHeaders,
+        repos::{
+            CertStore,
+            KvStore
+        },
+        services::{
+            admin,
+            scheduler::{
+                self,
+                ScheduledJob
+            }
+        },
+        types::{
+            errors::Errors,
+            responses::success::{
+                JobListResponse,
+                JobResponse,
+                JobRunResponse
+            }
+        }
+    },
+    utils::clock::Clock
+};
+
+/// Manual runs are recorded in the history under this instance name
+const MANUAL_RUN_INSTANCE: &str = "manual";
+
+async fn not_found() -> Result<(), Errors> {
+    Err(Errors::PageNotFound {
+        endpoints: Some(&[
+            ("GET", "/api/v1/jobs"),
+            ("POST", "/api/v1/jobs/{name}/run"),
+        ])
+    })
+}
+
+/// Returns the scheduled jobs with their next run times and the run history
+#[actix_web::get("")]
+pub async fn list_jobs_endpoint(
+    request: HttpRequest,
+    redis: web::Data<dyn KvStore>,
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<JobListResponse>, Errors> {
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    let leader = redis.get_value::<String>(scheduler::SCHEDULER_LEADER_KEY.to_string())
+        .await
+        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+    let mut jobs = Vec::with_capacity(ScheduledJob::all().len());
+    for job in ScheduledJob::all() {
+        let last_run = scheduler::get_last_run(redis.as_ref(), *job)
+            .await
+            .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+        let history = scheduler::get_history(redis.as_ref(), *job)
+            .await
+            .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+        jobs.push(JobResponse {
+            name: job.as_str().to_string(),
+            schedule: job.schedule_expression().to_string(),
+            last_run_at: last_run.map(|a| a.timestamp() as u64),
+            next_run_at: job.schedule()
+                .next_after(last_run.unwrap_or(clock.now()))
+                .map(|a| a.timestamp() as u64),
+            runs: history.iter().map(JobRunResponse::new).collect()
+        });
+    }
+
+    Ok(web::Json(
+        JobListResponse::new(leader, jobs)
+    ))
+}
+
+/// Runs the job immediately on this replica, the schedule isn't changed
+#[actix_web::post("/{name}/run")]
+pub async fn run_job_endpoint(
+    request: HttpRequest,
+    path: web::Path<(String,)>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<JobRunResponse>, Errors> {
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    let job = ScheduledJob::parse(&path.0)
+        .ok_or(Errors::ResourceNotFound { what: "job" })?;
+
+    let run = scheduler::run_job(redis.as_ref(), cert_repo.as_ref(), clock.as_ref(), job, MANUAL_RUN_INSTANCE)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+    Ok(web::Json(
+        JobRunResponse::new(&run)
+    ))
+}
+
+pub fn jobs_scope() -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = Error, InitError = ()>> {
+    web::scope("/jobs")
+        // The administrative endpoints are not for the browsers of the other sites
+        .wrap(SecurityHeaders::default())
+        .service(list_jobs_endpoint)
+        .service(run_job_endpoint)
+        .default_service(web::route().to(not_found))
+}
@@ -0,0 +1,43 @@
+use actix_web::{HttpRequest, web};
+use crate::{
+    api_v1::{
+        repos::CertStore,
+        services::{
+            kinds,
+            locale
+        },
+        types::{
+            errors::Errors,
+            responses::success::{
+                CertKindResponse,
+                CertKindsResponse
+            }
+        }
+    },
+    configs::ApiSettings,
+    utils::clock::Clock
+};
+
+/// Returns the kinds that can be chosen now with the names in the language of the request
+#[actix_web::get("/kinds")]
+pub async fn list_kinds_endpoint(
+    request: HttpRequest,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertKindsResponse>, Errors> {
+    let language = locale::negotiate_language(&request);
+    let mut kinds_list = Vec::new();
+
+    for kind in kinds::get_active_kinds(&settings, clock.now()) {
+        let remaining = kinds::get_remaining(cert_repo.as_ref(), &kind)
+            .await
+            .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+        kinds_list.push(CertKindResponse::new(kind, remaining, language));
+    }
+
+    Ok(web::Json(
+        CertKindsResponse::new(kinds_list)
+    ))
+}
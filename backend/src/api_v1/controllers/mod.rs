@@ -1,16 +1,30 @@
-use std::{future::Ready, sync::Arc, time::Duration};
-use actix_extensible_rate_limit::{RateLimiter, backend::{SimpleInput, SimpleInputFunctionBuilder, SimpleOutput, memory::InMemoryBackend}};
-use actix_web::{Error, ResponseError, Result, Scope, dev::{ServiceFactory, ServiceRequest}, web::{self, Data}};
-use fred::prelude::Client;
+use std::{future::{Ready, ready}, sync::Arc, time::Duration};
+use actix_extensible_rate_limit::{RateLimiter, backend::{SimpleInput, SimpleOutput, memory::InMemoryBackend}};
+use actix_web::{Error, HttpMessage, HttpResponse, Result, Scope, body::{BoxBody, MessageBody}, dev::{ServiceFactory, ServiceRequest, ServiceResponse}, http::header, middleware::{Next, from_fn}, web::{self, Data}};
 use sea_orm::DatabaseConnection;
-use crate::api_v1::{repos::{CertRepo, RedisRepo}, types::errors::Errors};
+use security_headers::SecurityHeaders;
+use crate::{api_v1::{repos::{CertStore, ErasureRepo, KvStore, WebhookRepo}, services::{client_ip::{self, ClientIp}, locale}, types::errors::{Errors, PROBLEM_JSON_CONTENT_TYPE}}, configs::ApiSettings, utils::clock::Clock};
 
+mod bulk_issuance;
+mod cert_visibility;
+mod challenge;
 mod code_confirmation;
 mod create_cert;
 mod delete_cert;
 mod forgot_cert;
+mod gallery;
 mod get_cert;
+mod idempotency;
+mod jobs;
+mod kinds;
+mod personal_data;
+mod renew_cert;
+mod security_headers;
+mod session;
 mod stats;
+mod transfer_cert;
+mod verification;
+mod webhooks;
 
 const BODY_PAYLOAD_LIMIT: usize = 4096; // 4 Kb
 
@@ -19,29 +33,140 @@ async fn not_found() -> Result<(), Errors> {
         endpoints: Some(&[
             ("POST", "/api/v1/cert/forgot"),
             ("GET", "/api/v1/cert/{uuid}"),
+            ("PATCH", "/api/v1/cert/visibility"),
+            ("POST", "/api/v1/cert/transfer"),
+            ("POST", "/api/v1/cert/transfer/confirm"),
+            ("POST", "/api/v1/cert/renew"),
+            ("GET", "/api/v1/certs"),
+            ("POST", "/api/v1/certs/bulk"),
+            ("GET", "/api/v1/certs/search"),
+            ("GET", "/api/v1/certs/daily"),
             ("POST", "/api/v1/cert"),
             ("DELETE", "/api/v1/cert"),
             ("POST", "/api/v1/send_code"),
-            ("ANY", "/api/v1/stats")
+            ("GET", "/api/v1/challenge"),
+            ("GET", "/api/v1/kinds"),
+            ("ANY", "/api/v1/jobs"),
+            ("ANY", "/api/v1/me"),
+            ("ANY", "/api/v1/session"),
+            ("ANY", "/api/v1/stats"),
+            ("ANY", "/api/v1/webhooks")
         ])
     })
 }
 
-fn rate_limit_middleware() -> RateLimiter<InMemoryBackend, SimpleOutput, impl Fn(&ServiceRequest) -> Ready<Result<SimpleInput, actix_web::Error>> + 'static>  {
-    let rate_limit_backend = InMemoryBackend::builder().build();
-    let rate_limit_input = SimpleInputFunctionBuilder::new(Duration::from_secs(1), 3)
-        .real_ip_key()
-        .build();
+/// The request counters of the per-second limit
+/// The server builds them once and gives them to every API version, so a client has one budget for all of them
+#[derive(Clone)]
+pub struct RateLimitCounters(InMemoryBackend);
+
+impl Default for RateLimitCounters {
+    fn default() -> Self {
+        Self(InMemoryBackend::builder().build())
+    }
+}
+
+fn rate_limit_middleware(counters: RateLimitCounters) -> RateLimiter<InMemoryBackend, SimpleOutput, impl Fn(&ServiceRequest) -> Ready<Result<SimpleInput, actix_web::Error>> + 'static>  {
+    let rate_limit_backend = counters.0;
+    // Keyed by the client address resolved with the trusted proxies, not by the raw forwarded headers
+    let rate_limit_input = |request: &ServiceRequest| ready(
+        request.extensions()
+            .get::<ClientIp>()
+            .map(|client_ip| SimpleInput {
+                interval: Duration::from_secs(1),
+                max_requests: 3,
+                key: client_ip.0.to_string()
+            })
+            .ok_or_else(|| Error::from(Errors::InternalServer { what: "IP address" }))
+    );
     let rate_limit_middleware = RateLimiter::builder(rate_limit_backend.clone(), rate_limit_input)
         .add_headers()
         .request_denied_response(|_| {
-            Errors::RequestsRateLimit.error_response()
+            HttpResponse::from_error(Errors::RequestsRateLimit)
         })
         .build();
 
     rate_limit_middleware
 }
 
+/// Marks the requests of the scopes that always answer with the RFC 7807 errors
+#[derive(Clone, Copy)]
+struct ProblemJsonErrors;
+
+/// Makes the errors of the scope "application/problem+json" whatever the Accept header is
+async fn use_problem_json(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    request.extensions_mut().insert(ProblemJsonErrors);
+
+    next.call(request).await
+}
+
+/// Returns true if the client asked for the RFC 7807 errors with the Accept header or the scope always uses them
+fn prefers_problem_json(request: &ServiceRequest) -> bool {
+    if request.extensions().get::<ProblemJsonErrors>().is_some() {
+        return true;
+    }
+
+    request.headers()
+        .get_all(header::ACCEPT)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|media_type| media_type.split(';').next())
+        .any(|media_type| media_type.trim().eq_ignore_ascii_case(PROBLEM_JSON_CONTENT_TYPE))
+}
+
+/// Renders the API errors again in the language negotiated from the request
+/// The representation is the usual one or the RFC 7807 one if the client prefers it
+/// Keeps the headers of the original response, e.g. the rate limit ones
+async fn localize_errors(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let language = locale::negotiate_language(request.request());
+    let problem_json = prefers_problem_json(&request);
+    let instance = request.path().to_string();
+    let response = next.call(request).await?;
+
+    let localized_option = response.response()
+        .error()
+        .and_then(|error| error.as_error::<Errors>())
+        .map(|error| if problem_json {
+            error.problem_response(language, &instance)
+        } else {
+            error.localized_response(language)
+        });
+
+    match localized_option {
+        Some(mut localized) => {
+            let own_headers: Vec<_> = localized.headers().keys().cloned().collect();
+
+            for (name, value) in response.headers() {
+                if !own_headers.contains(name) {
+                    localized.headers_mut().append(name.clone(), value.clone());
+                }
+            }
+
+            Ok(response.into_response(localized))
+        },
+        None => Ok(response.map_into_boxed_body())
+    }
+}
+
+/// Resolves the client address once and stores it in the request extensions
+/// The controllers and the rate limit middleware receive it as `ClientIp`
+async fn resolve_client_ip(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if let Some(ip) = client_ip::resolve_client_ip(request.request()) {
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.call(request).await
+}
+
 fn payload_limit() -> web::PayloadConfig {
     web::PayloadConfig::default()
         .limit(BODY_PAYLOAD_LIMIT)
@@ -57,20 +182,74 @@ fn json_payload_limit() -> web::JsonConfig {
 
 pub fn api_v1_scope(
     database_connection: Arc<DatabaseConnection>,
-    redis_client: Arc<Client>
-) -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = actix_web::dev::ServiceResponse<actix_web::body::EitherBody<actix_web::body::BoxBody>>, Error = actix_web::Error, InitError = ()>> {
-    let api_scope = web::scope("/api/v1")
-        .wrap(rate_limit_middleware())
+    cert_store: Arc<dyn CertStore>,
+    kv_store: Arc<dyn KvStore>,
+    clock: Arc<dyn Clock>,
+    settings: Arc<ApiSettings>,
+    rate_limit_counters: RateLimitCounters
+) -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<BoxBody>, Error = actix_web::Error, InitError = ()>> {
+    api_scope("/api/v1", database_connection, cert_store, kv_store, clock, settings, rate_limit_counters)
+}
+
+/// The same endpoints as in /api/v1, but the errors are always in the RFC 7807 representation
+pub fn api_v2_scope(
+    database_connection: Arc<DatabaseConnection>,
+    cert_store: Arc<dyn CertStore>,
+    kv_store: Arc<dyn KvStore>,
+    clock: Arc<dyn Clock>,
+    settings: Arc<ApiSettings>,
+    rate_limit_counters: RateLimitCounters
+) -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error, InitError = ()>> {
+    api_scope("/api/v2", database_connection, cert_store, kv_store, clock, settings, rate_limit_counters)
+        .wrap(from_fn(use_problem_json))
+}
+
+fn api_scope(
+    path: &str,
+    database_connection: Arc<DatabaseConnection>,
+    cert_store: Arc<dyn CertStore>,
+    kv_store: Arc<dyn KvStore>,
+    clock: Arc<dyn Clock>,
+    settings: Arc<ApiSettings>,
+    rate_limit_counters: RateLimitCounters
+) -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<BoxBody>, Error = actix_web::Error, InitError = ()>> {
+    let api_scope = web::scope(path)
+        .wrap(rate_limit_middleware(rate_limit_counters))
+        .wrap(
+            SecurityHeaders::default()
+                .with_cors(settings.cors_allowed_origins.clone())
+                .without_cors_for(&["/certs/bulk", "/webhooks", "/jobs"])
+        )
+        .wrap(from_fn(resolve_client_ip))
+        .wrap(from_fn(localize_errors))
         .app_data(payload_limit())
         .app_data(json_payload_limit())
-        .app_data(Data::new(CertRepo::new(database_connection)))
-        .app_data(Data::new(RedisRepo::new(redis_client)))
+        .app_data(Data::from(cert_store))
+        .app_data(Data::new(WebhookRepo::new(database_connection.clone())))
+        .app_data(Data::new(ErasureRepo::new(database_connection)))
+        .app_data(Data::from(kv_store))
+        .app_data(Data::from(clock))
+        .app_data(Data::from(settings))
         .service(get_cert::get_cert_endpoint)
         .service(create_cert::create_cert_endpoint)
         .service(delete_cert::delete_cert_endpoint)
+        .service(cert_visibility::update_visibility_endpoint)
+        .service(transfer_cert::start_transfer_endpoint)
+        .service(transfer_cert::confirm_transfer_endpoint)
+        .service(renew_cert::renew_cert_endpoint)
+        .service(bulk_issuance::bulk_scope())
+        .service(gallery::list_certs_endpoint)
+        .service(gallery::daily_cert_endpoint)
+        .service(gallery::search_certs_endpoint)
         .service(forgot_cert::forgot_cert_endpoint)
         .service(code_confirmation::send_code_endpoint)
+        .service(challenge::challenge_endpoint)
+        .service(kinds::list_kinds_endpoint)
         .service(stats::stats_scope())
+        .service(webhooks::webhooks_scope())
+        .service(jobs::jobs_scope())
+        .service(personal_data::me_scope())
+        .service(session::session_scope())
         .default_service(web::route().to(not_found));
 
     api_scope
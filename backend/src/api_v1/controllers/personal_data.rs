@@ -0,0 +1,184 @@
+use actix_web::{web, Error, Scope};
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::session, 
+        repos::{
+            CertStore, 
+            ErasureRepo, 
+            KvStore, 
+            WebhookRepo
+        }, 
+        services::{
+            codes, 
+            email, 
+            personal_data
+        }, 
+        types::{
+            errors::Errors, 
+            requests::PersonalDataRequest, 
+            responses::success::{
+                CertEmailResponse, 
+                ErasureReceiptResponse
+            }
+        }
+    }, 
+    utils::{
+        clock::Clock, 
+        log_error::ResultLogger, 
+        uuid::get_uuid
+    }
+};
+
+async fn not_found() -> Result<(), Errors> {
+    Err(Errors::PageNotFound {
+        endpoints: Some(&[
+            ("GET", "/api/v1/me"),
+            ("PATCH", "/api/v1/me/visibility"),
+            ("DELETE", "/api/v1/me/cert"),
+            ("POST", "/api/v1/me/export"),
+            ("POST", "/api/v1/me/erase"),
+            ("GET", "/api/v1/me/erasure/{id}"),
+        ])
+    })
+}
+
+#[actix_web::post("/export")]
+pub async fn export_data_endpoint(
+    body: Result<web::Json<PersonalDataRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>, 
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<CertEmailResponse>, Errors> {
+    let place_name = "POST /api/v1/me/export";
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            // Verify and consume the code from request body
+            codes::verify_and_consume_code(
+                redis.as_ref(), 
+                &body.email, &body.token, &body.code, 
+                "export", clock.now()
+            ).await?;
+
+            // Collect everything stored about the email address
+            let bundle = personal_data::collect_personal_data(
+                redis.as_ref(), 
+                cert_repo.as_ref(), 
+                &body.email, clock.now()
+            )
+                .await
+                .log_with_place_on_error(place_name)
+                .map_err(|_| Errors::InternalServer { what: "personal data collection" })?;
+
+            let data = serde_json::to_string_pretty(&bundle)
+                .map_err(|_| Errors::InternalServer { what: "personal data serialization" })?;
+
+            // The data is sent only to the confirmed email address
+            email::send_data_export(
+                redis.as_ref(), &body.email, &data
+            )
+                .await
+                .map_err(|_| Errors::InternalServer { what: "broker" })?;
+
+            Ok(web::Json(
+                CertEmailResponse::new(body.email.clone())
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
+
+#[actix_web::post("/erase")]
+pub async fn erase_data_endpoint(
+    body: Result<web::Json<PersonalDataRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>,
+    erasure_repo: web::Data<ErasureRepo>, 
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<ErasureReceiptResponse>, Errors> {
+    let place_name = "POST /api/v1/me/erase";
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            // Verify and consume the code from request body
+            codes::verify_and_consume_code(
+                redis.as_ref(), 
+                &body.email, &body.token, &body.code, 
+                "erase", clock.now()
+            ).await?;
+
+            // Remove everything stored about the email address
+            let receipt = personal_data::erase_personal_data(
+                redis.as_ref(), 
+                cert_repo.as_ref(), 
+                webhook_repo.as_ref(), 
+                erasure_repo.as_ref(), 
+                &body.email, clock.now()
+            )
+                .await
+                .log_with_place_on_error(place_name)
+                .map_err(|_| Errors::InternalServer { what: "personal data erasure" })?;
+
+            Ok(web::Json(
+                ErasureReceiptResponse::new(&receipt)
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
+
+#[actix_web::get("/erasure/{id}")]
+pub async fn erasure_receipt_endpoint(
+    path: web::Path<(String,)>,
+    erasure_repo: web::Data<ErasureRepo>
+) -> Result<web::Json<ErasureReceiptResponse>, Errors> {
+    let uuid = match get_uuid(&path.0) {
+        Some(uuid) => uuid,
+        None => {
+            return Err(Errors::BadRequest { what_invalid: "receipt ID" })
+        }
+    };
+
+    // The receipt contains only the hash of the email address, so it can be public
+    let receipt = erasure_repo.find_receipt_by_id(uuid)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?
+        .ok_or(Errors::ResourceNotFound { what: "erasure receipt" })?;
+
+    Ok(web::Json(
+        ErasureReceiptResponse::new(&receipt)
+    ))
+}
+
+pub fn me_scope() -> Scope {
+    web::scope("/me")
+        .service(session::me_endpoint)
+        .service(session::session_visibility_endpoint)
+        .service(session::session_delete_cert_endpoint)
+        .service(export_data_endpoint)
+        .service(erase_data_endpoint)
+        .service(erasure_receipt_endpoint)
+        .default_service(web::route().to(not_found))
+}
@@ -0,0 +1,112 @@
+use actix_web::{Error, web};
+use chrono::Duration;
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::verification::find_bound_cert,
+        repos::{
+            CertStore,
+            KvStore,
+            WebhookRepo
+        },
+        services::{
+            cache,
+            cert_cache,
+            codes,
+            expiry,
+            webhooks::{
+                self,
+                WebhookEvent
+            }
+        },
+        types::{
+            errors::Errors,
+            requests::RenewCertRequest,
+            responses::success::CertificateResponse
+        }
+    },
+    configs::ApiSettings,
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger
+    }
+};
+
+/// Extends the validity of the certificate by the period of its kind, the serial number stays the same
+/// An expired certificate is renewed from the current time and becomes active again
+#[actix_web::post("/cert/renew")]
+pub async fn renew_cert_endpoint(
+    body: Result<web::Json<RenewCertRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertificateResponse>, Errors> {
+    let place_name = "POST /api/v1/cert/renew";
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            // Verify and consume the code from request body
+            let now = clock.now();
+            let cert_id = codes::verify_and_consume_code(
+                redis.as_ref(),
+                &body.email, &body.token, &body.code,
+                "renew", now
+            ).await?;
+
+            // Receive the certificate the code was requested for
+            let cert = find_bound_cert(cert_repo.as_ref(), cert_id, &body.email).await?;
+
+            let expires_at = expiry::get_renewed_expiration(&settings, &cert, now);
+            let update_count = cert_repo.update_cert_expiry(cert.id, cert.email.clone(), expires_at)
+                .await
+                .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+            if update_count == 0 {
+                return Err(Errors::ResourceNotFound { what: "certificate" });
+            }
+
+            // Stop serving the old expiration from the cache
+            let _ = cert_cache::invalidate_cert(redis.as_ref(), &cert.id).await;
+
+            // The expired certificate is counted again
+            if expiry::is_expired(cert.expires_at, now) {
+                let _ = redis.increase_by_one(
+                    cache::get_key("stats:users_count"),
+                    Duration::days(1)
+                ).await;
+            }
+
+            let certificate = CertificateResponse::new(
+                &cert.id,
+                cert.name,
+                cert.title,
+                cert.kind,
+                expires_at,
+                now
+            );
+
+            // Notify the webhook subscribers
+            let _ = webhooks::dispatch_event(
+                redis.as_ref(),
+                webhook_repo.as_ref(),
+                WebhookEvent::CertUpdated,
+                serde_json::to_value(&certificate).unwrap_or_default()
+            ).await;
+
+            Ok(web::Json(certificate))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
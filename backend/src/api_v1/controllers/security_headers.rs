@@ -0,0 +1,201 @@
+use std::{
+    future::{Ready, ready},
+    rc::Rc
+};
+use actix_web::{
+    Error, HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse, Transform, forward_ready},
+    http::{
+        Method,
+        header::{self, HeaderMap, HeaderName, HeaderValue}
+    }
+};
+use futures::future::LocalBoxFuture;
+
+const ALLOWED_METHODS: &str = "GET, POST, PATCH, DELETE";
+const ALLOWED_HEADERS: &str = "Accept, Accept-Language, Authorization, Content-Type, Idempotency-Key, If-None-Match";
+const EXPOSED_HEADERS: &str = "ETag, Idempotent-Replayed, Retry-After, X-RateLimit-Limit, X-RateLimit-Remaining, X-RateLimit-Reset";
+const PREFLIGHT_MAX_AGE_SECONDS: &str = "600";
+
+/// Marks the responses that already got the headers of a nested scope, so the outer policy doesn't replace them
+struct SecurityHeadersApplied;
+
+/// The headers that every response of the scope gets and the origins allowed to read the responses
+#[derive(Clone)]
+struct SecurityPolicy {
+    headers: Vec<(HeaderName, HeaderValue)>,
+    /// Empty if the scope is not available for the other sites
+    cors_origins: Vec<String>,
+    /// The nested scopes that are not available for the other sites, e.g. "/webhooks"
+    closed_paths: Vec<String>
+}
+
+impl SecurityPolicy {
+    fn is_allowed_origin(&self, origin: &str) -> bool {
+        let origin = origin.trim_end_matches('/');
+
+        self.cors_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+    }
+
+    fn insert_headers(&self, headers: &mut HeaderMap, origin: Option<&str>) {
+        for (name, value) in &self.headers {
+            headers.insert(name.clone(), value.clone());
+        }
+
+        if self.cors_origins.is_empty() {
+            return;
+        }
+
+        // The answer depends on the origin, so the caches mustn't share it between the sites
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+
+        if let Some(origin) = origin.filter(|origin| self.is_allowed_origin(origin))
+            && let Ok(value) = HeaderValue::from_str(origin) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+            headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSED_HEADERS));
+        }
+    }
+
+    fn is_closed_path(&self, path: &str) -> bool {
+        self.closed_paths
+            .iter()
+            .any(|closed| path.strip_prefix(closed.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+    }
+
+    /// Answers the CORS preflight request, the disallowed origins, methods and paths get 403
+    fn preflight_response(&self, origin: &str, requested_method: &str, path: &str) -> HttpResponse {
+        let is_allowed_method = ALLOWED_METHODS
+            .split(", ")
+            .any(|method| method.eq_ignore_ascii_case(requested_method.trim()));
+
+        let mut response = if self.is_allowed_origin(origin) && is_allowed_method && !self.is_closed_path(path) {
+            HttpResponse::NoContent()
+                .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, ALLOWED_METHODS))
+                .insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, ALLOWED_HEADERS))
+                .insert_header((header::ACCESS_CONTROL_MAX_AGE, PREFLIGHT_MAX_AGE_SECONDS))
+                .finish()
+        } else {
+            HttpResponse::Forbidden().finish()
+        };
+
+        // The forbidden answer doesn't let the origin read it
+        let origin = Some(origin).filter(|_| response.status().is_success());
+        self.insert_headers(response.headers_mut(), origin);
+        response.extensions_mut().insert(SecurityHeadersApplied);
+        response
+    }
+}
+
+/// Adds the security headers and the CORS headers to the responses and answers the CORS preflight requests
+/// The API serves only JSON, so the defaults forbid loading anything, framing and sniffing
+/// A nested scope can wrap itself with another policy, the nearest one to the endpoint is used
+pub struct SecurityHeaders {
+    policy: SecurityPolicy
+}
+
+impl Default for SecurityHeaders {
+    /// The default policy, the scope is not available for the other sites, e.g. the administrative endpoints
+    fn default() -> Self {
+        Self {
+            policy: SecurityPolicy {
+                headers: vec![
+                    (header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("default-src 'none'; frame-ancestors 'none'")),
+                    (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                    (header::REFERRER_POLICY, HeaderValue::from_static("no-referrer")),
+                    (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"))
+                ],
+                cors_origins: Vec::new(),
+                closed_paths: Vec::new()
+            }
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Lets the origins read the responses, e.g. the CORS_ALLOWED_ORIGINS of the settings
+    pub fn with_cors(mut self, origins: Vec<String>) -> Self {
+        self.policy.cors_origins = origins;
+        self
+    }
+
+    /// Keeps the nested scopes closed for the other sites, the paths are relative to the wrapped scope
+    /// The preflight is answered before the nested scopes are reached, so their own policy can't do it
+    pub fn without_cors_for(mut self, paths: &[&str]) -> Self {
+        self.policy.closed_paths = paths.iter().map(|path| path.to_string()).collect();
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SecurityHeaders
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = SecurityHeadersMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SecurityHeadersMiddleware {
+            service: Rc::new(service),
+            policy: Rc::new(self.policy.clone())
+        }))
+    }
+}
+
+pub struct SecurityHeadersMiddleware<S> {
+    service: Rc<S>,
+    policy: Rc<SecurityPolicy>
+}
+
+impl<S, B> Service<ServiceRequest> for SecurityHeadersMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let policy = self.policy.clone();
+
+        Box::pin(async move {
+            let origin = request.headers()
+                .get(header::ORIGIN)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let preflight_method = request.headers()
+                .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+                .and_then(|value| value.to_str().ok())
+                .filter(|_| request.method() == Method::OPTIONS)
+                .map(str::to_string);
+
+            // The preflight is answered here, so it doesn't reach the rate limiter and uses up the budget of the real request
+            if let (Some(origin), Some(method)) = (origin.as_deref(), preflight_method) {
+                let response = policy.preflight_response(origin, &method, request.match_info().unprocessed());
+                return Ok(request.into_response(response).map_into_right_body());
+            }
+
+            let response = service.call(request).await?;
+
+            if response.response().extensions().get::<SecurityHeadersApplied>().is_some() {
+                return Ok(response.map_into_left_body());
+            }
+
+            let mut response = response.map_into_left_body();
+            policy.insert_headers(response.headers_mut(), origin.as_deref());
+            response.response_mut().extensions_mut().insert(SecurityHeadersApplied);
+
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use actix_web::{Error, HttpRequest, Scope, web};
+use chrono::{DateTime, Duration, Utc};
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::{
+            cert_visibility::change_visibility,
+            delete_cert::remove_cert,
+            verification::find_bound_cert
+        },
+        repos::{
+            CertModel,
+            CertStore,
+            KvStore,
+            WebhookRepo
+        },
+        services::{
+            admin,
+            codes,
+            sessions::{
+                self,
+                SessionClaims
+            }
+        },
+        types::{
+            errors::Errors,
+            requests::{
+                CreateSessionRequest,
+                SessionVisibilityRequest
+            },
+            responses::success::{
+                CertIdResponse,
+                CertVisibilityResponse,
+                MeResponse,
+                SessionResponse
+            }
+        }
+    },
+    configs::{
+        self,
+        ApiSettings
+    },
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger
+    }
+};
+
+async fn not_found() -> Result<(), Errors> {
+    Err(Errors::PageNotFound {
+        endpoints: Some(&[
+            ("POST", "/api/v1/session"),
+            ("DELETE", "/api/v1/session"),
+        ])
+    })
+}
+
+/// Checks the session from the "Authorization: Bearer <session>" header
+/// Returns the claims of a signed session that hasn't expired or been revoked
+pub async fn authorize_session(
+    request: &HttpRequest,
+    redis: &dyn KvStore,
+    settings: &ApiSettings,
+    now: DateTime<Utc>
+) -> Result<SessionClaims, Errors> {
+    let Some(secret) = &settings.session_secret else {
+        return Err(Errors::Unauthorized);
+    };
+
+    let token = admin::get_bearer_token(request).ok_or(Errors::Unauthorized)?;
+    let claims = sessions::decode_session(secret, token, now)
+        .map_err(|_| Errors::Unauthorized)?;
+
+    let is_revoked = sessions::is_revoked(redis, &claims)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+    if is_revoked {
+        return Err(Errors::Unauthorized);
+    }
+
+    Ok(claims)
+}
+
+/// Receives the certificate that the session manages
+/// The session stops working once the certificate belongs to another email address
+async fn find_session_cert(cert_repo: &dyn CertStore, claims: &SessionClaims) -> Result<CertModel, Errors> {
+    let cert_id = claims.cert_id().ok_or(Errors::Unauthorized)?;
+
+    cert_repo.find_cert_by_id(cert_id)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?
+        .filter(|cert| cert.email == claims.email)
+        .ok_or(Errors::ResourceNotFound { what: "certificate" })
+}
+
+#[actix_web::post("")]
+pub async fn create_session_endpoint(
+    body: Result<web::Json<CreateSessionRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<SessionResponse>, Errors> {
+    let place_name = "POST /api/v1/session";
+
+    // The sessions are disabled without the signing key
+    let Some(secret) = &settings.session_secret else {
+        return Err(Errors::PageNotFound { endpoints: None });
+    };
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            // Verify and consume the code from request body
+            let now = clock.now();
+            let cert_id = codes::verify_and_consume_code(
+                redis.as_ref(),
+                &body.email, &body.token, &body.code,
+                "session", now
+            ).await?;
+
+            // Receive the certificate the code was requested for
+            let cert = find_bound_cert(cert_repo.as_ref(), cert_id, &body.email).await?;
+
+            // Sign the session that manages only this certificate
+            let lifetime = Duration::from_std(configs::get_session_lifetime())
+                .map_err(|_| Errors::InternalServer { what: "session" })?;
+            let (session, claims) = sessions::issue_session(secret, &cert.id, &cert.email, now, lifetime)
+                .log_with_place_on_error(place_name)
+                .map_err(|_| Errors::InternalServer { what: "session" })?;
+
+            Ok(web::Json(
+                SessionResponse::new(session, &cert.id, claims.exp as u64)
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
+
+#[actix_web::delete("")]
+pub async fn revoke_session_endpoint(
+    request: HttpRequest,
+    redis: web::Data<dyn KvStore>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertIdResponse>, Errors> {
+    let now = clock.now();
+    let claims = authorize_session(&request, redis.as_ref(), &settings, now).await?;
+    let cert_id = claims.cert_id().ok_or(Errors::Unauthorized)?;
+
+    sessions::revoke_session(redis.as_ref(), &claims, now)
+        .await
+        .log_with_place_on_error("DELETE /api/v1/session")
+        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+    Ok(web::Json(
+        CertIdResponse::new(&cert_id)
+    ))
+}
+
+#[actix_web::get("")]
+pub async fn me_endpoint(
+    request: HttpRequest,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<MeResponse>, Errors> {
+    let claims = authorize_session(&request, redis.as_ref(), &settings, clock.now()).await?;
+    let cert = find_session_cert(cert_repo.as_ref(), &claims).await?;
+
+    Ok(web::Json(
+        MeResponse::new(&cert.id, cert.email, cert.name, cert.title, cert.is_public, claims.exp as u64)
+    ))
+}
+
+#[actix_web::patch("/visibility")]
+pub async fn session_visibility_endpoint(
+    request: HttpRequest,
+    body: Result<web::Json<SessionVisibilityRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertVisibilityResponse>, Errors> {
+    let place_name = "PATCH /api/v1/me/visibility";
+
+    let now = clock.now();
+    let claims = authorize_session(&request, redis.as_ref(), &settings, now).await?;
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body) => {
+            let cert = find_session_cert(cert_repo.as_ref(), &claims).await?;
+            let cert_id = cert.id;
+
+            change_visibility(redis.as_ref(), cert_repo.as_ref(), webhook_repo.as_ref(), cert, body.public, now).await?;
+
+            Ok(web::Json(
+                CertVisibilityResponse::new(&cert_id, body.public)
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
+
+#[actix_web::delete("/cert")]
+pub async fn session_delete_cert_endpoint(
+    request: HttpRequest,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertIdResponse>, Errors> {
+    let now = clock.now();
+    let claims = authorize_session(&request, redis.as_ref(), &settings, now).await?;
+    let cert = find_session_cert(cert_repo.as_ref(), &claims).await?;
+
+    if !remove_cert(redis.as_ref(), cert_repo.as_ref(), webhook_repo.as_ref(), &cert, now).await? {
+        return Err(Errors::ResourceNotFound { what: "certificate" });
+    }
+
+    // There is nothing left to manage
+    let _ = sessions::revoke_session(redis.as_ref(), &claims, now).await;
+
+    Ok(web::Json(
+        CertIdResponse::new(&cert.id)
+    ))
+}
+
+pub fn session_scope() -> Scope {
+    web::scope("/session")
+        .service(create_session_endpoint)
+        .service(revoke_session_endpoint)
+        .default_service(web::route().to(not_found))
+}
@@ -1,11 +1,27 @@
-use chrono::Duration;
-use actix_web::{web, Scope};
-use crate::api_v1::{
-    repos::{CertRepo, RedisRepo}, 
-    services::cache, 
-    types::{
-        errors::Errors, 
-        responses::success::StatsUserCountResponse
+use chrono::{Duration, NaiveDate};
+use actix_web::{web, Error, Scope};
+use crate::{
+    api_v1::{
+        repos::{CertStore, KvStore}, 
+        services::{
+            cache, 
+            timeseries::{
+                self, 
+                Interval
+            }
+        }, 
+        types::{
+            errors::Errors, 
+            requests::StatsTimeseriesQuery, 
+            responses::success::{
+                StatsTimeseriesResponse, 
+                StatsUserCountResponse
+            }
+        }
+    }, 
+    utils::{
+        clock::Clock, 
+        log_error::ResultLogger
     }
 };
 
@@ -13,14 +29,16 @@ async fn not_found() -> Result<(), Errors> {
     Err(Errors::PageNotFound { 
         endpoints: Some(&[
             ("GET", "/api/v1/stats/users_count"),
+            ("GET", "/api/v1/stats/timeseries"),
         ])
     })
 }
 
 #[actix_web::get("/users_count")]
 pub async fn users_count_endpoint(
-    cert_repo: web::Data<CertRepo>,
-    redis: web::Data<RedisRepo>
+    cert_repo: web::Data<dyn CertStore>,
+    redis: web::Data<dyn KvStore>,
+    clock: web::Data<dyn Clock>
 ) -> Result<web::Json<StatsUserCountResponse>, Errors> {
     // Receive a cached users count
     let cache_key = "stats:users_count";
@@ -32,8 +50,8 @@ pub async fn users_count_endpoint(
         ))
     }
 
-    // Receive the users count from the data base if not cached
-    let count = cert_repo.count_all()
+    // Receive the users count from the data base if not cached, the expired certificates aren't counted
+    let count = cert_repo.count_active(clock.now())
         .await
         .map_err(|_| Errors::InternalServer { what: "DB" })?;
 
@@ -50,9 +68,80 @@ pub async fn users_count_endpoint(
     ))
 }
 
+/// Parses an optional date in the YYYY-MM-DD format
+fn parse_date(value: &Option<String>) -> Result<Option<NaiveDate>, Errors> {
+    match value {
+        Some(date) => NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| Errors::BadRequest { what_invalid: "date (expected YYYY-MM-DD)" }),
+        None => Ok(None)
+    }
+}
+
+#[actix_web::get("/timeseries")]
+pub async fn timeseries_endpoint(
+    query: Result<web::Query<StatsTimeseriesQuery>, Error>,
+    cert_repo: web::Data<dyn CertStore>,
+    redis: web::Data<dyn KvStore>, 
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<StatsTimeseriesResponse>, Errors> {
+    let place_name = "GET /api/v1/stats/timeseries";
+
+    let query = query
+        .log_with_place_on_error(place_name)
+        .map_err(|_| Errors::BadRequest { what_invalid: "query" })?;
+
+    // Parse and validate the requested range
+    let interval = match &query.interval {
+        Some(interval) => Interval::parse(interval.trim())
+            .ok_or(Errors::BadRequest { what_invalid: "interval (expected day, week or month)" })?,
+        None => Interval::Day
+    };
+
+    let to = parse_date(&query.to)?
+        .unwrap_or(clock.now().date_naive());
+    let from = parse_date(&query.from)?
+        .unwrap_or(interval.go_back(to, 29));
+
+    if from > to {
+        return Err(Errors::BadRequest { what_invalid: "range (from is later than to)" });
+    }
+
+    let bucket_starts = timeseries::get_bucket_starts(interval, from, to);
+
+    if bucket_starts.len() > timeseries::MAX_BUCKETS {
+        return Err(Errors::BadRequest { what_invalid: "range (too many buckets)" });
+    }
+
+    // Receive the amount of certificates before the first bucket
+    let total_at_start = cert_repo.count_existing_at(timeseries::date_to_utc(bucket_starts[0]))
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    // Receive the created and deleted counts per bucket
+    let buckets = timeseries::get_buckets(
+        redis.as_ref(), 
+        cert_repo.as_ref(), 
+        interval, &bucket_starts, clock.now()
+    )
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    Ok(web::Json(
+        StatsTimeseriesResponse::new(
+            interval.as_str(), 
+            from.to_string(), 
+            to.to_string(), 
+            total_at_start, 
+            buckets
+        )
+    ))
+}
+
 pub fn stats_scope() -> Scope {
     let stats_scope = web::scope("/stats")
         .service(users_count_endpoint)
+        .service(timeseries_endpoint)
         .default_service(web::route().to(not_found));
 
     stats_scope
@@ -0,0 +1,273 @@
+use actix_web::{Error, web};
+use chrono::Duration;
+use short_uuid::ShortUuid;
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::verification::find_bound_cert,
+        repos::{
+            CertStore,
+            CreationError,
+            KvStore,
+            WebhookRepo
+        },
+        services::{
+            cert_cache,
+            codes,
+            email,
+            rate_limits,
+            transfers,
+            webhooks::{
+                self,
+                WebhookEvent
+            }
+        },
+        types::{
+            errors::Errors,
+            redis::PendingTransfer,
+            requests::{
+                ConfirmTransferRequest,
+                TransferCertRequest
+            },
+            responses::success::{
+                CertificateResponse,
+                CodeSentResponse
+            }
+        }
+    },
+    configs::ApiSettings,
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger
+    }
+};
+
+/// Returns an error if the email address can't receive one more certificate
+async fn check_cert_quota(cert_repo: &dyn CertStore, settings: &ApiSettings, email: &str) -> Result<(), Errors> {
+    let certs_count = cert_repo.count_by_email(email.to_string())
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    let limit = settings.certs_per_email_limit;
+    if certs_count >= limit {
+        return Err(Errors::CertQuotaExceeded { limit });
+    }
+
+    Ok(())
+}
+
+/// The first side of the transfer: the current holder confirms it with the code sent by the "transfer" purpose
+/// The new holder receives their own code, the certificate moves only after it's confirmed
+#[actix_web::post("/cert/transfer")]
+pub async fn start_transfer_endpoint(
+    body: Result<web::Json<TransferCertRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CodeSentResponse>, Errors> {
+    let place_name = "POST /api/v1/cert/transfer";
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            if body.new_email.eq_ignore_ascii_case(&body.email) {
+                return Err(Errors::BadRequest { what_invalid: "new email field value" });
+            }
+
+            // Verify the code of the current holder, it's consumed after the checks of the new address
+            let now = clock.now();
+            let cert_id = codes::verify_code(
+                redis.as_ref(),
+                &body.email, &body.token, &body.code,
+                "transfer", now
+            ).await?;
+
+            // Receive the certificate the code was requested for
+            let cert = find_bound_cert(cert_repo.as_ref(), cert_id, &body.email).await?;
+
+            check_cert_quota(cert_repo.as_ref(), &settings, &body.new_email).await?;
+
+            // Check rate limit by the new email address, the letters can't be sent to it too often
+            if !rate_limits::check_rate_counter(
+                redis.as_ref(),
+                "code", &body.new_email,
+                1
+            ).await {
+                let ttl = rate_limits::get_rate_time(
+                    redis.as_ref(),
+                    "code", &body.new_email
+                )
+                    .await
+                    .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+                return Err(Errors::EmailRateLimit {
+                    how_much: ttl.0.num_seconds() as u32,
+                    timestamp: ttl.1.timestamp() as u64
+                })
+            }
+
+            codes::consume_code(redis.as_ref(), &body.email, "transfer").await?;
+
+            // Remember the transfer until the new holder confirms it
+            transfers::save_pending_transfer(
+                redis.as_ref(),
+                &cert.id,
+                &PendingTransfer {
+                    old_email: body.email.clone(),
+                    new_email: body.new_email.clone()
+                }
+            )
+                .await
+                .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+            // Update rate limit by the new email address
+            let _ = rate_limits::increate_rate_counter(
+                redis.as_ref(),
+                "code", &body.new_email,
+                Duration::minutes(3)
+            ).await;
+
+            // The code of the new holder is bound to the same certificate
+            let email_code = codes::generate_email_code();
+            let email_token = codes::generate_code_token();
+
+            let expire_time = codes::save_code_in_storage(
+                redis.as_ref(),
+                &body.new_email,
+                "accept_transfer",
+                Some(&cert.id),
+                &email_code, &email_token,
+                now
+            )
+                .await
+                .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+            email::send_manage_code(
+                redis.as_ref(), &body.new_email, &email_code,
+                "отримання сертифіката з іншої пошти"
+            )
+                .await
+                .map_err(|_| Errors::InternalServer { what: "broker" })?;
+
+            Ok(web::Json(
+                CodeSentResponse::new(body.new_email, email_token, expire_time.timestamp() as u64)
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
+
+/// The second side of the transfer: the new holder confirms it with their code
+/// The certificate keeps its serial number and both holders are notified
+#[actix_web::post("/cert/transfer/confirm")]
+pub async fn confirm_transfer_endpoint(
+    body: Result<web::Json<ConfirmTransferRequest>, Error>,
+    redis: web::Data<dyn KvStore>,
+    cert_repo: web::Data<dyn CertStore>,
+    webhook_repo: web::Data<WebhookRepo>,
+    clock: web::Data<dyn Clock>,
+    settings: web::Data<ApiSettings>
+) -> Result<web::Json<CertificateResponse>, Errors> {
+    let place_name = "POST /api/v1/cert/transfer/confirm";
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            // Verify and consume the code of the new holder
+            let now = clock.now();
+            let cert_id = codes::verify_and_consume_code(
+                redis.as_ref(),
+                &body.email, &body.token, &body.code,
+                "accept_transfer", now
+            ).await?;
+
+            let cert_id = cert_id.ok_or(Errors::ResourceNotFound { what: "transfer" })?;
+
+            // The transfer could be replaced by a newer one to another address
+            let transfer = transfers::get_pending_transfer(redis.as_ref(), &cert_id)
+                .await
+                .map_err(|_| Errors::InternalServer { what: "cache storage" })?
+                .filter(|transfer| transfer.new_email == body.email)
+                .ok_or(Errors::ResourceNotFound { what: "transfer" })?;
+
+            let cert = find_bound_cert(cert_repo.as_ref(), Some(cert_id), &transfer.old_email).await?;
+
+            // The address could receive other certificates while the transfer waited
+            let limit = settings.certs_per_email_limit;
+            let update_count = match cert_repo.update_cert_email(cert.id, transfer.old_email.clone(), body.email.clone(), limit).await {
+                Ok(update_count) => update_count,
+                Err(CreationError::QuotaExceeded) => {
+                    return Err(Errors::CertQuotaExceeded { limit });
+                },
+                Err(_) => {
+                    return Err(Errors::InternalServer { what: "DB" });
+                }
+            };
+
+            if update_count == 0 {
+                return Err(Errors::ResourceNotFound { what: "certificate" });
+            }
+
+            let _ = transfers::remove_pending_transfer(redis.as_ref(), &cert.id).await;
+
+            // The modification time of the cached copy is outdated
+            let _ = cert_cache::invalidate_cert(redis.as_ref(), &cert.id).await;
+
+            let certificate = CertificateResponse::new(
+                &cert.id,
+                cert.name.clone(),
+                cert.title.clone(),
+                cert.kind.clone(),
+                cert.expires_at,
+                now
+            );
+
+            // Notify the webhook subscribers, the email addresses aren't shared with them
+            let _ = webhooks::dispatch_event(
+                redis.as_ref(),
+                webhook_repo.as_ref(),
+                WebhookEvent::CertUpdated,
+                serde_json::to_value(&certificate).unwrap_or_default()
+            ).await;
+
+            // Tell both holders, the previous one learns about the transfer even if it wasn't expected
+            let serial = ShortUuid::from_uuid(&cert.id).to_string();
+
+            let _ = email::send_transfer_out(
+                redis.as_ref(),
+                &transfer.old_email, &serial, &body.email
+            )
+                .await
+                .log_with_place_on_error(place_name);
+
+            let _ = email::send_transfer_in(
+                redis.as_ref(),
+                &body.email, &serial, &cert.name, &cert.title
+            )
+                .await
+                .log_with_place_on_error(place_name);
+
+            Ok(web::Json(certificate))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
@@ -0,0 +1,25 @@
+use uuid::Uuid;
+use crate::api_v1::{
+    repos::{
+        CertModel,
+        CertStore
+    },
+    types::errors::Errors
+};
+
+/// Returns the certificate the code is bound to if it still belongs to the email address
+/// The codes sent before the binding have no certificate ID, they have to be requested again
+pub async fn find_bound_cert(
+    cert_repo: &dyn CertStore,
+    cert_id: Option<Uuid>, email: &str
+) -> Result<CertModel, Errors> {
+    let Some(cert_id) = cert_id else {
+        return Err(Errors::ResourceNotFound { what: "certificate" });
+    };
+
+    cert_repo.find_cert_by_id(cert_id)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?
+        .filter(|cert| cert.email == email)
+        .ok_or(Errors::ResourceNotFound { what: "certificate" })
+}
@@ -0,0 +1,228 @@
+use actix_web::{web, Error, HttpRequest, Scope, body::MessageBody, dev::{ServiceFactory, ServiceRequest, ServiceResponse}};
+use uuid::Uuid;
+use validator::Validate;
+use crate::{
+    api_v1::{
+        controllers::security_headers::SecurityHeaders,
+        repos::{
+            KvStore,
+            WebhookModel,
+            WebhookRepo
+        },
+        services::{
+            admin,
+            webhooks::{
+                self,
+                WebhookEvent
+            }
+        },
+        types::{
+            errors::Errors,
+            requests::CreateWebhookRequest,
+            responses::success::{
+                WebhookDeliveriesResponse,
+                WebhookDeliveryResponse,
+                WebhookListResponse,
+                WebhookResponse
+            }
+        }
+    },
+    utils::{
+        clock::Clock,
+        log_error::ResultLogger,
+        uuid::get_uuid
+    }
+};
+
+const DELIVERIES_LOG_LIMIT: u64 = 50;
+
+async fn not_found() -> Result<(), Errors> {
+    Err(Errors::PageNotFound {
+        endpoints: Some(&[
+            ("GET", "/api/v1/webhooks"),
+            ("POST", "/api/v1/webhooks"),
+            ("DELETE", "/api/v1/webhooks/{id}"),
+            ("GET", "/api/v1/webhooks/{id}/deliveries"),
+            ("POST", "/api/v1/webhooks/{id}/test"),
+        ])
+    })
+}
+
+/// Receives the webhook subscription by the ID from the path
+async fn find_webhook(
+    webhook_repo: &WebhookRepo,
+    id: &str
+) -> Result<WebhookModel, Errors> {
+    let uuid = match get_uuid(id) {
+        Some(uuid) => uuid,
+        None => {
+            return Err(Errors::BadRequest { what_invalid: "webhook ID" })
+        }
+    };
+
+    let find_option = webhook_repo.find_webhook_by_id(uuid)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    find_option.ok_or(Errors::ResourceNotFound { what: "webhook" })
+}
+
+#[actix_web::post("")]
+pub async fn create_webhook_endpoint(
+    request: HttpRequest,
+    body: Result<web::Json<CreateWebhookRequest>, Error>,
+    webhook_repo: web::Data<WebhookRepo>,
+    clock: web::Data<dyn Clock>
+) -> Result<web::Json<WebhookResponse>, Errors> {
+    let place_name = "POST /api/v1/webhooks";
+
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    match body.log_with_place_on_error(place_name) {
+        Ok(body_unclear) => {
+            // Clean and validate the request body
+            let body = body_unclear.trim();
+
+            if body
+                .validate()
+                .log_with_place_on_error(place_name)
+                .is_err() {
+                return Err(Errors::BadRequest { what_invalid: "field values" });
+            }
+
+            let mut events = body.events.clone();
+            events.sort();
+            events.dedup();
+
+            let webhook = WebhookModel {
+                id: Uuid::new_v4(),
+                url: body.url.clone(),
+                secret: webhooks::generate_webhook_secret(),
+                events,
+                created_at: clock.now()
+            };
+
+            webhook_repo.create_webhook(webhook.clone())
+                .await
+                .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+            // The secret is shown only once
+            Ok(web::Json(
+                WebhookResponse::with_secret(&webhook)
+            ))
+        },
+        Err(_) => Err(Errors::BadRequest { what_invalid: "body" })
+    }
+}
+
+#[actix_web::get("")]
+pub async fn list_webhooks_endpoint(
+    request: HttpRequest,
+    webhook_repo: web::Data<WebhookRepo>
+) -> Result<web::Json<WebhookListResponse>, Errors> {
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    let webhooks = webhook_repo.find_all_webhooks()
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    Ok(web::Json(
+        WebhookListResponse::new(
+            webhooks.iter().map(WebhookResponse::new).collect()
+        )
+    ))
+}
+
+#[actix_web::delete("/{id}")]
+pub async fn delete_webhook_endpoint(
+    request: HttpRequest,
+    path: web::Path<(String,)>,
+    webhook_repo: web::Data<WebhookRepo>
+) -> Result<web::Json<WebhookResponse>, Errors> {
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    let webhook = find_webhook(&webhook_repo, &path.0).await?;
+
+    let deletion_count = webhook_repo.remove_webhook_by_id(webhook.id)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    if deletion_count == 0 {
+        Err(Errors::ResourceNotFound { what: "webhook" })
+    } else {
+        // Return the removed webhook
+        Ok(web::Json(
+            WebhookResponse::new(&webhook)
+        ))
+    }
+}
+
+#[actix_web::get("/{id}/deliveries")]
+pub async fn webhook_deliveries_endpoint(
+    request: HttpRequest,
+    path: web::Path<(String,)>,
+    webhook_repo: web::Data<WebhookRepo>
+) -> Result<web::Json<WebhookDeliveriesResponse>, Errors> {
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    let webhook = find_webhook(&webhook_repo, &path.0).await?;
+
+    let deliveries = webhook_repo.find_deliveries_by_webhook(webhook.id, DELIVERIES_LOG_LIMIT)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "DB" })?;
+
+    Ok(web::Json(
+        WebhookDeliveriesResponse::new(
+            deliveries.iter().map(WebhookDeliveryResponse::new).collect()
+        )
+    ))
+}
+
+#[actix_web::post("/{id}/test")]
+pub async fn test_webhook_endpoint(
+    request: HttpRequest,
+    path: web::Path<(String,)>,
+    redis: web::Data<dyn KvStore>,
+    webhook_repo: web::Data<WebhookRepo>
+) -> Result<web::Json<WebhookDeliveryResponse>, Errors> {
+    if !admin::is_admin_request(&request) {
+        return Err(Errors::Unauthorized);
+    }
+
+    let webhook = find_webhook(&webhook_repo, &path.0).await?;
+
+    // Fire the test event to this webhook only
+    let delivery = webhooks::dispatch_to_webhook(
+        redis.as_ref(),
+        webhook_repo.as_ref(),
+        &webhook,
+        &WebhookEvent::Test,
+        serde_json::json!({ "webhook_id": webhook.id.to_string() })
+    )
+        .await
+        .map_err(|_| Errors::InternalServer { what: "webhook delivery" })?;
+
+    Ok(web::Json(
+        WebhookDeliveryResponse::new(&delivery)
+    ))
+}
+
+pub fn webhooks_scope() -> Scope<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = Error, InitError = ()>> {
+    web::scope("/webhooks")
+        // The administrative endpoints are not for the browsers of the other sites
+        .wrap(SecurityHeaders::default())
+        .service(create_webhook_endpoint)
+        .service(list_webhooks_endpoint)
+        .service(delete_webhook_endpoint)
+        .service(webhook_deliveries_endpoint)
+        .service(test_webhook_endpoint)
+        .default_service(web::route().to(not_found))
+}
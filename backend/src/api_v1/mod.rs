@@ -1,13 +1,87 @@
-use sea_orm::schema::SchemaBuilder;
+use std::sync::Arc;
+use fred::prelude::Client;
+use sea_orm::{
+    ConnectionTrait,
+    DatabaseConnection,
+    DbBackend,
+    schema::SchemaBuilder
+};
+use crate::utils::clock::Clock;
 
 mod controllers;
-mod models;
-mod types;
-mod repos;
-mod services;
+pub mod models;
+pub mod types;
+pub mod repos;
+pub mod services;
 
-pub use controllers::api_v1_scope;
+pub use controllers::{RateLimitCounters, api_v1_scope, api_v2_scope};
+pub use services::admin::is_admin_request;
 
-pub fn register_cert_in_db_schema(schema_builder: SchemaBuilder) -> SchemaBuilder {
-    schema_builder.register(models::cert::Entity)
+pub fn register_models_in_db_schema(schema_builder: SchemaBuilder) -> SchemaBuilder {
+    schema_builder
+        .register(models::cert::Entity)
+        .register(models::cert_deletion::Entity)
+        .register(models::cert_deletion_count::Entity)
+        .register(models::erasure_receipt::Entity)
+        .register(models::webhook::Entity)
+        .register(models::webhook_delivery::Entity)
+}
+
+/// Drops the unique email constraint of the certificates tables created before the quota
+/// The sync only adds the missing columns and indexes, so the old constraint is dropped separately
+pub async fn drop_unique_email_constraint(database_connection: &DatabaseConnection) -> anyhow::Result<()> {
+    if database_connection.get_database_backend() == DbBackend::Postgres {
+        database_connection.execute_unprepared(models::cert::POSTGRES_DROP_UNIQUE_EMAIL).await?;
+    }
+
+    Ok(())
+}
+
+/// Creates the indexes that can't be registered in the schema builder
+pub async fn create_extra_db_indexes(database_connection: &DatabaseConnection) -> anyhow::Result<()> {
+    for statement in models::cert::EXTRA_INDEXES {
+        database_connection.execute_unprepared(statement).await?;
+    }
+
+    if database_connection.get_database_backend() == DbBackend::Postgres {
+        for statement in models::cert::POSTGRES_EXTRA_INDEXES {
+            database_connection.execute_unprepared(statement).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Starts the background workers that live as long as the server
+pub fn spawn_background_workers(
+    database_connection: Arc<DatabaseConnection>,
+    cert_store: Arc<dyn repos::CertStore>,
+    kv_store: Arc<dyn repos::KvStore>,
+    clock: Arc<dyn Clock>
+) {
+    actix_web::rt::spawn(services::webhooks::run_delivery_worker(
+        kv_store.clone(),
+        repos::WebhookRepo::new(database_connection)
+    ));
+    actix_web::rt::spawn(services::scheduler::run_scheduler(
+        kv_store,
+        cert_store,
+        clock
+    ));
+}
+
+/// Returns the amount of jobs waiting in every background queue
+pub async fn get_queue_depths(redis_client: Arc<Client>) -> anyhow::Result<Vec<(&'static str, u64)>> {
+    let redis: &dyn repos::KvStore = &repos::RedisRepo::new(redis_client);
+
+    Ok(vec![
+        (
+            services::email::EMAIL_JOBS_KEY,
+            redis.list_len(services::email::EMAIL_JOBS_KEY.to_string()).await?
+        ),
+        (
+            services::webhooks::WEBHOOK_JOBS_KEY,
+            redis.sorted_set_len(services::webhooks::WEBHOOK_JOBS_KEY.to_string()).await?
+        )
+    ])
 }
@@ -6,12 +6,45 @@ use sea_orm::{Set, entity::prelude::*};
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
-    #[sea_orm(unique)]
+    #[sea_orm(indexed)]
     pub email: String,
     pub name: String,
     pub title: String,
+    #[sea_orm(indexed, default_value = "classic")]
+    pub kind: String,
+    #[sea_orm(indexed, default_expr = "Expr::current_timestamp()")]
+    pub created_at: DateTimeUtc,
+    #[sea_orm(default_value = false)]
+    pub is_public: bool,
+    #[sea_orm(nullable)]
+    pub updated_at: Option<DateTimeUtc>,
+    /// The certificates issued without a validity period never expire
+    #[sea_orm(indexed, nullable)]
+    pub expires_at: Option<DateTimeUtc>,
 }
 
+/// The text of the certificate that the search works over
+/// The index expressions must be the same, otherwise the indexes aren't used
+pub const SEARCH_DOCUMENT: &str = "lower(name || ' ' || title)";
+
+/// Partial indexes that keep the public gallery and the search fast on large tables
+/// The schema builder can't describe them, so they are created separately
+pub const EXTRA_INDEXES: &[&str] = &[
+    "CREATE INDEX IF NOT EXISTS idx_certs_public_newest ON certs (created_at DESC, id DESC) WHERE is_public",
+    "CREATE INDEX IF NOT EXISTS idx_certs_public_id ON certs (id) WHERE is_public",
+];
+
+/// The search indexes need the PostgreSQL extensions, SQLite searches without them
+/// The trigram index matches the inflected Ukrainian words, the 'simple' full text one matches the exact words
+pub const POSTGRES_EXTRA_INDEXES: &[&str] = &[
+    "CREATE EXTENSION IF NOT EXISTS pg_trgm",
+    "CREATE INDEX IF NOT EXISTS idx_certs_public_search_trgm ON certs USING gin ((lower(name || ' ' || title)) gin_trgm_ops) WHERE is_public",
+    "CREATE INDEX IF NOT EXISTS idx_certs_public_search_fts ON certs USING gin (to_tsvector('simple', lower(name || ' ' || title))) WHERE is_public",
+];
+
+/// The email was unique before the quota, the sync doesn't drop the old constraint
+pub const POSTGRES_DROP_UNIQUE_EMAIL: &str = "ALTER TABLE certs DROP CONSTRAINT IF EXISTS certs_email_key";
+
 impl ActiveModelBehavior for ActiveModel {
     fn new() -> Self {
         Self {
@@ -0,0 +1,16 @@
+use sea_orm::entity::prelude::*;
+
+/// A record left after a certificate removal without any personal data
+/// Used to compute the statistics of deletions
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "cert_deletions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub created_at: DateTimeUtc,
+    #[sea_orm(indexed)]
+    pub deleted_at: DateTimeUtc,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
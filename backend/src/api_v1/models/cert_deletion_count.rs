@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+
+/// The deletion records rolled up by day when the scheduler purges them
+/// Keeps the statistics of deletions without the IDs of the removed certificates
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "cert_deletion_counts")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    /// The start of the day the certificates were created
+    pub created_at: DateTimeUtc,
+    /// The start of the day the certificates were deleted
+    #[sea_orm(indexed)]
+    pub deleted_at: DateTimeUtc,
+    pub deletions: i64,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,27 @@
+use sea_orm::{Set, entity::prelude::*};
+
+/// An auditable record of the personal data erasure
+/// Stores only a hash of the email address, so the receipt itself holds no personal data
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "erasure_receipts")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    #[sea_orm(indexed)]
+    pub email_hash: String,
+    pub certificates_removed: i32,
+    pub cache_keys_removed: i32,
+    pub email_jobs_removed: i32,
+    pub webhook_deliveries_removed: i32,
+    pub erased_at: DateTimeUtc,
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: Set(Uuid::new_v4()),
+            ..ActiveModelTrait::default()
+        }
+    }
+}
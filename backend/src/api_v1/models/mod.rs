@@ -1 +1,6 @@
 pub mod cert;
+pub mod cert_deletion;
+pub mod cert_deletion_count;
+pub mod erasure_receipt;
+pub mod webhook;
+pub mod webhook_delivery;
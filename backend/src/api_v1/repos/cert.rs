@@ -1,16 +1,36 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
 use uuid::Uuid;
-use anyhow::{Result, Error};
+use anyhow::{Result, Error, anyhow};
 use sea_orm::{
     ActiveValue::Set, 
+    ColumnTrait, 
+    Condition, 
+    ConnectionTrait, 
     DatabaseConnection, 
+    DatabaseTransaction, 
+    DbBackend, 
+    DbErr, 
     EntityTrait,
+    FromQueryResult, 
     PaginatorTrait, 
+    QueryFilter, 
+    QueryOrder, 
     QuerySelect, 
-    SqlErr
+    Statement, 
+    TransactionTrait, 
+    sea_query::Expr
 };
 use crate::{
-    api_v1::models::cert, 
+    api_v1::{
+        models::{
+            cert, 
+            cert_deletion, 
+            cert_deletion_count
+        }, 
+        repos::CertStore
+    }, 
     utils::log_error::ResultLogger
 };
 
@@ -18,18 +38,109 @@ pub struct CertRepo {
     database: Arc<DatabaseConnection>
 }
 
+#[derive(Clone)]
 pub struct CertModel {
     pub id: Uuid,
     pub email: String,
     pub name: String,
-    pub title: String
+    pub title: String,
+    pub kind: String,
+    pub created_at: DateTime<Utc>,
+    pub is_public: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>
 }
 
 pub enum CreationError {
-    UniqueErr,
+    /// The email address already has as many certificates as the quota allows
+    QuotaExceeded,
+    /// The kind already has as many certificates as its cap allows
+    KindCapReached,
     Another(Error)
 }
 
+impl From<cert::Model> for CertModel {
+    fn from(model: cert::Model) -> Self {
+        Self {
+            id: model.id,
+            email: model.email,
+            name: model.name,
+            title: model.title,
+            kind: model.kind,
+            created_at: model.created_at,
+            is_public: model.is_public,
+            updated_at: model.updated_at,
+            expires_at: model.expires_at
+        }
+    }
+}
+
+#[derive(Clone, FromQueryResult)]
+pub struct CertSearchHit {
+    pub id: Uuid,
+    pub name: String,
+    pub title: String,
+    pub rank: f64
+}
+
+/// Returns the SQLite expression that truncates the time column like the PostgreSQL date_trunc
+/// SQLite keeps the time as text, so the weeks are moved back to Monday by the weekday number
+fn sqlite_truncate_time(column: &str, unit: &str) -> Result<String> {
+    match unit {
+        "day" => Ok(format!("datetime({column}, 'start of day')")),
+        "week" => Ok(format!(
+            "datetime({column}, 'start of day', '-' || ((CAST(strftime('%w', {column}) AS INTEGER) + 6) % 7) || ' days')"
+        )),
+        "month" => Ok(format!("datetime({column}, 'start of month')")),
+        _ => Err(anyhow!("Unknown time unit \"{}\"", unit))
+    }
+}
+
+/// Returns the start of the day of the time, the rolled up deletion records keep only the days
+fn start_of_day(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+/// PostgreSQL sums the integers into the numeric type, so the sum is cast back
+const ROLLED_UP_COUNT: &str = "CAST(SUM(deletions) AS BIGINT)";
+
+/// How many of the newest public certificates the SQLite search looks through
+/// SQLite's LIKE and lower() ignore the Cyrillic case, so the certificates are matched in memory
+const SQLITE_SEARCH_SCAN_LIMIT: u64 = 10_000;
+
+/// Adds the counts to the buckets with the same start or appends the new buckets
+fn merge_buckets(buckets: &mut Vec<(NaiveDateTime, u64)>, counts: impl IntoIterator<Item = (NaiveDateTime, u64)>) {
+    for (bucket, count) in counts {
+        match buckets.iter_mut().find(|(a, _)| *a == bucket) {
+            Some((_, bucket_count)) => *bucket_count += count,
+            None => buckets.push((bucket, count))
+        }
+    }
+}
+
+/// Makes the parallel transactions with the same key wait for each other until the commit
+/// PostgreSQL locks the hash of the key, SQLite lets one transaction write at a time
+/// and fails the other one that counted before the write instead of waiting for it
+async fn lock_in_transaction(transaction: &DatabaseTransaction, key: String) -> Result<(), DbErr> {
+    if transaction.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+
+    transaction.execute_raw(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_advisory_xact_lock(hashtext($1))",
+        [key.into()]
+    )).await?;
+
+    Ok(())
+}
+
+#[derive(FromQueryResult)]
+struct BucketCount {
+    bucket: NaiveDateTime,
+    count: i64
+}
+
 impl CertRepo {
     pub fn new(database: Arc<DatabaseConnection>) -> Self {
         Self {
@@ -37,34 +148,188 @@ impl CertRepo {
         }
     }
 
-    /// Saves the certificate instance to the data base
-    pub async fn create_cert(&self, cert: CertModel) -> Result<Uuid, CreationError> {
+    /// Removes the certificates that match the condition and leaves deletion records for the statistics
+    /// Returns the number of removed certificates
+    async fn remove_certs_by_condition(&self, condition: Condition, place: &'static str) -> Result<u64> {
+        let removed_certs = cert::Entity::delete_many()
+            .filter(condition)
+            .exec_with_returning(self.database.as_ref())
+            .await
+            .log_with_place_on_error(place)?;
+
+        if removed_certs.is_empty() {
+            return Ok(0);
+        }
+
+        let deleted_at = Utc::now();
+        let deletions = removed_certs.iter().map(|cert| cert_deletion::ActiveModel {
+            id: Set(cert.id),
+            created_at: Set(cert.created_at),
+            deleted_at: Set(deleted_at)
+        });
+
+        let _ = cert_deletion::Entity::insert_many(deletions)
+            .exec(self.database.as_ref())
+            .await
+            .log_with_place_on_error(place);
+
+        Ok(removed_certs.len() as u64)
+    }
+
+    /// SQLite has neither the trigram nor the full text search and lowers only the ASCII letters
+    /// So the newest public certificates are matched here, that is fine for the small instances SQLite is meant for
+    async fn search_public_certs_by_substring(&self, query: &str, query_variant: &str, limit: u64, offset: u64) -> Result<Vec<CertSearchHit>> {
+        let public_certs = cert::Entity::find()
+            .filter(cert::Column::IsPublic.eq(true))
+            .order_by_desc(cert::Column::CreatedAt)
+            .limit(SQLITE_SEARCH_SCAN_LIMIT)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("search_public_certs_by_substring")?;
+
+        let mut hits: Vec<CertSearchHit> = public_certs
+            .into_iter()
+            .filter_map(|cert| {
+                let document = format!("{} {}", cert.name, cert.title).to_lowercase();
+                let rank = [query, query_variant]
+                    .iter()
+                    .filter(|variant| document.contains(*variant))
+                    .count();
+
+                (rank > 0).then_some(CertSearchHit {
+                    id: cert.id,
+                    name: cert.name,
+                    title: cert.title,
+                    rank: rank as f64
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.rank.total_cmp(&a.rank).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(
+            hits
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect()
+        )
+    }
+
+    /// Groups the rows of the table by the truncated time column and counts them
+    /// `unit` is a PostgreSQL date_trunc unit: "day", "week" or "month"
+    /// The `counted` expression is `COUNT(*)` for the records and the sum of the counts for the rolled up ones
+    async fn count_by_buckets(
+        &self, 
+        table: &str, column: &str, counted: &str, unit: &str, 
+        from: DateTime<Utc>, to: DateTime<Utc>
+    ) -> Result<Vec<(NaiveDateTime, u64)>> {
+        let backend = self.database.get_database_backend();
+        let statement = match backend {
+            DbBackend::Sqlite => Statement::from_sql_and_values(
+                backend, 
+                format!(
+                    "SELECT {bucket} AS bucket, {counted} AS count \
+                    FROM {table} WHERE {column} >= ?1 AND {column} < ?2 GROUP BY bucket", 
+                    bucket = sqlite_truncate_time(column, unit)?
+                ), 
+                [from.into(), to.into()]
+            ),
+            _ => Statement::from_sql_and_values(
+                backend, 
+                format!(
+                    "SELECT date_trunc($1, {column} AT TIME ZONE 'UTC') AS bucket, {counted} AS count \
+                    FROM {table} WHERE {column} >= $2 AND {column} < $3 GROUP BY bucket"
+                ), 
+                [unit.into(), from.into(), to.into()]
+            )
+        };
+
+        let buckets = BucketCount::find_by_statement(statement)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_by_buckets")?;
+
+        Ok(
+            buckets
+                .into_iter()
+                .map(|a| (a.bucket, a.count as u64))
+                .collect()
+        )
+    }
+}
+
+#[async_trait]
+impl CertStore for CertRepo {
+    async fn create_cert(&self, cert: CertModel, quota: u64, kind_cap: Option<u64>) -> Result<Uuid, CreationError> {
+        let transaction = self.database.begin()
+            .await
+            .log_with_place_on_error("create_cert")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        // READ COMMITTED doesn't hide the parallel inserts from the counts, so the email is locked first
+        // The locks are taken in the same order, the email and then the kind, so the transactions don't deadlock
+        lock_in_transaction(&transaction, format!("cert_email:{}", cert.email))
+            .await
+            .log_with_place_on_error("create_cert")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        let existing_count: u64 = cert::Entity::find()
+            .filter(cert::Column::Email.eq(cert.email.clone()))
+            .count(&transaction)
+            .await
+            .log_with_place_on_error("create_cert")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        if existing_count >= quota {
+            return Err(CreationError::QuotaExceeded);
+        }
+
+        if let Some(cap) = kind_cap {
+            lock_in_transaction(&transaction, format!("cert_kind:{}", cert.kind))
+                .await
+                .log_with_place_on_error("create_cert")
+                .map_err(|err| CreationError::Another(err.into()))?;
+
+            let kind_count: u64 = cert::Entity::find()
+                .filter(cert::Column::Kind.eq(cert.kind.clone()))
+                .count(&transaction)
+                .await
+                .log_with_place_on_error("create_cert")
+                .map_err(|err| CreationError::Another(err.into()))?;
+
+            if kind_count >= cap {
+                return Err(CreationError::KindCapReached);
+            }
+        }
+
         let model_to_insert = cert::ActiveModel {
             id: Set(cert.id),
             email: Set(cert.email),
             name: Set(cert.name),
-            title: Set(cert.title)
+            title: Set(cert.title),
+            kind: Set(cert.kind),
+            created_at: Set(cert.created_at),
+            is_public: Set(cert.is_public),
+            updated_at: Set(cert.updated_at),
+            expires_at: Set(cert.expires_at)
         };
 
-        let created_cert_or_error = cert::Entity::insert(model_to_insert)
-            .exec(self.database.as_ref())
+        let created_cert = cert::Entity::insert(model_to_insert)
+            .exec(&transaction)
             .await
-            .log_with_place_on_error("create_cert");
+            .log_with_place_on_error("create_cert")
+            .map_err(|err| CreationError::Another(err.into()))?;
 
-        match created_cert_or_error {
-            Ok(cert) => Ok(cert.last_insert_id),
-            Err(err) => {
-                if let Some(SqlErr::UniqueConstraintViolation(_)) = err.sql_err() {
-                    Err(CreationError::UniqueErr)
-                } else {
-                    Err(CreationError::Another(err.into()))
-                }
-            }
-        }
+        transaction.commit()
+            .await
+            .log_with_place_on_error("create_cert")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        Ok(created_cert.last_insert_id)
     }
 
-    /// Returns a certificate by the ID
-    pub async fn find_cert_by_id(&self, id: Uuid) -> Result<Option<CertModel>> {
+    async fn find_cert_by_id(&self, id: Uuid) -> Result<Option<CertModel>> {
         let search_result = cert::Entity::find_by_id(id)
             .limit(1)
             .one(self.database.as_ref())
@@ -76,82 +341,381 @@ impl CertRepo {
                 id, 
                 email: cert.email, 
                 name: cert.name, 
-                title: cert.title
+                title: cert.title, 
+                kind: cert.kind, 
+                created_at: cert.created_at, 
+                is_public: cert.is_public,
+                updated_at: cert.updated_at,
+                expires_at: cert.expires_at
             }))
         } else {
             Ok(None)
         }
     }
 
-    /// Returns a certificate by the email address
-    pub async fn find_cert_by_email(&self, email: String) -> Result<Option<CertModel>> {
-        let search_result = cert::Entity::find_by_email(email.to_string())
-            .limit(1)
-            .one(self.database.as_ref())
+    async fn find_certs_by_email(&self, email: String) -> Result<Vec<CertModel>> {
+        let search_result = cert::Entity::find()
+            .filter(cert::Column::Email.eq(email))
+            .order_by_asc(cert::Column::CreatedAt)
+            .order_by_asc(cert::Column::Id)
+            .all(self.database.as_ref())
             .await
-            .log_with_place_on_error("find_cert_by_email")?;
+            .log_with_place_on_error("find_certs_by_email")?;
 
-        if let Some(cert) = search_result {
-            Ok(Some(CertModel { 
-                id: cert.id, 
-                email, 
-                name: cert.name, 
-                title: cert.title
-            }))
-        } else {
-            Ok(None)
+        Ok(search_result.into_iter().map(CertModel::from).collect())
+    }
+
+    async fn remove_cert_by_id(&self, id: Uuid) -> Result<u64> {
+        self.remove_certs_by_condition(
+            Condition::all()
+                .add(cert::Column::Id.eq(id)), 
+            "remove_cert_by_id"
+        ).await
+    }
+
+    async fn remove_cert_by_email(&self, email: String) -> Result<u64> {
+        self.remove_certs_by_condition(
+            Condition::all()
+                .add(cert::Column::Email.eq(email)), 
+            "remove_cert_by_email"
+        ).await
+    }
+
+    async fn remove_cert_by_id_and_email(&self, id: Uuid, email: String) -> Result<u64> {
+        self.remove_certs_by_condition(
+            Condition::all()
+                .add(cert::Column::Id.eq(id))
+                .add(cert::Column::Email.eq(email)), 
+            "remove_cert_by_id_and_email"
+        ).await
+    }
+
+    async fn roll_up_deletion_records_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let transaction = self.database.begin()
+            .await
+            .log_with_place_on_error("roll_up_deletion_records_before")?;
+
+        let old_deletions = cert_deletion::Entity::find()
+            .filter(cert_deletion::Column::DeletedAt.lt(before))
+            .all(&transaction)
+            .await
+            .log_with_place_on_error("roll_up_deletion_records_before")?;
+
+        if old_deletions.is_empty() {
+            return Ok(0);
         }
+
+        // The statistics need only the days, so the records of the same days become one count
+        let mut day_counts: HashMap<(DateTime<Utc>, DateTime<Utc>), i64> = HashMap::new();
+
+        for deletion in &old_deletions {
+            *day_counts.entry((start_of_day(deletion.created_at), start_of_day(deletion.deleted_at))).or_default() += 1;
+        }
+
+        let counts = day_counts.into_iter().map(|((created_at, deleted_at), deletions)| cert_deletion_count::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            created_at: Set(created_at),
+            deleted_at: Set(deleted_at),
+            deletions: Set(deletions)
+        });
+
+        cert_deletion_count::Entity::insert_many(counts)
+            .exec(&transaction)
+            .await
+            .log_with_place_on_error("roll_up_deletion_records_before")?;
+
+        // The counts and the removal are committed together, so a record is never counted twice or lost
+        cert_deletion::Entity::delete_many()
+            .filter(cert_deletion::Column::DeletedAt.lt(before))
+            .exec(&transaction)
+            .await
+            .log_with_place_on_error("roll_up_deletion_records_before")?;
+
+        transaction.commit()
+            .await
+            .log_with_place_on_error("roll_up_deletion_records_before")?;
+
+        Ok(old_deletions.len() as u64)
     }
 
-    /// Removes a certificate by the ID
-    /// Returns 1 if the certificate was removed and 0 if the certificate wasn't
-    pub async fn remove_cert_by_id(&self, id: Uuid) -> Result<u64> {
+    async fn count_active(&self, now: DateTime<Utc>) -> Result<u64> {
+        let count: u64 = cert::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(cert::Column::ExpiresAt.is_null())
+                    .add(cert::Column::ExpiresAt.gt(now))
+            )
+            .count(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_active")?;
+
+        Ok(count)
+    }
+
+    async fn count_by_email(&self, email: String) -> Result<u64> {
+        let count: u64 = cert::Entity::find()
+            .filter(cert::Column::Email.eq(email))
+            .count(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_by_email")?;
+
+        Ok(count)
+    }
+
+    async fn count_by_kind(&self, kind: String) -> Result<u64> {
+        let count: u64 = cert::Entity::find()
+            .filter(cert::Column::Kind.eq(kind))
+            .count(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_by_kind")?;
+
+        Ok(count)
+    }
+
+    async fn count_existing_at(&self, at: DateTime<Utc>) -> Result<u64> {
+        let existing: u64 = cert::Entity::find()
+            .filter(cert::Column::CreatedAt.lt(at))
+            .count(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_existing_at")?;
+
+        let deleted_later: u64 = cert_deletion::Entity::find()
+            .filter(cert_deletion::Column::CreatedAt.lt(at))
+            .filter(cert_deletion::Column::DeletedAt.gte(at))
+            .count(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_existing_at")?;
+
+        let rolled_up_later: i64 = cert_deletion_count::Entity::find()
+            .filter(cert_deletion_count::Column::CreatedAt.lt(at))
+            .filter(cert_deletion_count::Column::DeletedAt.gte(at))
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("count_existing_at")?
+            .iter()
+            .map(|count| count.deletions)
+            .sum();
+
+        Ok(existing + deleted_later + rolled_up_later as u64)
+    }
+
+    async fn count_created_by_buckets(&self, unit: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(NaiveDateTime, u64)>> {
+        let mut created = self.count_by_buckets("certs", "created_at", "COUNT(*)", unit, from, to).await?;
+        let deleted = self.count_by_buckets("cert_deletions", "created_at", "COUNT(*)", unit, from, to).await?;
+        let rolled_up = self.count_by_buckets("cert_deletion_counts", "created_at", ROLLED_UP_COUNT, unit, from, to).await?;
+
+        // Certificates that were deleted later were created too
+        merge_buckets(&mut created, deleted.into_iter().chain(rolled_up));
+
+        Ok(created)
+    }
+
+    async fn count_deleted_by_buckets(&self, unit: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(NaiveDateTime, u64)>> {
+        let mut deleted = self.count_by_buckets("cert_deletions", "deleted_at", "COUNT(*)", unit, from, to).await?;
+        let rolled_up = self.count_by_buckets("cert_deletion_counts", "deleted_at", ROLLED_UP_COUNT, unit, from, to).await?;
+
+        merge_buckets(&mut deleted, rolled_up);
+
+        Ok(deleted)
+    }
+
+    async fn set_cert_visibility(&self, id: Uuid, email: String, is_public: bool) -> Result<u64> {
         Ok(
-            cert::Entity::delete_by_id(id)
+            cert::Entity::update_many()
+                .col_expr(cert::Column::IsPublic, Expr::value(is_public))
+                .filter(cert::Column::Id.eq(id))
+                .filter(cert::Column::Email.eq(email))
                 .exec(self.database.as_ref())
                 .await
-                .log_with_place_on_error("remove_cert_by_id")?
+                .log_with_place_on_error("set_cert_visibility")?
                 .rows_affected
         )
     }
 
-    /// Removes a certificate by the email address
-    /// Returns 1 if the certificate was removed and 0 if the certificate wasn't
-    pub async fn remove_cert_by_email(&self, email: String) -> Result<u64> {
+    async fn find_public_certs_newest(&self, cursor: Option<(DateTime<Utc>, Uuid)>, limit: u64) -> Result<Vec<CertModel>> {
+        let mut query = cert::Entity::find()
+            .filter(cert::Column::IsPublic.eq(true));
+
+        if let Some((created_at, id)) = cursor {
+            query = query.filter(
+                Condition::any()
+                    .add(cert::Column::CreatedAt.lt(created_at))
+                    .add(
+                        Condition::all()
+                            .add(cert::Column::CreatedAt.eq(created_at))
+                            .add(cert::Column::Id.lt(id))
+                    )
+            );
+        }
+
+        let search_result = query
+            .order_by_desc(cert::Column::CreatedAt)
+            .order_by_desc(cert::Column::Id)
+            .limit(limit)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_public_certs_newest")?;
+
+        Ok(search_result.into_iter().map(CertModel::from).collect())
+    }
+
+    async fn find_public_certs_from_id(&self, pivot: Uuid, limit: u64) -> Result<Vec<CertModel>> {
+        let mut search_result = cert::Entity::find()
+            .filter(cert::Column::IsPublic.eq(true))
+            .filter(cert::Column::Id.gte(pivot))
+            .order_by_asc(cert::Column::Id)
+            .limit(limit)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_public_certs_from_id")?;
+
+        if (search_result.len() as u64) < limit {
+            let wrapped = cert::Entity::find()
+                .filter(cert::Column::IsPublic.eq(true))
+                .filter(cert::Column::Id.lt(pivot))
+                .order_by_asc(cert::Column::Id)
+                .limit(limit - search_result.len() as u64)
+                .all(self.database.as_ref())
+                .await
+                .log_with_place_on_error("find_public_certs_from_id")?;
+
+            search_result.extend(wrapped);
+        }
+
+        Ok(search_result.into_iter().map(CertModel::from).collect())
+    }
+
+    async fn find_certs_after_id(&self, cursor: Option<Uuid>, limit: u64) -> Result<Vec<CertModel>> {
+        let mut query = cert::Entity::find();
+
+        if let Some(id) = cursor {
+            query = query.filter(cert::Column::Id.gt(id));
+        }
+
+        let search_result = query
+            .order_by_asc(cert::Column::Id)
+            .limit(limit)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_certs_after_id")?;
+
+        Ok(search_result.into_iter().map(CertModel::from).collect())
+    }
+
+    async fn update_cert_names(&self, id: Uuid, name: String, title: String) -> Result<u64> {
         Ok(
-            cert::Entity::delete(cert::ActiveModel {
-                email: Set(email),
-                ..Default::default()
-            })
+            cert::Entity::update_many()
+                .col_expr(cert::Column::Name, Expr::value(name))
+                .col_expr(cert::Column::Title, Expr::value(title))
+                .col_expr(cert::Column::UpdatedAt, Expr::value(Some(Utc::now())))
+                .filter(cert::Column::Id.eq(id))
                 .exec(self.database.as_ref())
                 .await
-                .log_with_place_on_error("remove_cert_by_email")?
+                .log_with_place_on_error("update_cert_names")?
                 .rows_affected
         )
     }
 
-    /// Removes a certificate by the ID and email address
-    /// Returns 1 if the certificate was removed and 0 if the certificate wasn't
-    pub async fn remove_cert_by_id_and_email(&self, id: Uuid, email: String) -> Result<u64> {
+    async fn update_cert_email(&self, id: Uuid, old_email: String, new_email: String, quota: u64) -> Result<u64, CreationError> {
+        let transaction = self.database.begin()
+            .await
+            .log_with_place_on_error("update_cert_email")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        // The same lock as the creation takes, so the new address can't pass the quota with them together
+        lock_in_transaction(&transaction, format!("cert_email:{}", new_email))
+            .await
+            .log_with_place_on_error("update_cert_email")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        let existing_count: u64 = cert::Entity::find()
+            .filter(cert::Column::Email.eq(new_email.clone()))
+            .count(&transaction)
+            .await
+            .log_with_place_on_error("update_cert_email")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        if existing_count >= quota {
+            return Err(CreationError::QuotaExceeded);
+        }
+
+        let update_count = cert::Entity::update_many()
+            .col_expr(cert::Column::Email, Expr::value(new_email))
+            .col_expr(cert::Column::UpdatedAt, Expr::value(Some(Utc::now())))
+            .filter(cert::Column::Id.eq(id))
+            .filter(cert::Column::Email.eq(old_email))
+            .exec(&transaction)
+            .await
+            .log_with_place_on_error("update_cert_email")
+            .map_err(|err| CreationError::Another(err.into()))?
+            .rows_affected;
+
+        transaction.commit()
+            .await
+            .log_with_place_on_error("update_cert_email")
+            .map_err(|err| CreationError::Another(err.into()))?;
+
+        Ok(update_count)
+    }
+
+    async fn update_cert_expiry(&self, id: Uuid, email: String, expires_at: Option<DateTime<Utc>>) -> Result<u64> {
         Ok(
-            cert::Entity::delete(cert::ActiveModel {
-                id: Set(id),
-                email: Set(email),
-                ..Default::default()
-            })
+            cert::Entity::update_many()
+                .col_expr(cert::Column::ExpiresAt, Expr::value(expires_at))
+                .col_expr(cert::Column::UpdatedAt, Expr::value(Some(Utc::now())))
+                .filter(cert::Column::Id.eq(id))
+                .filter(cert::Column::Email.eq(email))
                 .exec(self.database.as_ref())
                 .await
-                .log_with_place_on_error("remove_cert_by_id_and_email")?
+                .log_with_place_on_error("update_cert_expiry")?
                 .rows_affected
         )
     }
 
-    /// Returns the total amount of certificates
-    pub async fn count_all(&self) -> Result<u64> {
-        let count: u64 = cert::Entity::find()
-            .count(self.database.as_ref())
-            .await?;
+    async fn find_certs_expiring_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CertModel>> {
+        let search_result = cert::Entity::find()
+            .filter(cert::Column::ExpiresAt.gt(from))
+            .filter(cert::Column::ExpiresAt.lte(to))
+            .order_by_asc(cert::Column::ExpiresAt)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_certs_expiring_between")?;
 
-        Ok(count)
+        Ok(search_result.into_iter().map(CertModel::from).collect())
+    }
+
+    /// The full text search uses the 'simple' configuration, PostgreSQL has no Ukrainian stemming out of the box
+    /// The inflected forms, e.g. "сертифікат" and "сертифікати", are matched by the trigram word similarity instead
+    async fn search_public_certs(&self, query: &str, query_variant: &str, limit: u64, offset: u64) -> Result<Vec<CertSearchHit>> {
+        let backend = self.database.get_database_backend();
+        if backend == DbBackend::Sqlite {
+            return self.search_public_certs_by_substring(query, query_variant, limit, offset).await;
+        }
+
+        let document = cert::SEARCH_DOCUMENT;
+        let statement = Statement::from_sql_and_values(
+            backend, 
+            format!(
+                "SELECT id, name, title, ( \
+                    GREATEST(word_similarity($1, {document}), word_similarity($2, {document})) \
+                    + ts_rank(to_tsvector('simple', {document}), plainto_tsquery('simple', $1) || plainto_tsquery('simple', $2)) \
+                )::float8 AS rank \
+                FROM certs \
+                WHERE is_public AND ( \
+                    $1 <% {document} OR $2 <% {document} \
+                    OR to_tsvector('simple', {document}) @@ (plainto_tsquery('simple', $1) || plainto_tsquery('simple', $2)) \
+                ) \
+                ORDER BY rank DESC, id \
+                LIMIT $3 OFFSET $4"
+            ), 
+            [query.into(), query_variant.into(), (limit as i64).into(), (offset as i64).into()]
+        );
+
+        let hits = CertSearchHit::find_by_statement(statement)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("search_public_certs")?;
+
+        Ok(hits)
     }
- }
+}
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use uuid::Uuid;
+use anyhow::Result;
+use crate::api_v1::repos::{
+    CertModel,
+    CertSearchHit,
+    CreationError
+};
+
+/// A storage of the certificates and their deletion records
+/// Implemented by the data base repository and by the in-memory storage for the tests
+#[async_trait]
+pub trait CertStore: Send + Sync {
+    /// Saves the certificate instance to the data base
+    /// Fails with the quota error if the email address already has `quota` certificates
+    /// and with the cap error if the kind already has `kind_cap` certificates
+    async fn create_cert(&self, cert: CertModel, quota: u64, kind_cap: Option<u64>) -> Result<Uuid, CreationError>;
+
+    /// Returns a certificate by the ID
+    async fn find_cert_by_id(&self, id: Uuid) -> Result<Option<CertModel>>;
+
+    /// Returns all certificates of the email address from the oldest one
+    async fn find_certs_by_email(&self, email: String) -> Result<Vec<CertModel>>;
+
+    /// Removes a certificate by the ID
+    /// Returns 1 if the certificate was removed and 0 if the certificate wasn't
+    async fn remove_cert_by_id(&self, id: Uuid) -> Result<u64>;
+
+    /// Removes all certificates of the email address
+    /// Returns the number of removed certificates
+    async fn remove_cert_by_email(&self, email: String) -> Result<u64>;
+
+    /// Removes a certificate by the ID and email address
+    /// Returns 1 if the certificate was removed and 0 if the certificate wasn't
+    async fn remove_cert_by_id_and_email(&self, id: Uuid, email: String) -> Result<u64>;
+
+    /// Rolls up the deletion records of the certificates deleted before the specified time into the daily counts
+    /// The statistics stay the same, only the IDs of the removed certificates are forgotten
+    /// Returns the number of rolled up records
+    async fn roll_up_deletion_records_before(&self, before: DateTime<Utc>) -> Result<u64>;
+
+    /// Returns the amount of certificates that haven't expired at the specified time
+    async fn count_active(&self, now: DateTime<Utc>) -> Result<u64>;
+
+    /// Returns the amount of certificates of the email address
+    async fn count_by_email(&self, email: String) -> Result<u64>;
+
+    /// Returns the amount of certificates of the kind
+    async fn count_by_kind(&self, kind: String) -> Result<u64>;
+
+    /// Returns the amount of certificates that existed at the specified time
+    async fn count_existing_at(&self, at: DateTime<Utc>) -> Result<u64>;
+
+    /// Returns the amount of created certificates grouped by the time buckets
+    /// `unit` is "day", "week" (starting on Monday) or "month"
+    async fn count_created_by_buckets(&self, unit: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(NaiveDateTime, u64)>>;
+
+    /// Returns the amount of deleted certificates grouped by the time buckets
+    async fn count_deleted_by_buckets(&self, unit: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(NaiveDateTime, u64)>>;
+
+    /// Changes the visibility of the certificate in the public gallery
+    /// Returns 1 if the certificate was updated and 0 if the certificate wasn't
+    async fn set_cert_visibility(&self, id: Uuid, email: String, is_public: bool) -> Result<u64>;
+
+    /// Returns the newest public certificates created before the cursor (creation time and ID)
+    async fn find_public_certs_newest(&self, cursor: Option<(DateTime<Utc>, Uuid)>, limit: u64) -> Result<Vec<CertModel>>;
+
+    /// Returns public certificates starting from the pivot ID and wrapping around the smallest ID
+    /// Random v4 IDs are uniformly distributed, so a random pivot gives a random sample without a full scan
+    async fn find_public_certs_from_id(&self, pivot: Uuid, limit: u64) -> Result<Vec<CertModel>>;
+
+    /// Returns all certificates with the ID greater than the cursor ordered by the ID
+    /// Used to walk through the whole table in batches
+    async fn find_certs_after_id(&self, cursor: Option<Uuid>, limit: u64) -> Result<Vec<CertModel>>;
+
+    /// Changes the name and the title of the certificate and remembers the modification time
+    async fn update_cert_names(&self, id: Uuid, name: String, title: String) -> Result<u64>;
+
+    /// Moves the certificate from the old email address to the new one, the ID stays the same
+    /// Returns 1 if the certificate was moved and 0 if it doesn't belong to the old address anymore
+    /// Fails with the quota error if the new email address already has `quota` certificates
+    async fn update_cert_email(&self, id: Uuid, old_email: String, new_email: String, quota: u64) -> Result<u64, CreationError>;
+
+    /// Changes the expiration time of the certificate, None makes it never expire
+    /// Returns 1 if the certificate was updated and 0 if it doesn't belong to the email address anymore
+    async fn update_cert_expiry(&self, id: Uuid, email: String, expires_at: Option<DateTime<Utc>>) -> Result<u64>;
+
+    /// Returns the certificates that expire after `from` and not later than `to`, the soonest first
+    async fn find_certs_expiring_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CertModel>>;
+
+    /// Searches the public certificates by the name and the title and orders them by the relevance
+    /// Both query variants (e.g. the original and the transliterated one) are matched
+    async fn search_public_certs(&self, query: &str, query_variant: &str, limit: u64, offset: u64) -> Result<Vec<CertSearchHit>>;
+}
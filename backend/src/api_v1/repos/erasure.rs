@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use anyhow::Result;
+use sea_orm::{
+    ActiveValue::Set, 
+    DatabaseConnection, 
+    EntityTrait, 
+    QuerySelect
+};
+use crate::{
+    api_v1::models::erasure_receipt, 
+    utils::log_error::ResultLogger
+};
+
+pub struct ErasureRepo {
+    database: Arc<DatabaseConnection>
+}
+
+#[derive(Clone)]
+pub struct ErasureReceiptModel {
+    pub id: Uuid,
+    pub email_hash: String,
+    pub certificates_removed: i32,
+    pub cache_keys_removed: i32,
+    pub email_jobs_removed: i32,
+    pub webhook_deliveries_removed: i32,
+    pub erased_at: DateTime<Utc>
+}
+
+impl From<erasure_receipt::Model> for ErasureReceiptModel {
+    fn from(model: erasure_receipt::Model) -> Self {
+        Self {
+            id: model.id,
+            email_hash: model.email_hash,
+            certificates_removed: model.certificates_removed,
+            cache_keys_removed: model.cache_keys_removed,
+            email_jobs_removed: model.email_jobs_removed,
+            webhook_deliveries_removed: model.webhook_deliveries_removed,
+            erased_at: model.erased_at
+        }
+    }
+}
+
+impl ErasureRepo {
+    pub fn new(database: Arc<DatabaseConnection>) -> Self {
+        Self {
+            database
+        }
+    }
+
+    /// Saves the erasure receipt to the data base
+    pub async fn create_receipt(&self, receipt: ErasureReceiptModel) -> Result<Uuid> {
+        let model_to_insert = erasure_receipt::ActiveModel {
+            id: Set(receipt.id),
+            email_hash: Set(receipt.email_hash),
+            certificates_removed: Set(receipt.certificates_removed),
+            cache_keys_removed: Set(receipt.cache_keys_removed),
+            email_jobs_removed: Set(receipt.email_jobs_removed),
+            webhook_deliveries_removed: Set(receipt.webhook_deliveries_removed),
+            erased_at: Set(receipt.erased_at)
+        };
+
+        let created_receipt = erasure_receipt::Entity::insert(model_to_insert)
+            .exec(self.database.as_ref())
+            .await
+            .log_with_place_on_error("create_receipt")?;
+
+        Ok(created_receipt.last_insert_id)
+    }
+
+    /// Returns an erasure receipt by the ID
+    pub async fn find_receipt_by_id(&self, id: Uuid) -> Result<Option<ErasureReceiptModel>> {
+        let search_result = erasure_receipt::Entity::find_by_id(id)
+            .limit(1)
+            .one(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_receipt_by_id")?;
+
+        Ok(search_result.map(ErasureReceiptModel::from))
+    }
+}
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use anyhow::Result;
+
+/// A key-value storage with expiring keys, lists, hashes and sorted sets
+/// Implemented by the Redis repository and by the in-memory storage for the tests
+#[async_trait]
+pub trait KvStore: Send + Sync {
+    /// Increases the value of the counter by specified value
+    /// Set the negative value to decrease the counter value
+    async fn increase_by(&self, key: String, value: i64, expire: Duration) -> Result<u64>;
+
+    /// Returns the value of the variable
+    async fn get_string(&self, key: String) -> Result<Option<String>>;
+
+    /// Changes the value of the variable or create a variable with specified value
+    /// replace_expire = true will change the TTL if the variable already exists
+    /// replace_expire = false won't change the variable if it already exists and returns None
+    async fn set_string(&self, key: String, value: String, expire: Duration, replace_expire: bool) -> Result<Option<()>>;
+
+    /// Removes the variable by the key
+    async fn delete_by_key(&self, key: String) -> Result<u64>;
+
+    /// Returns the time to left and UTC expiration time of the specified variable
+    async fn get_ttl(&self, key: String) -> Result<(Duration, DateTime<Utc>)>;
+
+    /// Adds the element to the head of the list by the specified key
+    async fn lpush(&self, key: String, value: String) -> Result<u32>;
+
+    /// Schedules the member in a sorted set by the specified key to be taken at the UTC time
+    async fn schedule(&self, key: String, value: String, at: DateTime<Utc>) -> Result<()>;
+
+    /// Takes up to `limit` members from a sorted set by the specified key whose time has come
+    /// Every member is returned only once, even if several processes take members concurrently
+    async fn take_due(&self, key: String, limit: i64) -> Result<Vec<String>>;
+
+    /// Returns the values of the hash fields by the specified key, None for every missing field
+    async fn hash_get_many(&self, key: String, fields: Vec<String>) -> Result<Vec<Option<String>>>;
+
+    /// Sets the values of the hash fields by the specified key and updates the TTL of the hash
+    async fn hash_set_many(&self, key: String, values: HashMap<String, String>, expire: Duration) -> Result<()>;
+
+    /// Returns all keys that match the glob-style pattern
+    async fn scan_keys(&self, pattern: String) -> Result<Vec<String>>;
+
+    /// Returns all elements of the list by the specified key
+    async fn list_all(&self, key: String) -> Result<Vec<String>>;
+
+    /// Removes all occurrences of the element from the list by the specified key
+    /// Returns the number of removed elements
+    async fn list_remove(&self, key: String, value: String) -> Result<u64>;
+
+    /// Returns the length of the list by the specified key
+    async fn list_len(&self, key: String) -> Result<u64>;
+
+    /// Keeps only the elements of the list by the specified key between the indexes (inclusive)
+    async fn list_trim(&self, key: String, start: i64, stop: i64) -> Result<()>;
+
+    /// Returns the amount of members in the sorted set by the specified key
+    async fn sorted_set_len(&self, key: String) -> Result<u64>;
+
+    /// Takes the lease by the specified key for the owner or extends it if the owner already holds it
+    /// Returns false if another owner holds the lease
+    async fn acquire_lease(&self, key: String, owner: String, expire: Duration) -> Result<bool>;
+}
+
+impl<'a> dyn KvStore + 'a {
+    /// Increases the value of the counter by 1
+    pub async fn increase_by_one(&self, key: String, expire: Duration) -> Result<u64> {
+        self.increase_by(key, 1, expire).await
+    }
+
+    /// Returns the value of the variable parsed into the type
+    /// A value that can't be parsed is treated as missing
+    pub async fn get_value<T: FromStr>(&self, key: String) -> Result<Option<T>> {
+        Ok(
+            self.get_string(key)
+                .await?
+                .and_then(|value| value.parse().ok())
+        )
+    }
+
+    /// Changes the value of the variable or create a variable with specified value
+    /// replace_expire = true will change the TTL if the variable already exists
+    /// replace_expire = false won't change the variable if it already exists and returns None
+    pub async fn set_value<T: Display>(&self, key: String, value: T, expire: Duration, replace_expire: bool) -> Result<Option<()>> {
+        self.set_string(key, value.to_string(), expire, replace_expire).await
+    }
+}
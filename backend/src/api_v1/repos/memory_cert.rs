@@ -0,0 +1,365 @@
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex}
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Days, NaiveDateTime, Utc};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+use crate::{
+    api_v1::repos::{
+        CertModel,
+        CertSearchHit,
+        CertStore,
+        CreationError
+    },
+    utils::clock::Clock
+};
+
+/// A removed certificate kept for the statistics, like the cert_deletions table
+/// The rolled up records are like the cert_deletion_counts table, they count several certificates by day
+struct DeletionRecord {
+    created_at: DateTime<Utc>,
+    deleted_at: DateTime<Utc>,
+    count: u64,
+    rolled_up: bool
+}
+
+/// A certificates storage in the process memory
+/// Used by the tests instead of the data base, the search is a plain substring match
+pub struct MemoryCertStore {
+    certs: Mutex<Vec<CertModel>>,
+    deletions: Mutex<Vec<DeletionRecord>>,
+    clock: Arc<dyn Clock>
+}
+
+/// Truncates the time like the PostgreSQL date_trunc does for the "day", "week" and "month" units
+fn truncate_time(time: &DateTime<Utc>, unit: &str) -> Result<NaiveDateTime> {
+    let date = time.date_naive();
+
+    let start = match unit {
+        "day" => date,
+        "week" => date - Days::new(date.weekday().num_days_from_monday() as u64),
+        "month" => date.with_day(1).ok_or(anyhow!("Invalid date"))?,
+        _ => return Err(anyhow!("Unknown time unit \"{}\"", unit))
+    };
+
+    Ok(start.and_time(Default::default()))
+}
+
+/// Sums the counts of the times grouped by the truncated time
+fn count_by_buckets(times: impl Iterator<Item = (DateTime<Utc>, u64)>, unit: &str) -> Result<Vec<(NaiveDateTime, u64)>> {
+    let mut buckets: Vec<(NaiveDateTime, u64)> = Vec::new();
+
+    for (time, count) in times {
+        let bucket = truncate_time(&time, unit)?;
+
+        match buckets.iter_mut().find(|(a, _)| *a == bucket) {
+            Some((_, bucket_count)) => *bucket_count += count,
+            None => buckets.push((bucket, count))
+        }
+    }
+
+    Ok(buckets)
+}
+
+impl MemoryCertStore {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            certs: Mutex::new(Vec::new()),
+            deletions: Mutex::new(Vec::new()),
+            clock
+        }
+    }
+
+    /// Removes the certificates that match the condition and leaves deletion records for the statistics
+    fn remove_certs_by_condition(&self, condition: impl Fn(&CertModel) -> bool) -> u64 {
+        let deleted_at = self.clock.now();
+        let mut certs = self.certs.lock().unwrap();
+        let mut deletions = self.deletions.lock().unwrap();
+
+        let length = certs.len();
+        certs.retain(|cert| {
+            if condition(cert) {
+                deletions.push(DeletionRecord { created_at: cert.created_at, deleted_at, count: 1, rolled_up: false });
+                false
+            } else {
+                true
+            }
+        });
+
+        (length - certs.len()) as u64
+    }
+}
+
+#[async_trait]
+impl CertStore for MemoryCertStore {
+    async fn create_cert(&self, cert: CertModel, quota: u64, kind_cap: Option<u64>) -> Result<Uuid, CreationError> {
+        let mut certs = self.certs.lock().unwrap();
+
+        if certs.iter().any(|a| a.id == cert.id) {
+            return Err(CreationError::Another(anyhow!("The certificate {} already exists", cert.id)));
+        }
+
+        if certs.iter().filter(|a| a.email == cert.email).count() as u64 >= quota {
+            return Err(CreationError::QuotaExceeded);
+        }
+
+        if let Some(cap) = kind_cap && certs.iter().filter(|a| a.kind == cert.kind).count() as u64 >= cap {
+            return Err(CreationError::KindCapReached);
+        }
+
+        let id = cert.id;
+        certs.push(cert);
+
+        Ok(id)
+    }
+
+    async fn find_cert_by_id(&self, id: Uuid) -> Result<Option<CertModel>> {
+        Ok(self.certs.lock().unwrap().iter().find(|cert| cert.id == id).cloned())
+    }
+
+    async fn find_certs_by_email(&self, email: String) -> Result<Vec<CertModel>> {
+        let mut certs: Vec<CertModel> = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cert.email == email)
+            .cloned()
+            .collect();
+
+        certs.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(certs)
+    }
+
+    async fn remove_cert_by_id(&self, id: Uuid) -> Result<u64> {
+        Ok(self.remove_certs_by_condition(|cert| cert.id == id))
+    }
+
+    async fn remove_cert_by_email(&self, email: String) -> Result<u64> {
+        Ok(self.remove_certs_by_condition(|cert| cert.email == email))
+    }
+
+    async fn remove_cert_by_id_and_email(&self, id: Uuid, email: String) -> Result<u64> {
+        Ok(self.remove_certs_by_condition(|cert| cert.id == id && cert.email == email))
+    }
+
+    async fn roll_up_deletion_records_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let mut deletions = self.deletions.lock().unwrap();
+
+        let (old_deletions, kept_deletions): (Vec<DeletionRecord>, Vec<DeletionRecord>) = deletions
+            .drain(..)
+            .partition(|deletion| !deletion.rolled_up && deletion.deleted_at < before);
+        *deletions = kept_deletions;
+
+        let mut rolled_up: Vec<DeletionRecord> = Vec::new();
+
+        for deletion in &old_deletions {
+            let created_at = truncate_time(&deletion.created_at, "day")?.and_utc();
+            let deleted_at = truncate_time(&deletion.deleted_at, "day")?.and_utc();
+
+            match rolled_up.iter_mut().find(|a| a.created_at == created_at && a.deleted_at == deleted_at) {
+                Some(record) => record.count += deletion.count,
+                None => rolled_up.push(DeletionRecord { created_at, deleted_at, count: deletion.count, rolled_up: true })
+            }
+        }
+
+        deletions.extend(rolled_up);
+
+        Ok(old_deletions.len() as u64)
+    }
+
+    async fn count_active(&self, now: DateTime<Utc>) -> Result<u64> {
+        Ok(
+            self.certs.lock().unwrap()
+                .iter()
+                .filter(|cert| cert.expires_at.is_none_or(|expires_at| now < expires_at))
+                .count() as u64
+        )
+    }
+
+    async fn count_by_email(&self, email: String) -> Result<u64> {
+        Ok(self.certs.lock().unwrap().iter().filter(|cert| cert.email == email).count() as u64)
+    }
+
+    async fn count_by_kind(&self, kind: String) -> Result<u64> {
+        Ok(self.certs.lock().unwrap().iter().filter(|cert| cert.kind == kind).count() as u64)
+    }
+
+    async fn count_existing_at(&self, at: DateTime<Utc>) -> Result<u64> {
+        let existing = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cert.created_at < at)
+            .count();
+        let deleted_later: u64 = self.deletions.lock().unwrap()
+            .iter()
+            .filter(|deletion| deletion.created_at < at && deletion.deleted_at >= at)
+            .map(|deletion| deletion.count)
+            .sum();
+
+        Ok(existing as u64 + deleted_later)
+    }
+
+    async fn count_created_by_buckets(&self, unit: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(NaiveDateTime, u64)>> {
+        // Certificates that were deleted later were created too
+        let mut times: Vec<(DateTime<Utc>, u64)> = self.certs.lock().unwrap()
+            .iter()
+            .map(|cert| (cert.created_at, 1))
+            .collect();
+        times.extend(self.deletions.lock().unwrap().iter().map(|deletion| (deletion.created_at, deletion.count)));
+
+        count_by_buckets(times.into_iter().filter(|(time, _)| *time >= from && *time < to), unit)
+    }
+
+    async fn count_deleted_by_buckets(&self, unit: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(NaiveDateTime, u64)>> {
+        let times: Vec<(DateTime<Utc>, u64)> = self.deletions.lock().unwrap()
+            .iter()
+            .map(|deletion| (deletion.deleted_at, deletion.count))
+            .filter(|(time, _)| *time >= from && *time < to)
+            .collect();
+
+        count_by_buckets(times.into_iter(), unit)
+    }
+
+    async fn set_cert_visibility(&self, id: Uuid, email: String, is_public: bool) -> Result<u64> {
+        let mut certs = self.certs.lock().unwrap();
+
+        match certs.iter_mut().find(|cert| cert.id == id && cert.email == email) {
+            Some(cert) => {
+                cert.is_public = is_public;
+                Ok(1)
+            },
+            None => Ok(0)
+        }
+    }
+
+    async fn find_public_certs_newest(&self, cursor: Option<(DateTime<Utc>, Uuid)>, limit: u64) -> Result<Vec<CertModel>> {
+        let mut certs: Vec<CertModel> = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cert.is_public)
+            .filter(|cert| cursor.is_none_or(|cursor| (cert.created_at, cert.id) < cursor))
+            .cloned()
+            .collect();
+
+        certs.sort_by_key(|cert| Reverse((cert.created_at, cert.id)));
+        certs.truncate(limit as usize);
+
+        Ok(certs)
+    }
+
+    async fn find_public_certs_from_id(&self, pivot: Uuid, limit: u64) -> Result<Vec<CertModel>> {
+        let mut certs: Vec<CertModel> = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cert.is_public)
+            .cloned()
+            .collect();
+
+        // The certificates from the pivot go first, the smaller IDs wrap around
+        certs.sort_by_key(|cert| (cert.id < pivot, cert.id));
+        certs.truncate(limit as usize);
+
+        Ok(certs)
+    }
+
+    async fn find_certs_after_id(&self, cursor: Option<Uuid>, limit: u64) -> Result<Vec<CertModel>> {
+        let mut certs: Vec<CertModel> = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cursor.is_none_or(|cursor| cert.id > cursor))
+            .cloned()
+            .collect();
+
+        certs.sort_by_key(|cert| cert.id);
+        certs.truncate(limit as usize);
+
+        Ok(certs)
+    }
+
+    async fn update_cert_names(&self, id: Uuid, name: String, title: String) -> Result<u64> {
+        let now = self.clock.now();
+        let mut certs = self.certs.lock().unwrap();
+
+        match certs.iter_mut().find(|cert| cert.id == id) {
+            Some(cert) => {
+                cert.name = name;
+                cert.title = title;
+                cert.updated_at = Some(now);
+                Ok(1)
+            },
+            None => Ok(0)
+        }
+    }
+
+    async fn update_cert_email(&self, id: Uuid, old_email: String, new_email: String, quota: u64) -> Result<u64, CreationError> {
+        let now = self.clock.now();
+        let mut certs = self.certs.lock().unwrap();
+
+        if certs.iter().filter(|a| a.email == new_email).count() as u64 >= quota {
+            return Err(CreationError::QuotaExceeded);
+        }
+
+        match certs.iter_mut().find(|cert| cert.id == id && cert.email == old_email) {
+            Some(cert) => {
+                cert.email = new_email;
+                cert.updated_at = Some(now);
+                Ok(1)
+            },
+            None => Ok(0)
+        }
+    }
+
+    async fn update_cert_expiry(&self, id: Uuid, email: String, expires_at: Option<DateTime<Utc>>) -> Result<u64> {
+        let now = self.clock.now();
+        let mut certs = self.certs.lock().unwrap();
+
+        match certs.iter_mut().find(|cert| cert.id == id && cert.email == email) {
+            Some(cert) => {
+                cert.expires_at = expires_at;
+                cert.updated_at = Some(now);
+                Ok(1)
+            },
+            None => Ok(0)
+        }
+    }
+
+    async fn find_certs_expiring_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CertModel>> {
+        let mut certs: Vec<CertModel> = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cert.expires_at.is_some_and(|expires_at| from < expires_at && expires_at <= to))
+            .cloned()
+            .collect();
+
+        certs.sort_by_key(|cert| cert.expires_at);
+
+        Ok(certs)
+    }
+
+    async fn search_public_certs(&self, query: &str, query_variant: &str, limit: u64, offset: u64) -> Result<Vec<CertSearchHit>> {
+        let mut hits: Vec<CertSearchHit> = self.certs.lock().unwrap()
+            .iter()
+            .filter(|cert| cert.is_public)
+            .filter_map(|cert| {
+                let document = format!("{} {}", cert.name, cert.title).to_lowercase();
+                let rank = [query, query_variant]
+                    .iter()
+                    .filter(|variant| document.contains(*variant))
+                    .count();
+
+                (rank > 0).then(|| CertSearchHit {
+                    id: cert.id,
+                    name: cert.name.clone(),
+                    title: cert.title.clone(),
+                    rank: rank as f64
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.rank.total_cmp(&a.rank).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(
+            hits
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect()
+        )
+    }
+}
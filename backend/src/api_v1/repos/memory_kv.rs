@@ -0,0 +1,316 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex}
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use anyhow::{Result, anyhow};
+use crate::{
+    api_v1::repos::KvStore,
+    utils::clock::Clock
+};
+
+enum MemoryValue {
+    String(String),
+    List(VecDeque<String>),
+    Hash(HashMap<String, String>),
+    SortedSet(Vec<(f64, String)>)
+}
+
+struct MemoryEntry {
+    value: MemoryValue,
+    expires_at: Option<DateTime<Utc>>
+}
+
+/// A key-value storage in the process memory, the keys expire by the injected clock
+/// Used by the tests instead of Redis, so the expirations can be checked without waiting
+pub struct MemoryKvStore {
+    entries: Mutex<HashMap<String, MemoryEntry>>,
+    clock: Arc<dyn Clock>
+}
+
+/// Checks if the key matches the glob-style pattern with "*", "?" and "\" escapes
+fn matches_glob(pattern: &[char], key: &[char]) -> bool {
+    match pattern.first() {
+        None => key.is_empty(),
+        Some('*') => (0..=key.len()).any(|skip| matches_glob(&pattern[1..], &key[skip..])),
+        Some('?') => !key.is_empty() && matches_glob(&pattern[1..], &key[1..]),
+        Some('\\') if pattern.len() > 1 => key.first() == Some(&pattern[1]) && matches_glob(&pattern[2..], &key[1..]),
+        Some(c) => key.first() == Some(c) && matches_glob(&pattern[1..], &key[1..])
+    }
+}
+
+impl MemoryKvStore {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            clock
+        }
+    }
+
+    /// Runs the function over the entries after the expired ones are removed
+    fn with_entries<R>(&self, function: impl FnOnce(&mut HashMap<String, MemoryEntry>, DateTime<Utc>) -> R) -> R {
+        let now = self.clock.now();
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|_, entry| entry.expires_at.is_none_or(|expires_at| expires_at > now));
+
+        function(&mut entries, now)
+    }
+}
+
+fn wrong_type(key: &str) -> anyhow::Error {
+    anyhow!("The value by the key \"{}\" has another type", key)
+}
+
+#[async_trait]
+impl KvStore for MemoryKvStore {
+    async fn increase_by(&self, key: String, value: i64, expire: Duration) -> Result<u64> {
+        self.with_entries(|entries, now| {
+            let entry = entries.entry(key.clone()).or_insert(MemoryEntry {
+                value: MemoryValue::String("0".to_string()),
+                expires_at: None
+            });
+
+            let MemoryValue::String(current) = &entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            let new_value = current.parse::<i64>()? + value;
+            entry.value = MemoryValue::String(new_value.to_string());
+            entry.expires_at = Some(now + expire);
+
+            Ok(u64::try_from(new_value)?)
+        })
+    }
+
+    async fn get_string(&self, key: String) -> Result<Option<String>> {
+        self.with_entries(|entries, _| {
+            match entries.get(&key) {
+                Some(MemoryEntry { value: MemoryValue::String(value), .. }) => Ok(Some(value.clone())),
+                Some(_) => Err(wrong_type(&key)),
+                None => Ok(None)
+            }
+        })
+    }
+
+    async fn set_string(&self, key: String, value: String, expire: Duration, replace_expire: bool) -> Result<Option<()>> {
+        self.with_entries(|entries, now| {
+            if !replace_expire && entries.contains_key(&key) {
+                return Ok(None);
+            }
+
+            entries.insert(key, MemoryEntry {
+                value: MemoryValue::String(value),
+                expires_at: Some(now + expire)
+            });
+
+            Ok(Some(()))
+        })
+    }
+
+    async fn delete_by_key(&self, key: String) -> Result<u64> {
+        self.with_entries(|entries, _| Ok(entries.remove(&key).map_or(0, |_| 1)))
+    }
+
+    async fn get_ttl(&self, key: String) -> Result<(Duration, DateTime<Utc>)> {
+        self.with_entries(|entries, now| {
+            let expires_at = entries
+                .get(&key)
+                .and_then(|entry| entry.expires_at)
+                .unwrap_or(now);
+
+            Ok((expires_at - now, expires_at))
+        })
+    }
+
+    async fn lpush(&self, key: String, value: String) -> Result<u32> {
+        self.with_entries(|entries, _| {
+            let entry = entries.entry(key.clone()).or_insert(MemoryEntry {
+                value: MemoryValue::List(VecDeque::new()),
+                expires_at: None
+            });
+
+            let MemoryValue::List(list) = &mut entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            list.push_front(value);
+
+            Ok(list.len() as u32)
+        })
+    }
+
+    async fn schedule(&self, key: String, value: String, at: DateTime<Utc>) -> Result<()> {
+        self.with_entries(|entries, _| {
+            let entry = entries.entry(key.clone()).or_insert(MemoryEntry {
+                value: MemoryValue::SortedSet(Vec::new()),
+                expires_at: None
+            });
+
+            let MemoryValue::SortedSet(members) = &mut entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            members.retain(|(_, member)| *member != value);
+            members.push((at.timestamp() as f64, value));
+            members.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+            Ok(())
+        })
+    }
+
+    async fn take_due(&self, key: String, limit: i64) -> Result<Vec<String>> {
+        self.with_entries(|entries, now| {
+            let Some(entry) = entries.get_mut(&key) else {
+                return Ok(Vec::new());
+            };
+
+            let MemoryValue::SortedSet(members) = &mut entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            let due_count = members
+                .iter()
+                .take_while(|(score, _)| *score <= now.timestamp() as f64)
+                .take(limit.max(0) as usize)
+                .count();
+
+            Ok(members.drain(..due_count).map(|(_, member)| member).collect())
+        })
+    }
+
+    async fn hash_get_many(&self, key: String, fields: Vec<String>) -> Result<Vec<Option<String>>> {
+        self.with_entries(|entries, _| {
+            match entries.get(&key) {
+                Some(MemoryEntry { value: MemoryValue::Hash(hash), .. }) => Ok(
+                    fields.iter().map(|field| hash.get(field).cloned()).collect()
+                ),
+                Some(_) => Err(wrong_type(&key)),
+                None => Ok(vec![None; fields.len()])
+            }
+        })
+    }
+
+    async fn hash_set_many(&self, key: String, values: HashMap<String, String>, expire: Duration) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        self.with_entries(|entries, now| {
+            let entry = entries.entry(key.clone()).or_insert(MemoryEntry {
+                value: MemoryValue::Hash(HashMap::new()),
+                expires_at: None
+            });
+
+            let MemoryValue::Hash(hash) = &mut entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            hash.extend(values);
+            entry.expires_at = Some(now + expire);
+
+            Ok(())
+        })
+    }
+
+    async fn scan_keys(&self, pattern: String) -> Result<Vec<String>> {
+        let pattern: Vec<char> = pattern.chars().collect();
+
+        self.with_entries(|entries, _| {
+            Ok(
+                entries
+                    .keys()
+                    .filter(|key| matches_glob(&pattern, &key.chars().collect::<Vec<char>>()))
+                    .cloned()
+                    .collect()
+            )
+        })
+    }
+
+    async fn list_all(&self, key: String) -> Result<Vec<String>> {
+        self.with_entries(|entries, _| {
+            match entries.get(&key) {
+                Some(MemoryEntry { value: MemoryValue::List(list), .. }) => Ok(list.iter().cloned().collect()),
+                Some(_) => Err(wrong_type(&key)),
+                None => Ok(Vec::new())
+            }
+        })
+    }
+
+    async fn list_remove(&self, key: String, value: String) -> Result<u64> {
+        self.with_entries(|entries, _| {
+            let Some(entry) = entries.get_mut(&key) else {
+                return Ok(0);
+            };
+
+            let MemoryValue::List(list) = &mut entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            let length = list.len();
+            list.retain(|element| *element != value);
+
+            Ok((length - list.len()) as u64)
+        })
+    }
+
+    async fn list_len(&self, key: String) -> Result<u64> {
+        Ok(self.list_all(key).await?.len() as u64)
+    }
+
+    async fn list_trim(&self, key: String, start: i64, stop: i64) -> Result<()> {
+        self.with_entries(|entries, _| {
+            let Some(entry) = entries.get_mut(&key) else {
+                return Ok(());
+            };
+
+            let MemoryValue::List(list) = &mut entry.value else {
+                return Err(wrong_type(&key));
+            };
+
+            // Negative indexes count from the end like in Redis
+            let length = list.len() as i64;
+            let start = if start < 0 { (length + start).max(0) } else { start.min(length) };
+            let stop = if stop < 0 { length + stop } else { stop.min(length - 1) };
+
+            *list = list
+                .iter()
+                .skip(start as usize)
+                .take((stop - start + 1).max(0) as usize)
+                .cloned()
+                .collect();
+
+            Ok(())
+        })
+    }
+
+    async fn sorted_set_len(&self, key: String) -> Result<u64> {
+        self.with_entries(|entries, _| {
+            match entries.get(&key) {
+                Some(MemoryEntry { value: MemoryValue::SortedSet(members), .. }) => Ok(members.len() as u64),
+                Some(_) => Err(wrong_type(&key)),
+                None => Ok(0)
+            }
+        })
+    }
+
+    async fn acquire_lease(&self, key: String, owner: String, expire: Duration) -> Result<bool> {
+        self.with_entries(|entries, now| {
+            match entries.get_mut(&key) {
+                Some(MemoryEntry { value: MemoryValue::String(current), expires_at }) if *current == owner => {
+                    *expires_at = Some(now + expire);
+                    Ok(true)
+                },
+                Some(_) => Ok(false),
+                None => {
+                    entries.insert(key, MemoryEntry {
+                        value: MemoryValue::String(owner),
+                        expires_at: Some(now + expire)
+                    });
+                    Ok(true)
+                }
+            }
+        })
+    }
+}
@@ -1,5 +1,17 @@
 mod cert;
+mod cert_store;
+mod erasure;
+mod kv_store;
+mod memory_cert;
+mod memory_kv;
 mod redis;
+mod webhook;
 
 pub use cert::*;
+pub use cert_store::*;
+pub use erasure::*;
+pub use kv_store::*;
+pub use memory_cert::*;
+pub use memory_kv::*;
 pub use redis::*;
+pub use webhook::*;
@@ -1,13 +1,19 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use chrono::{DateTime, Duration, Utc};
 use fred::{
     prelude::*, 
     types::{
         Expiration, 
+        Key, 
         Value
     }
 };
-use crate::utils::log_error::ResultLogger;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use crate::{
+    api_v1::repos::KvStore, 
+    utils::log_error::ResultLogger
+};
 use anyhow::Result;
 use thiserror::Error;
 
@@ -27,39 +33,11 @@ impl RedisRepo {
             redis: redis_client
         }
     }
+}
 
-    /// Increases the value of the counter by 1
-    pub async fn increase_by_one(&self, key: String, expire: Duration) -> Result<u64> {
-        let pipeline = self.redis.multi();
-
-        let result: Value = pipeline
-            .incr(&key)
-            .await
-            .log_with_place_on_error("increase_by_one")?;
-
-        if !result.is_queued() {
-            return Err(RedisRepoError::DidNotQueued.into());
-        }
-
-        let result: Value = pipeline
-            .expire(&key, expire.num_seconds(), None)
-            .await
-            .log_with_place_on_error("increase_by_one")?;
-
-        if !result.is_queued() {
-            return Err(RedisRepoError::DidNotQueued.into());
-        }
-
-        let (new_value, _): (u64, u8) = pipeline.exec(true)
-            .await
-            .log_with_place_on_error("increase_by_one")?;
-
-        Ok(new_value)
-    }
-
-    /// Increases the value of the counter by specified value
-    /// Set the negative value to decrease the counter value
-    pub async fn increase_by(&self, key: String, value: i64, expire: Duration) -> Result<u64> {
+#[async_trait]
+impl KvStore for RedisRepo {
+    async fn increase_by(&self, key: String, value: i64, expire: Duration) -> Result<u64> {
         let pipeline = self.redis.multi();
 
         let result: Value = pipeline
@@ -87,23 +65,15 @@ impl RedisRepo {
         Ok(new_value)
     }
 
-    /// Returns the value of stored in the Redis storage variable
-    pub async fn get_value<T: FromValue>(&self, key: String) -> Result<Option<T>> {
+    async fn get_string(&self, key: String) -> Result<Option<String>> {
         Ok(
-            self.redis.get::<Option<T>, String>(key)
+            self.redis.get::<Option<String>, String>(key)
                 .await
-                .log_with_place_on_error("get_value")?
+                .log_with_place_on_error("get_string")?
         )
     }
 
-    /// Changes the value of the variable or create a variable with specified value
-    /// replace_expire = true will change the TTL if the variable already exists
-    /// replace_expire = false won't change the TTL if the variable already exists
-    pub async fn set_value<T>(&self, key: String, value: T, expire: Duration, replace_expire: bool) -> Result<Option<()>> 
-    where 
-        T: TryInto<Value> + Send,
-        T::Error: Into<Error> + Send
-    {
+    async fn set_string(&self, key: String, value: String, expire: Duration, replace_expire: bool) -> Result<Option<()>> {
         let a: Option<()> = self.redis.set(
             key, 
             value,
@@ -116,39 +86,12 @@ impl RedisRepo {
             false
         )
             .await
-            .log_with_place_on_error("set_value")?;
+            .log_with_place_on_error("set_string")?;
 
         Ok(a)
     }
 
-    /// Changes the value of the variable or create a variable with specified value
-    /// Returns the previous value of the variable if it already exists
-    /// replace_expire = true will change the TTL if the variable already exists
-    /// replace_expire = false won't change the TTL if the variable already exists
-    pub async fn set_value_return_previous<T>(&self, key: String, value: T, expire: Duration, replace_expire: bool) -> Result<Option<T>> 
-    where 
-        T: TryInto<Value> + FromValue + Send,
-        T::Error: Into<Error> + Send
-    {
-        let a: Option<T> = self.redis.set(
-            key, 
-            value,
-            Some(Expiration::EX(expire.num_seconds())), 
-            if replace_expire { 
-                None 
-            } else { 
-                Some(SetOptions::NX) 
-            }, 
-            false
-        )
-            .await
-            .log_with_place_on_error("set_value_return_previous")?;
-
-        Ok(a)
-    }
-
-    /// Removes the variable by the key from the Redis storage
-    pub async fn delete_by_key(&self, key: String) -> Result<u64> {
+    async fn delete_by_key(&self, key: String) -> Result<u64> {
         let count: u64 = self.redis
             .del(&key)
             .await
@@ -157,8 +100,7 @@ impl RedisRepo {
         Ok(count)
     }
 
-    /// Returns the time to left and UTC expiration time of the specified variable
-    pub async fn get_ttl(&self, key: String) -> Result<(Duration, DateTime<Utc>)> {
+    async fn get_ttl(&self, key: String) -> Result<(Duration, DateTime<Utc>)> {
         let timestamp_milli: i64 = self.redis
             .pexpire_time(key)
             .await
@@ -169,16 +111,156 @@ impl RedisRepo {
         Ok((timestamp - now, timestamp))
     }
 
-    /// Adds the object in a queue by the specified key
-    pub async fn lpush<T>(&self, key: String, value: T) -> Result<u32>
-    where 
-        T: TryInto<Value> + Send,
-        T::Error: Into<Error> + Send
-    {
+    async fn lpush(&self, key: String, value: String) -> Result<u32> {
         let new_size: u32 = self.redis.lpush(key, value)
             .await
             .log_with_place_on_error("lpush")?;
 
         Ok(new_size)
     }
+
+    async fn schedule(&self, key: String, value: String, at: DateTime<Utc>) -> Result<()> {
+        let _: u64 = self.redis.zadd(key, None, None, false, false, (at.timestamp() as f64, value))
+            .await
+            .log_with_place_on_error("schedule")?;
+
+        Ok(())
+    }
+
+    async fn take_due(&self, key: String, limit: i64) -> Result<Vec<String>> {
+        let now = Utc::now().timestamp() as f64;
+
+        let due_members: Vec<String> = self.redis
+            .zrangebyscore(&key, "-inf", now, false, Some((0, limit)))
+            .await
+            .log_with_place_on_error("take_due")?;
+
+        let mut taken_members = Vec::with_capacity(due_members.len());
+        for member in due_members {
+            let removed: u64 = self.redis
+                .zrem(&key, member.clone())
+                .await
+                .log_with_place_on_error("take_due")?;
+
+            if removed > 0 {
+                taken_members.push(member);
+            }
+        }
+
+        Ok(taken_members)
+    }
+
+    async fn hash_get_many(&self, key: String, fields: Vec<String>) -> Result<Vec<Option<String>>> {
+        if fields.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let values: Vec<Option<String>> = self.redis
+            .hmget(key, fields)
+            .await
+            .log_with_place_on_error("hash_get_many")?;
+
+        Ok(values)
+    }
+
+    async fn hash_set_many(&self, key: String, values: HashMap<String, String>, expire: Duration) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let _: u64 = self.redis
+            .hset(&key, values)
+            .await
+            .log_with_place_on_error("hash_set_many")?;
+
+        let _: u8 = self.redis
+            .expire(&key, expire.num_seconds(), None)
+            .await
+            .log_with_place_on_error("hash_set_many")?;
+
+        Ok(())
+    }
+
+    async fn scan_keys(&self, pattern: String) -> Result<Vec<String>> {
+        let keys: Vec<Key> = self.redis
+            .scan_buffered(pattern, Some(100), None)
+            .try_collect()
+            .await
+            .log_with_place_on_error("scan_keys")?;
+
+        Ok(
+            keys
+                .into_iter()
+                .filter_map(|key| key.into_string())
+                .collect()
+        )
+    }
+
+    async fn list_all(&self, key: String) -> Result<Vec<String>> {
+        let elements: Vec<String> = self.redis
+            .lrange(key, 0, -1)
+            .await
+            .log_with_place_on_error("list_all")?;
+
+        Ok(elements)
+    }
+
+    async fn list_remove(&self, key: String, value: String) -> Result<u64> {
+        let count: u64 = self.redis
+            .lrem(key, 0, value)
+            .await
+            .log_with_place_on_error("list_remove")?;
+
+        Ok(count)
+    }
+
+    async fn list_len(&self, key: String) -> Result<u64> {
+        let length: u64 = self.redis
+            .llen(key)
+            .await
+            .log_with_place_on_error("list_len")?;
+
+        Ok(length)
+    }
+
+    async fn sorted_set_len(&self, key: String) -> Result<u64> {
+        let length: u64 = self.redis
+            .zcard(key)
+            .await
+            .log_with_place_on_error("sorted_set_len")?;
+
+        Ok(length)
+    }
+
+    async fn list_trim(&self, key: String, start: i64, stop: i64) -> Result<()> {
+        let _: () = self.redis
+            .ltrim(key, start, stop)
+            .await
+            .log_with_place_on_error("list_trim")?;
+
+        Ok(())
+    }
+
+    async fn acquire_lease(&self, key: String, owner: String, expire: Duration) -> Result<bool> {
+        // Checking and taking the lease must be atomic, otherwise two owners may take it at once
+        let script = r#"
+            local current = redis.call('GET', KEYS[1])
+            if current == false then
+                redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+                return 1
+            end
+            if current == ARGV[1] then
+                redis.call('EXPIRE', KEYS[1], ARGV[2])
+                return 1
+            end
+            return 0
+        "#;
+
+        let acquired: u8 = self.redis
+            .eval(script, vec![key], vec![owner, expire.num_seconds().to_string()])
+            .await
+            .log_with_place_on_error("acquire_lease")?;
+
+        Ok(acquired == 1)
+    }
 }
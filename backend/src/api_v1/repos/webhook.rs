@@ -0,0 +1,253 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use anyhow::Result;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::Set, 
+    ColumnTrait, 
+    DatabaseConnection, 
+    EntityTrait, 
+    QueryFilter, 
+    QueryOrder, 
+    QuerySelect
+};
+use crate::{
+    api_v1::models::{
+        webhook, 
+        webhook_delivery
+    }, 
+    utils::log_error::ResultLogger
+};
+
+pub struct WebhookRepo {
+    database: Arc<DatabaseConnection>
+}
+
+#[derive(Clone)]
+pub struct WebhookModel {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>
+}
+
+#[derive(Clone)]
+pub struct WebhookDeliveryModel {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub response_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>
+}
+
+impl From<webhook::Model> for WebhookModel {
+    fn from(model: webhook::Model) -> Self {
+        Self {
+            id: model.id,
+            url: model.url,
+            secret: model.secret,
+            events: model.events
+                .split(",")
+                .filter(|event| !event.is_empty())
+                .map(|event| event.to_string())
+                .collect(),
+            created_at: model.created_at
+        }
+    }
+}
+
+impl From<webhook_delivery::Model> for WebhookDeliveryModel {
+    fn from(model: webhook_delivery::Model) -> Self {
+        Self {
+            id: model.id,
+            webhook_id: model.webhook_id,
+            event: model.event,
+            payload: model.payload,
+            status: model.status,
+            attempts: model.attempts,
+            response_code: model.response_code,
+            last_error: model.last_error,
+            created_at: model.created_at,
+            next_attempt_at: model.next_attempt_at,
+            delivered_at: model.delivered_at
+        }
+    }
+}
+
+impl WebhookRepo {
+    pub fn new(database: Arc<DatabaseConnection>) -> Self {
+        Self {
+            database
+        }
+    }
+
+    /// Saves the webhook subscription to the data base
+    pub async fn create_webhook(&self, webhook: WebhookModel) -> Result<Uuid> {
+        let model_to_insert = webhook::ActiveModel {
+            id: Set(webhook.id),
+            url: Set(webhook.url),
+            secret: Set(webhook.secret),
+            events: Set(webhook.events.join(",")),
+            created_at: Set(webhook.created_at)
+        };
+
+        let created_webhook = webhook::Entity::insert(model_to_insert)
+            .exec(self.database.as_ref())
+            .await
+            .log_with_place_on_error("create_webhook")?;
+
+        Ok(created_webhook.last_insert_id)
+    }
+
+    /// Returns a webhook subscription by the ID
+    pub async fn find_webhook_by_id(&self, id: Uuid) -> Result<Option<WebhookModel>> {
+        let search_result = webhook::Entity::find_by_id(id)
+            .limit(1)
+            .one(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_webhook_by_id")?;
+
+        Ok(search_result.map(WebhookModel::from))
+    }
+
+    /// Returns all webhook subscriptions ordered by the creation time
+    pub async fn find_all_webhooks(&self) -> Result<Vec<WebhookModel>> {
+        let search_result = webhook::Entity::find()
+            .order_by_asc(webhook::Column::CreatedAt)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_all_webhooks")?;
+
+        Ok(search_result.into_iter().map(WebhookModel::from).collect())
+    }
+
+    /// Returns all webhook subscriptions that are subscribed to the event
+    pub async fn find_webhooks_by_event(&self, event: &str) -> Result<Vec<WebhookModel>> {
+        let webhooks = self.find_all_webhooks().await?;
+
+        Ok(
+            webhooks
+                .into_iter()
+                .filter(|webhook| webhook.events.iter().any(|a| a == event))
+                .collect()
+        )
+    }
+
+    /// Removes a webhook subscription and its delivery log by the ID
+    /// Returns 1 if the webhook was removed and 0 if the webhook wasn't
+    pub async fn remove_webhook_by_id(&self, id: Uuid) -> Result<u64> {
+        webhook_delivery::Entity::delete_many()
+            .filter(webhook_delivery::Column::WebhookId.eq(id))
+            .exec(self.database.as_ref())
+            .await
+            .log_with_place_on_error("remove_webhook_by_id")?;
+
+        Ok(
+            webhook::Entity::delete_by_id(id)
+                .exec(self.database.as_ref())
+                .await
+                .log_with_place_on_error("remove_webhook_by_id")?
+                .rows_affected
+        )
+    }
+
+    /// Saves the delivery to the log
+    pub async fn create_delivery(&self, delivery: WebhookDeliveryModel) -> Result<Uuid> {
+        let model_to_insert = webhook_delivery::ActiveModel {
+            id: Set(delivery.id),
+            webhook_id: Set(delivery.webhook_id),
+            event: Set(delivery.event),
+            payload: Set(delivery.payload),
+            status: Set(delivery.status),
+            attempts: Set(delivery.attempts),
+            response_code: Set(delivery.response_code),
+            last_error: Set(delivery.last_error),
+            created_at: Set(delivery.created_at),
+            next_attempt_at: Set(delivery.next_attempt_at),
+            delivered_at: Set(delivery.delivered_at)
+        };
+
+        let created_delivery = webhook_delivery::Entity::insert(model_to_insert)
+            .exec(self.database.as_ref())
+            .await
+            .log_with_place_on_error("create_delivery")?;
+
+        Ok(created_delivery.last_insert_id)
+    }
+
+    /// Returns a delivery by the ID
+    pub async fn find_delivery_by_id(&self, id: Uuid) -> Result<Option<WebhookDeliveryModel>> {
+        let search_result = webhook_delivery::Entity::find_by_id(id)
+            .limit(1)
+            .one(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_delivery_by_id")?;
+
+        Ok(search_result.map(WebhookDeliveryModel::from))
+    }
+
+    /// Returns the latest deliveries of the webhook subscription
+    pub async fn find_deliveries_by_webhook(&self, webhook_id: Uuid, limit: u64) -> Result<Vec<WebhookDeliveryModel>> {
+        let search_result = webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::WebhookId.eq(webhook_id))
+            .order_by_desc(webhook_delivery::Column::CreatedAt)
+            .limit(limit)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_deliveries_by_webhook")?;
+
+        Ok(search_result.into_iter().map(WebhookDeliveryModel::from).collect())
+    }
+
+    /// Returns the deliveries with the status, e.g. the pending ones
+    pub async fn find_deliveries_by_status(&self, status: &str) -> Result<Vec<WebhookDeliveryModel>> {
+        let search_result = webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::Status.eq(status))
+            .order_by_asc(webhook_delivery::Column::CreatedAt)
+            .all(self.database.as_ref())
+            .await
+            .log_with_place_on_error("find_deliveries_by_status")?;
+
+        Ok(search_result.into_iter().map(WebhookDeliveryModel::from).collect())
+    }
+
+    /// Saves the result of the delivery attempt
+    pub async fn update_delivery_attempt(&self, delivery: &WebhookDeliveryModel) -> Result<()> {
+        webhook_delivery::ActiveModel {
+            id: Set(delivery.id),
+            status: Set(delivery.status.clone()),
+            attempts: Set(delivery.attempts),
+            response_code: Set(delivery.response_code),
+            last_error: Set(delivery.last_error.clone()),
+            next_attempt_at: Set(delivery.next_attempt_at),
+            delivered_at: Set(delivery.delivered_at),
+            ..Default::default()
+        }
+            .update(self.database.as_ref())
+            .await
+            .log_with_place_on_error("update_delivery_attempt")?;
+
+        Ok(())
+    }
+
+    /// Removes the deliveries whose payload mentions the value (e.g. a certificate ID)
+    /// Returns the number of removed deliveries
+    pub async fn remove_deliveries_mentioning(&self, value: &str) -> Result<u64> {
+        Ok(
+            webhook_delivery::Entity::delete_many()
+                .filter(webhook_delivery::Column::Payload.contains(value))
+                .exec(self.database.as_ref())
+                .await
+                .log_with_place_on_error("remove_deliveries_mentioning")?
+                .rows_affected
+        )
+    }
+}
@@ -0,0 +1,38 @@
+use actix_web::{HttpRequest, http::header};
+use crate::configs;
+
+/// Compares two strings in a constant time to not leak the token through the response timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Returns the token from the "Authorization: Bearer <token>" header
+pub fn get_bearer_token(request: &HttpRequest) -> Option<&str> {
+    request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim())
+}
+
+/// Checks if the request contains the "Authorization: Bearer <ADMIN_TOKEN>" header
+/// Always returns false if the administrative token is not configured
+pub fn is_admin_request(request: &HttpRequest) -> bool {
+    let admin_token = match configs::get_admin_token() {
+        Some(token) => token,
+        None => {
+            return false;
+        }
+    };
+
+    match get_bearer_token(request) {
+        Some(token) => constant_time_eq(token.as_bytes(), admin_token.as_bytes()),
+        None => false
+    }
+}
@@ -1,13 +1,7 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 use anyhow::Result;
 use chrono::Duration;
-use fred::{
-    error::Error, 
-    types::{
-        FromValue, 
-        Value
-    }
-};
-use crate::api_v1::repos::RedisRepo;
+use crate::api_v1::repos::KvStore;
 
 /// Turns a cache key into the specialized Redis key
 pub fn get_key(
@@ -17,23 +11,45 @@ pub fn get_key(
 }
 
 /// Returns the stored cache value by the cache key
-pub async fn get_cache<T: FromValue>(
-    redis: &RedisRepo,
+pub async fn get_cache<T: FromStr>(
+    redis: &dyn KvStore,
     key: String
 ) -> Result<Option<T>> {
     redis.get_value(get_key(&key)).await
 }
 
 /// Caches a value by the cache key for a some time
-pub async fn set_cache<T>(
-    redis: &RedisRepo,
+pub async fn set_cache<T: Display>(
+    redis: &dyn KvStore,
     key: &str, value: T, exp: Duration
-) -> Result<()>
-where 
-    T: TryInto<Value> + Send,
-    T::Error: Into<Error> + Send
-{
+) -> Result<()> {
     redis.set_value(get_key(key), value, exp, true).await?;
 
     Ok(())
 }
+
+/// Returns the stored cache values of the fields in the cache hash, None for every missing field
+pub async fn get_cache_fields(
+    redis: &dyn KvStore,
+    key: &str, fields: Vec<String>
+) -> Result<Vec<Option<String>>> {
+    redis.hash_get_many(get_key(key), fields).await
+}
+
+/// Caches the values of the fields in the cache hash for a some time
+pub async fn set_cache_fields(
+    redis: &dyn KvStore,
+    key: &str, values: HashMap<String, String>, exp: Duration
+) -> Result<()> {
+    redis.hash_set_many(get_key(key), values, exp).await
+}
+
+/// Removes the cache value by the cache key
+pub async fn remove_cache(
+    redis: &dyn KvStore,
+    key: &str
+) -> Result<()> {
+    redis.delete_by_key(get_key(key)).await?;
+
+    Ok(())
+}
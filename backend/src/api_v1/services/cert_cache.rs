@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+use crate::api_v1::{
+    repos::{
+        CertStore,
+        KvStore
+    },
+    services::cache
+};
+
+/// The cached copy lives shortly, so a copy written by a read that raced a change doesn't stay for long
+const CERT_CACHE_EXPIRATION_MINUTES: i64 = 10;
+
+/// The public part of the certificate that the read endpoint returns
+pub struct CachedCert {
+    pub name: String,
+    pub title: String,
+    pub kind: String,
+    pub modified_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>
+}
+
+fn get_cert_key(id: &Uuid) -> String {
+    format!("cert:{}", id)
+}
+
+/// Returns the public part of the certificate from the cache or from the data base
+/// A certificate found in the data base is cached
+pub async fn get_cert(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    id: Uuid
+) -> Result<Option<CachedCert>> {
+    let key = get_cert_key(&id);
+
+    // The cache is only a shortcut, so its errors send the request to the data base
+    let cached_fields = cache::get_cache_fields(
+        redis,
+        &key,
+        vec![
+            "name".to_string(), "title".to_string(), "kind".to_string(),
+            "modified_at".to_string(), "expires_at".to_string()
+        ]
+    ).await.unwrap_or_default();
+
+    // The expiration is empty for the certificates that never expire
+    if let [Some(name), Some(title), Some(kind), Some(modified_at), Some(expires_at)] = cached_fields.as_slice() {
+        let modified_at = modified_at.parse().ok().and_then(|a| DateTime::from_timestamp(a, 0));
+        let expires_at = match expires_at.as_str() {
+            "" => Some(None),
+            expires_at => expires_at.parse().ok().and_then(|a| DateTime::from_timestamp(a, 0)).map(Some)
+        };
+
+        if let (Some(modified_at), Some(expires_at)) = (modified_at, expires_at) {
+            return Ok(Some(CachedCert {
+                name: name.clone(),
+                title: title.clone(),
+                kind: kind.clone(),
+                modified_at,
+                expires_at
+            }));
+        }
+    }
+
+    let Some(cert) = cert_repo.find_cert_by_id(id).await? else {
+        return Ok(None);
+    };
+
+    let cached = CachedCert {
+        name: cert.name,
+        title: cert.title,
+        kind: cert.kind,
+        modified_at: cert.updated_at.unwrap_or(cert.created_at),
+        expires_at: cert.expires_at
+    };
+
+    let _ = cache::set_cache_fields(
+        redis,
+        &key,
+        HashMap::from([
+            ("name".to_string(), cached.name.clone()),
+            ("title".to_string(), cached.title.clone()),
+            ("kind".to_string(), cached.kind.clone()),
+            ("modified_at".to_string(), cached.modified_at.timestamp().to_string()),
+            ("expires_at".to_string(), cached.expires_at.map(|a| a.timestamp().to_string()).unwrap_or_default())
+        ]),
+        Duration::minutes(CERT_CACHE_EXPIRATION_MINUTES)
+    ).await;
+
+    Ok(Some(cached))
+}
+
+/// Removes the cached copy of the certificate, must be called after every change or removal
+pub async fn invalidate_cert(
+    redis: &dyn KvStore,
+    id: &Uuid
+) -> Result<()> {
+    cache::remove_cache(redis, &get_cert_key(id)).await
+}
@@ -0,0 +1,162 @@
+use std::sync::OnceLock;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::prelude::*;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use anyhow::Result;
+use crate::{
+    api_v1::repos::KvStore,
+    configs
+};
+
+/// How long the client has to solve the challenge and send the solution
+pub const CHALLENGE_LIFETIME_SECONDS: i64 = 300;
+
+/// Every time the accepted solutions per minute double over this amount, the difficulty grows by one bit
+const LOAD_PER_DIFFICULTY_STEP: u64 = 30;
+
+/// The longest solution that is checked, the solvers send a counter
+const MAX_SOLUTION_LENGTH: usize = 64;
+
+/// A signed puzzle: find a solution that makes SHA-256 of "{challenge}:{solution}" start with `difficulty` zero bits
+pub struct Challenge {
+    pub challenge: String,
+    pub difficulty: u32,
+    pub expires_at: i64
+}
+
+/// Returns the key that signs the challenges
+/// Without CHALLENGE_SECRET a random key lives as long as the process, so the replicas must share the variable
+fn get_secret() -> &'static str {
+    static SECRET: OnceLock<String> = OnceLock::new();
+
+    SECRET.get_or_init(|| {
+        configs::get_challenge_secret().unwrap_or_else(|| {
+            log::warn!("CHALLENGE_SECRET is not set, the challenges are signed with a random key of this process");
+            hex::encode(OsRng.r#gen::<[u8; 32]>())
+        })
+    })
+}
+
+fn sign(payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(get_secret().as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(payload.as_bytes());
+
+    mac
+}
+
+fn get_load_key(minute: i64) -> String {
+    format!("challenge:load:{}", minute)
+}
+
+/// Counts the leading zero bits of the hash
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+
+    for byte in hash {
+        bits += byte.leading_zeros();
+
+        if *byte != 0 {
+            break;
+        }
+    }
+
+    bits
+}
+
+/// Returns the difficulty for the current load
+/// The load is the amount of solutions accepted during the current and the previous minute
+pub async fn get_difficulty(redis: &dyn KvStore, now: DateTime<Utc>) -> u32 {
+    let (min, max) = configs::get_challenge_difficulty_range();
+    let minute = now.timestamp() / 60;
+
+    let mut load = 0;
+    for key in [get_load_key(minute), get_load_key(minute - 1)] {
+        load += redis.get_value::<u64>(key).await.ok().flatten().unwrap_or(0);
+    }
+
+    let mut extra_bits = 0;
+    while extra_bits < max - min && load >= LOAD_PER_DIFFICULTY_STEP << extra_bits {
+        extra_bits += 1;
+    }
+
+    min + extra_bits
+}
+
+/// Issues a new signed challenge with the difficulty for the current load
+pub async fn issue_challenge(redis: &dyn KvStore, now: DateTime<Utc>) -> Challenge {
+    let difficulty = get_difficulty(redis, now).await;
+    let expires_at = now.timestamp() + CHALLENGE_LIFETIME_SECONDS;
+    let nonce = hex::encode(OsRng.r#gen::<[u8; 16]>());
+
+    let payload = format!("{}.{}.{}", nonce, difficulty, expires_at);
+    let signature = hex::encode(sign(&payload).finalize().into_bytes());
+
+    Challenge {
+        challenge: format!("{}.{}", payload, signature),
+        difficulty,
+        expires_at
+    }
+}
+
+/// Checks the signature, the expiration and the solution of the challenge
+/// An accepted solution can't be used again and adds to the load
+pub async fn verify_solution(
+    redis: &dyn KvStore,
+    challenge: &str, solution: &str,
+    now: DateTime<Utc>
+) -> Result<bool> {
+    if solution.is_empty() || solution.len() > MAX_SOLUTION_LENGTH {
+        return Ok(false);
+    }
+
+    let Some((payload, signature)) = challenge.rsplit_once('.') else {
+        return Ok(false);
+    };
+
+    let is_signed = hex::decode(signature)
+        .is_ok_and(|signature| sign(payload).verify_slice(&signature).is_ok());
+
+    if !is_signed {
+        return Ok(false);
+    }
+
+    let parts: Vec<&str> = payload.split('.').collect();
+    let [nonce, difficulty, expires_at] = parts.as_slice() else {
+        return Ok(false);
+    };
+
+    let (Ok(difficulty), Ok(expires_at)) = (difficulty.parse::<u32>(), expires_at.parse::<i64>()) else {
+        return Ok(false);
+    };
+
+    if expires_at <= now.timestamp() {
+        return Ok(false);
+    }
+
+    let hash = Sha256::digest(format!("{}:{}", challenge, solution).as_bytes());
+    if leading_zero_bits(&hash) < difficulty {
+        return Ok(false);
+    }
+
+    // The nonce is remembered until the challenge expires, so the solution works only once
+    let first_use = redis.set_value(
+        format!("challenge:used:{}", nonce),
+        1,
+        Duration::seconds(expires_at - now.timestamp()),
+        false
+    ).await?;
+
+    if first_use.is_none() {
+        return Ok(false);
+    }
+
+    let _ = redis.increase_by_one(
+        get_load_key(now.timestamp() / 60),
+        Duration::minutes(2)
+    ).await;
+
+    Ok(true)
+}
@@ -0,0 +1,107 @@
+use std::{
+    future::{Ready, ready},
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock
+};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, dev::Payload, http::header::HeaderMap};
+use ipnet::IpNet;
+use crate::{
+    api_v1::types::errors::Errors,
+    configs
+};
+
+/// The address of the client that sent the request
+/// The forwarded headers are taken into account only if the TCP peer is a trusted proxy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl FromRequest for ClientIp {
+    type Error = Errors;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            request.extensions()
+                .get::<ClientIp>()
+                .copied()
+                .ok_or(Errors::InternalServer { what: "IP address" })
+        )
+    }
+}
+
+/// Returns the trusted proxies, the list is read once
+fn get_trusted_proxies() -> &'static [IpNet] {
+    static TRUSTED_PROXIES: OnceLock<Vec<IpNet>> = OnceLock::new();
+
+    TRUSTED_PROXIES.get_or_init(configs::get_trusted_proxies)
+}
+
+fn is_trusted(ip: &IpAddr) -> bool {
+    get_trusted_proxies()
+        .iter()
+        .any(|network| network.contains(ip))
+}
+
+/// Parses an address from a header or the connection info, a port, quotes and the IPv6 brackets are allowed
+pub fn parse_ip(raw: &str) -> Option<IpAddr> {
+    let raw = raw.trim().trim_matches('"');
+
+    raw.parse::<IpAddr>()
+        .ok()
+        .or_else(|| raw.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .or_else(|| raw.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().ok())
+        .map(|ip| ip.to_canonical())
+}
+
+/// Returns the chain of the forwarded addresses, from the client to the latest proxy
+/// The "for" parameters of the Forwarded header go first, then X-Forwarded-For and X-Real-IP
+fn get_forwarded_chain(headers: &HeaderMap) -> Vec<IpAddr> {
+    let forwarded: Vec<IpAddr> = headers.get_all("forwarded")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .flat_map(|element| element.split(';'))
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+        .filter_map(|(_, value)| parse_ip(value))
+        .collect();
+
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    let forwarded_for: Vec<IpAddr> = headers.get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(parse_ip)
+        .collect();
+
+    if !forwarded_for.is_empty() {
+        return forwarded_for;
+    }
+
+    headers.get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_ip)
+        .into_iter()
+        .collect()
+}
+
+/// Resolves the client address of the request
+/// The forwarded chain is read from the end and every trusted proxy in it is skipped,
+/// so the client can't put another address before the one the proxy has added
+pub fn resolve_client_ip(request: &HttpRequest) -> Option<IpAddr> {
+    let peer = request.peer_addr()?.ip().to_canonical();
+
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+
+    let chain = get_forwarded_chain(request.headers());
+
+    chain.iter()
+        .rev()
+        .find(|ip| !is_trusted(ip))
+        .or(chain.first())
+        .copied()
+        .or(Some(peer))
+}
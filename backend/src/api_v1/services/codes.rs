@@ -1,8 +1,13 @@
 use chrono::{DateTime, Duration, Utc};
 use rand::prelude::*;
 use rand::rngs::OsRng;
+use uuid::Uuid;
 use validator::ValidationError;
-use crate::api_v1::repos::RedisRepo;
+use crate::api_v1::{
+    repos::KvStore,
+    services::rate_limits,
+    types::errors::Errors
+};
 use anyhow::{Result, Error};
 
 pub const EMAIL_CODE_LETTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
@@ -91,23 +96,38 @@ pub fn validate_email_token(token: &str) -> Result<(), ValidationError> {
 }
 
 pub enum VerificationResult {
-    Ok { purpose: String },
+    /// The certificate ID is stored for the purposes that manage one certificate
+    Ok { purpose: String, cert_id: Option<Uuid> },
     NotFound,
     InvalidToken,
     InvalidCode,
     UnknownError(Error)
 }
 
+/// Returns the storage key of the code with the purpose
+/// The transfer codes are kept apart, so a transfer to the address can't replace the code its holder requested
+pub fn get_code_key(email: &str, purpose: &str) -> String {
+    match purpose {
+        "accept_transfer" => format!("transfer_code:{}", email),
+        _ => format!("confirm_code:{}", email)
+    }
+}
+
+/// Returns all the storage keys the codes of the email address can be kept under
+pub fn get_code_keys(email: &str) -> [String; 2] {
+    [get_code_key(email, "create"), get_code_key(email, "accept_transfer")]
+}
+
 /// Validates code and token, compares stored values with the user's ones
 pub async fn verify_email_code(
-    redis: &RedisRepo,
-    email: &str, token: &str, code: &str
+    redis: &dyn KvStore,
+    email: &str, token: &str, code: &str, expected_purpose: &str
 ) -> VerificationResult {
     if validate_email_code(code).is_err() || validate_email_token(token).is_err() {
         return VerificationResult::InvalidCode;
     }
 
-    let key = format!("confirm_code:{}", email);
+    let key = get_code_key(email, expected_purpose);
 
     let redis_code_data = match redis.get_value::<String>(key).await {
         Ok(a) => a,
@@ -141,6 +161,8 @@ pub async fn verify_email_code(
                 }
             };
 
+            let redis_cert_id = split.next().and_then(|a| Uuid::parse_str(a).ok());
+
             if redis_token != token {
                 return VerificationResult::InvalidToken;
             }
@@ -150,7 +172,8 @@ pub async fn verify_email_code(
             }
 
             VerificationResult::Ok {
-                purpose: redis_purpose.to_string()
+                purpose: redis_purpose.to_string(),
+                cert_id: redis_cert_id
             }
         },
         None => VerificationResult::NotFound,
@@ -158,12 +181,16 @@ pub async fn verify_email_code(
 }
 
 /// Stores the code and all details about it in the storage to be ready for use for confirmation
+/// The code that manages one certificate is bound to its ID, so it can't be used for another certificate of the email
 pub async fn save_code_in_storage(
-    redis: &RedisRepo,
-    email: &str, purpose: &str, generated_code: &str, generated_token: &str
+    redis: &dyn KvStore,
+    email: &str, purpose: &str, cert_id: Option<&Uuid>, generated_code: &str, generated_token: &str, now: DateTime<Utc>
 ) -> Result<DateTime<Utc>> {
-    let key = format!("confirm_code:{}", email);
-    let value = format!("{}:{}:{}", generated_token, generated_code, purpose.to_string());
+    let key = get_code_key(email, purpose);
+    let value = match cert_id {
+        Some(cert_id) => format!("{}:{}:{}:{}", generated_token, generated_code, purpose, cert_id),
+        None => format!("{}:{}:{}", generated_token, generated_code, purpose)
+    };
 
     let expire_time = Duration::seconds(24 * 60 * 60);
 
@@ -171,19 +198,138 @@ pub async fn save_code_in_storage(
         .set_value(key, value, expire_time, true)
         .await?;
 
-    Ok(Utc::now() + expire_time)
+    Ok(now + expire_time)
 }
 
 /// Removes the code from the storage to make it inaccessible for confirmation
+/// Returns 1 if the code was removed and 0 if it was already gone
 pub async fn remove_code_from_storage(
-    redis: &RedisRepo,
-    email: &str
-) -> Result<()> {
-    let key = format!("confirm_code:{}", email);
+    redis: &dyn KvStore,
+    email: &str, purpose: &str
+) -> Result<u64> {
+    let key = get_code_key(email, purpose);
 
     redis
         .delete_by_key(key)
-        .await?;
+        .await
+}
+
+/// Returns the route that accepts codes with the purpose
+fn route_for_purpose(purpose: &str) -> &'static str {
+    match purpose {
+        "create" => "POST /api/v1/cert",
+        "delete" => "DELETE /api/v1/cert",
+        "visibility" => "PATCH /api/v1/cert/visibility",
+        "export" => "POST /api/v1/me/export",
+        "erase" => "POST /api/v1/me/erase",
+        "session" => "POST /api/v1/session",
+        "transfer" => "POST /api/v1/cert/transfer",
+        "accept_transfer" => "POST /api/v1/cert/transfer/confirm",
+        "renew" => "POST /api/v1/cert/renew",
+        _ => "POST /api/v1/send_code"
+    }
+}
+
+/// Verifies the code from the request body against the expected purpose and makes it inaccessible on success
+/// Counts invalid attempts and blocks the email address for the code sending when there are no tries left
+/// Returns the ID of the certificate the code is bound to
+pub async fn verify_and_consume_code(
+    redis: &dyn KvStore,
+    email: &str, token: &str, code: &str, expected_purpose: &str, now: DateTime<Utc>
+) -> Result<Option<Uuid>, Errors> {
+    let cert_id = verify_code(redis, email, token, code, expected_purpose, now).await?;
+    consume_code(redis, email, expected_purpose).await?;
+
+    Ok(cert_id)
+}
+
+/// Makes the verified code inaccessible and resets the rate counter of the email address
+/// Fails if a parallel request has consumed the code first
+pub async fn consume_code(redis: &dyn KvStore, email: &str, purpose: &str) -> Result<(), Errors> {
+    // Delete code from the Redis storage
+    let removed = remove_code_from_storage(redis, email, purpose)
+        .await
+        .map_err(|_| Errors::InternalServer { what: "cache storage" })?;
+
+    if removed == 0 {
+        return Err(Errors::ResourceNotFound { what: "code record" });
+    }
+
+    // Reset the rate counter by the email address
+    let _ = rate_limits::reset_rate_counter(
+        redis,
+        "code", email
+    ).await;
 
     Ok(())
 }
+
+/// Verifies the code like `verify_and_consume_code`, but leaves it in the storage
+/// The handlers that can still reject the request consume the code with `consume_code` after their checks
+pub async fn verify_code(
+    redis: &dyn KvStore,
+    email: &str, token: &str, code: &str, expected_purpose: &str, now: DateTime<Utc>
+) -> Result<Option<Uuid>, Errors> {
+    let verification_result = verify_email_code(redis, email, token, code, expected_purpose).await;
+
+    match verification_result {
+        VerificationResult::Ok { purpose, cert_id } => {
+            if purpose != expected_purpose {
+                // When created code has the wrong purpose
+                return Err(Errors::InvalidRoute { correct_route: route_for_purpose(&purpose) });
+            }
+
+            Ok(cert_id)
+        },
+        VerificationResult::InvalidToken => Err(Errors::InvalidToken),
+        VerificationResult::NotFound => Err(Errors::ResourceNotFound { what: "code record" }),
+        VerificationResult::UnknownError( .. ) => Err(Errors::InternalServer { what: "code verification" }),
+        VerificationResult::InvalidCode => {
+            if rate_limits::check_rate_counter(
+                redis,
+                "token_tries", token,
+                5
+            ).await {
+                // Invalid code, but there are some tries left
+
+                rate_limits::increate_rate_counter(
+                    redis,
+                    "token_tries", token,
+                    Duration::days(1)
+                )
+                    .await
+                    .map_err(|_| Errors::InvalidCode)?;
+
+                Err(Errors::InvalidCode)
+            } else {
+                // Invalid code, but there is no tries left
+
+                let block_duration = Duration::minutes(15);
+                let block_timestamp = now + block_duration;
+
+                // Make the code inaccesible to confirm
+                let _ = remove_code_from_storage(
+                    redis, email, expected_purpose
+                ).await;
+
+                // Block the email address for the code sending
+                let _ = redis.set_value(
+                    rate_limits::get_key("code", email),
+                    10000,
+                    block_duration, true
+                ).await;
+
+                // Remove the tries counter from the Redis storage
+                let _ = rate_limits::reset_rate_counter(
+                    redis,
+                    "token_tries", token
+                ).await;
+
+                Err(Errors::TriesOut {
+                    how_much: block_duration.num_seconds() as u32,
+                    timestamp: block_timestamp.timestamp() as u64
+                })
+            }
+        },
+    }
+}
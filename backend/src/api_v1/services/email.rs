@@ -1,19 +1,27 @@
 use std::collections::HashMap;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use short_uuid::ShortUuid;
 use crate::api_v1::{
-    repos::RedisRepo, 
+    repos::{
+        CertModel, 
+        KvStore
+    }, 
     types::redis::EmailTask
 };
 
+/// The Redis list that is consumed by the email worker
+pub const EMAIL_JOBS_KEY: &str = "email_jobs";
+
 /// Send a letter with the creation code on the specified email
 pub async fn send_create_code(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     email: &str, code: &str
 ) -> Result<()> {
     let mut replacements = HashMap::new();
     replacements.insert("CERTCODE".to_string(), code.to_string());
 
-    redis.lpush("email_jobs".to_string(), serde_json::to_string(&EmailTask {
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
         email: email.to_string(),
         purpose: "create".to_string(),
         replacements: replacements,
@@ -24,13 +32,13 @@ pub async fn send_create_code(
 
 /// Send a letter with the deletion code on the specified email
 pub async fn send_delete_code(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     email: &str, code: &str
 ) -> Result<()> {
     let mut replacements = HashMap::new();
     replacements.insert("CERTCODE".to_string(), code.to_string());
 
-    redis.lpush("email_jobs".to_string(), serde_json::to_string(&EmailTask {
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
         email: email.to_string(),
         purpose: "delete".to_string(),
         replacements: replacements,
@@ -39,15 +47,27 @@ pub async fn send_delete_code(
     Ok(())
 }
 
-/// Send a letter with the certificate ID on the specified email
+/// Send a letter with the serial numbers of all certificates of the specified email
+/// Every serial number is followed by the name and the title, so the holder can tell the certificates apart
 pub async fn send_forgot_cert(
-    redis: &RedisRepo,
-    email: &str, cert_id: &str
+    redis: &dyn KvStore,
+    email: &str, certs: &[CertModel]
 ) -> Result<()> {
+    let cert_list = certs
+        .iter()
+        .map(|cert| format!(
+            "<div style=\"padding:6px 0;\">{}<br/><span style=\"font-size:14px; font-weight:normal; color:#333333; font-family: Arial, Helvetica, sans-serif;\">{} · {}</span></div>",
+            ShortUuid::from_uuid(&cert.id),
+            escape_html(&cert.name),
+            escape_html(&cert.title)
+        ))
+        .collect::<Vec<_>>()
+        .join("");
+
     let mut replacements = HashMap::new();
-    replacements.insert("CERTID".to_string(), cert_id.to_string());
+    replacements.insert("CERTLIST".to_string(), cert_list);
 
-    redis.lpush("email_jobs".to_string(), serde_json::to_string(&EmailTask {
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
         email: email.to_string(),
         purpose: "forgot".to_string(),
         replacements: replacements,
@@ -55,3 +75,125 @@ pub async fn send_forgot_cert(
 
     Ok(())
 }
+
+/// Send a letter with the code that confirms a certificate management action on the specified email
+/// The action is a human-readable description shown in the letter
+pub async fn send_manage_code(
+    redis: &dyn KvStore,
+    email: &str, code: &str, action: &str
+) -> Result<()> {
+    let mut replacements = HashMap::new();
+    replacements.insert("CERTCODE".to_string(), code.to_string());
+    replacements.insert("ACTION".to_string(), action.to_string());
+
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
+        email: email.to_string(),
+        purpose: "manage".to_string(),
+        replacements,
+    }).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Send a letter with the exported personal data on the specified email
+pub async fn send_data_export(
+    redis: &dyn KvStore,
+    email: &str, data: &str
+) -> Result<()> {
+    let mut replacements = HashMap::new();
+    replacements.insert("DATA".to_string(), escape_html(data));
+
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
+        email: email.to_string(),
+        purpose: "export".to_string(),
+        replacements,
+    }).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Send a letter about the certificate issued on behalf of the holder on the specified email
+/// The name and the title come from the organisers, so they are escaped
+pub async fn send_cert_ready(
+    redis: &dyn KvStore,
+    email: &str, cert_id: &str, name: &str, title: &str
+) -> Result<()> {
+    let mut replacements = HashMap::new();
+    replacements.insert("CERTID".to_string(), cert_id.to_string());
+    replacements.insert("NAME".to_string(), escape_html(name));
+    replacements.insert("TITLE".to_string(), escape_html(title));
+
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
+        email: email.to_string(),
+        purpose: "ready".to_string(),
+        replacements,
+    }).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Send a letter to the previous holder about the certificate moved to another email address
+pub async fn send_transfer_out(
+    redis: &dyn KvStore,
+    email: &str, cert_id: &str, new_email: &str
+) -> Result<()> {
+    let mut replacements = HashMap::new();
+    replacements.insert("CERTID".to_string(), cert_id.to_string());
+    replacements.insert("EMAIL".to_string(), escape_html(new_email));
+
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
+        email: email.to_string(),
+        purpose: "transfer_out".to_string(),
+        replacements,
+    }).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Send a letter to the new holder about the certificate moved to their email address
+pub async fn send_transfer_in(
+    redis: &dyn KvStore,
+    email: &str, cert_id: &str, name: &str, title: &str
+) -> Result<()> {
+    let mut replacements = HashMap::new();
+    replacements.insert("CERTID".to_string(), cert_id.to_string());
+    replacements.insert("NAME".to_string(), escape_html(name));
+    replacements.insert("TITLE".to_string(), escape_html(title));
+
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
+        email: email.to_string(),
+        purpose: "transfer_in".to_string(),
+        replacements,
+    }).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Send a letter about the certificate that expires soon, the expiration date is in the UTC
+pub async fn send_expiry_reminder(
+    redis: &dyn KvStore,
+    email: &str, cert_id: &str, name: &str, expires_at: &DateTime<Utc>
+) -> Result<()> {
+    let mut replacements = HashMap::new();
+    replacements.insert("CERTID".to_string(), cert_id.to_string());
+    replacements.insert("NAME".to_string(), escape_html(name));
+    replacements.insert("EXPIRES".to_string(), expires_at.format("%d.%m.%Y").to_string());
+
+    redis.lpush(EMAIL_JOBS_KEY.to_string(), serde_json::to_string(&EmailTask {
+        email: email.to_string(),
+        purpose: "expiry".to_string(),
+        replacements,
+    }).unwrap()).await?;
+
+    Ok(())
+}
+
+/// Escapes the characters that have a special meaning in HTML, so a text can be inserted into a letter as is
+fn escape_html(text: &str) -> String {
+    text
+        .replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+        .replace("'", "&#39;")
+}
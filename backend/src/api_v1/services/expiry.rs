@@ -0,0 +1,88 @@
+use chrono::{DateTime, Duration, Utc};
+use short_uuid::ShortUuid;
+use anyhow::Result;
+use crate::{
+    api_v1::{
+        repos::{
+            CertModel,
+            CertStore,
+            KvStore
+        },
+        services::{
+            email,
+            kinds
+        }
+    },
+    configs::{
+        self,
+        ApiSettings
+    }
+};
+
+fn get_reminder_key(cert: &CertModel, expires_at: &DateTime<Utc>) -> String {
+    format!("expiry_reminder:{}:{}", cert.id, expires_at.timestamp())
+}
+
+/// Returns true if the certificate has expired at the time
+pub fn is_expired(expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= now)
+}
+
+/// Returns the expiration time of a certificate of the kind issued at the time, None if it never expires
+pub fn get_expiration(settings: &ApiSettings, kind: &str, issued_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    kinds::get_validity(settings, kind).map(|validity| issued_at + validity)
+}
+
+/// Returns the expiration time of the renewed certificate
+/// The validity period is counted from the renewal, so the repeated renewals can't push the expiration further
+/// A certificate that never expires keeps no expiration
+pub fn get_renewed_expiration(settings: &ApiSettings, cert: &CertModel, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    cert.expires_at?;
+
+    get_expiration(settings, &cert.kind, now)
+}
+
+/// Sends the reminders about the certificates that expire within EXPIRY_REMINDER_DAYS
+/// Every expiration is reminded once, a renewed certificate is reminded again before its new expiration
+/// Returns the number of the sent reminders
+pub async fn send_expiry_reminders(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    now: DateTime<Utc>
+) -> Result<u64> {
+    let reminder_period = Duration::from_std(configs::get_expiry_reminder_period())?;
+    let expiring_certs = cert_repo.find_certs_expiring_between(now, now + reminder_period).await?;
+    let mut sent_count = 0;
+
+    for cert in expiring_certs {
+        let Some(expires_at) = cert.expires_at else {
+            continue;
+        };
+
+        // The mark outlives the expiration, so the next runs skip the certificate
+        let reminder_key = get_reminder_key(&cert, &expires_at);
+        let is_first_reminder = redis.set_string(
+            reminder_key.clone(),
+            now.timestamp().to_string(),
+            expires_at - now + Duration::days(1),
+            false
+        ).await?.is_some();
+
+        if !is_first_reminder {
+            continue;
+        }
+
+        // The next run tries again if the letter wasn't queued
+        if let Err(e) = email::send_expiry_reminder(
+            redis,
+            &cert.email, &ShortUuid::from_uuid(&cert.id).to_string(), &cert.name, &expires_at
+        ).await {
+            let _ = redis.delete_by_key(reminder_key).await;
+            return Err(e);
+        }
+
+        sent_count += 1;
+    }
+
+    Ok(sent_count)
+}
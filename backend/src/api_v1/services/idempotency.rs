@@ -0,0 +1,171 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use anyhow::Result;
+use crate::{
+    api_v1::repos::KvStore,
+    utils::smart_trim::smart_trim
+};
+
+/// How long the first response is replayed for the retries
+pub const IDEMPOTENCY_KEY_LIFETIME_HOURS: i64 = 24;
+
+/// How long the key stays locked while the first request is processed
+/// The lock expires by itself if the process dies in the middle
+const IN_PROGRESS_LIFETIME_SECONDS: i64 = 60;
+
+/// The longest key that is accepted, the clients usually send a UUID
+const MAX_KEY_LENGTH: usize = 255;
+
+/// The first request sent with the key, the response is empty until the request is processed
+#[derive(Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    /// SHA-256 of the method, the path and the body, the retries must have the same one
+    pub fingerprint: String,
+    /// The email address from the request body, so the record is exported and erased with the other personal data
+    #[serde(default)]
+    pub email: Option<String>,
+    pub response: Option<StoredResponse>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String
+}
+
+/// What to do with the request that has the key
+pub enum IdempotencyState {
+    /// The key is new and now locked, the request must be processed
+    Started,
+    /// The same request is still processed
+    InProgress,
+    /// The key was used for another request
+    Mismatch,
+    /// The same request was already processed, its response must be replayed
+    Completed(StoredResponse)
+}
+
+fn get_key(endpoint: &str, idempotency_key: &str) -> String {
+    format!("idempotency:{}:{}", endpoint, idempotency_key)
+}
+
+/// Returns true if the key is 1-255 visible ASCII characters
+pub fn is_valid_key(idempotency_key: &str) -> bool {
+    !idempotency_key.is_empty()
+        && idempotency_key.len() <= MAX_KEY_LENGTH
+        && idempotency_key.chars().all(|c| c.is_ascii_graphic())
+}
+
+/// Returns the email address of the JSON request body the same way the endpoints trim it
+pub fn get_request_email(body: &[u8]) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+
+    body.get("email")?.as_str().map(smart_trim)
+}
+
+/// Returns the fingerprint of the request, the endpoint is a part of it
+pub fn get_fingerprint(endpoint: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(endpoint.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    hex::encode(hasher.finalize())
+}
+
+/// Locks the key for the request or returns the state of the request that already has it
+pub async fn begin_request(
+    redis: &dyn KvStore,
+    endpoint: &str, idempotency_key: &str, fingerprint: &str, email: Option<&str>
+) -> Result<IdempotencyState> {
+    let key = get_key(endpoint, idempotency_key);
+    let record = IdempotencyRecord {
+        fingerprint: fingerprint.to_string(),
+        email: email.map(str::to_string),
+        response: None
+    };
+
+    let locked = redis.set_string(
+        key.clone(),
+        serde_json::to_string(&record)?,
+        Duration::seconds(IN_PROGRESS_LIFETIME_SECONDS),
+        false
+    ).await?;
+
+    if locked.is_some() {
+        return Ok(IdempotencyState::Started);
+    }
+
+    // The key could expire between the two calls, then the request has to be sent again
+    let Some(existing) = redis.get_string(key).await? else {
+        return Ok(IdempotencyState::InProgress);
+    };
+    let existing: IdempotencyRecord = serde_json::from_str(&existing)?;
+
+    if existing.fingerprint != fingerprint {
+        return Ok(IdempotencyState::Mismatch);
+    }
+
+    Ok(match existing.response {
+        Some(response) => IdempotencyState::Completed(response),
+        None => IdempotencyState::InProgress
+    })
+}
+
+/// Stores the response of the processed request for the retries
+pub async fn complete_request(
+    redis: &dyn KvStore,
+    endpoint: &str, idempotency_key: &str, fingerprint: &str, email: Option<&str>,
+    response: StoredResponse
+) -> Result<()> {
+    let record = IdempotencyRecord {
+        fingerprint: fingerprint.to_string(),
+        email: email.map(str::to_string),
+        response: Some(response)
+    };
+
+    redis.set_string(
+        get_key(endpoint, idempotency_key),
+        serde_json::to_string(&record)?,
+        Duration::hours(IDEMPOTENCY_KEY_LIFETIME_HOURS),
+        true
+    ).await?;
+
+    Ok(())
+}
+
+/// Unlocks the key after a failed request, so it can be retried with the same key
+pub async fn abort_request(
+    redis: &dyn KvStore,
+    endpoint: &str, idempotency_key: &str
+) -> Result<()> {
+    redis.delete_by_key(get_key(endpoint, idempotency_key)).await?;
+
+    Ok(())
+}
+
+/// Returns the storage keys and the records of the requests sent with the email address
+pub async fn find_records_of_email(
+    redis: &dyn KvStore,
+    email: &str
+) -> Result<Vec<(String, IdempotencyRecord)>> {
+    let mut records = Vec::new();
+
+    for key in redis.scan_keys(get_key("*", "*")).await? {
+        // The record could expire after the scan
+        let Some(raw) = redis.get_string(key.clone()).await? else {
+            continue;
+        };
+        let Ok(record) = serde_json::from_str::<IdempotencyRecord>(&raw) else {
+            continue;
+        };
+
+        if record.email.as_deref() == Some(email) {
+            records.push((key, record));
+        }
+    }
+
+    Ok(records)
+}
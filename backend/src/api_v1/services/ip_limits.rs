@@ -0,0 +1,86 @@
+use std::net::IpAddr;
+use chrono::Duration;
+use ipnet::IpNet;
+use anyhow::Result;
+use crate::{
+    api_v1::{
+        repos::KvStore,
+        services::rate_limits
+    },
+    configs::ApiSettings
+};
+
+/// A network the IP rate limit is counted by
+pub struct IpRateKey {
+    /// The network in the CIDR notation, e.g. "203.0.113.0/24"
+    pub key: String,
+    /// How many times the limit of the network is larger than the limit of a single address
+    pub limit_factor: u64
+}
+
+/// Returns the networks of the address, from the narrowest to the widest
+/// Every wider network gets a larger share of the limit, the allowlisted networks multiply all of them
+pub fn get_ip_rate_keys(settings: &ApiSettings, ip: IpAddr) -> Vec<IpRateKey> {
+    let allowlist_factor = settings.ip_allowlist
+        .iter()
+        .filter(|(network, _)| network.contains(&ip))
+        .map(|(_, factor)| *factor)
+        .max()
+        .unwrap_or(1);
+
+    let prefixes = match ip {
+        IpAddr::V4(_) => &settings.ipv4_limit_prefixes,
+        IpAddr::V6(_) => &settings.ipv6_limit_prefixes
+    };
+
+    let mut keys = Vec::new();
+    let mut limit_factor = allowlist_factor;
+
+    for prefix in prefixes {
+        if let Ok(network) = IpNet::new(ip, *prefix) {
+            keys.push(IpRateKey {
+                key: network.trunc().to_string(),
+                limit_factor
+            });
+
+            limit_factor = limit_factor.saturating_mul(settings.subnet_limit_factor);
+        }
+    }
+
+    // No prefixes are configured, so the address is the only network
+    if keys.is_empty() {
+        keys.push(IpRateKey { key: IpNet::from(ip).to_string(), limit_factor: allowlist_factor });
+    }
+
+    keys
+}
+
+/// Returns the first network which counter reached its share of the limit
+pub async fn find_exceeded_key<'a>(
+    redis: &dyn KvStore,
+    realm: &str, keys: &'a [IpRateKey], limit: u64
+) -> Option<&'a IpRateKey> {
+    for key in keys {
+        if !rate_limits::check_rate_counter(
+            redis,
+            realm, &key.key,
+            limit.saturating_mul(key.limit_factor)
+        ).await {
+            return Some(key);
+        }
+    }
+
+    None
+}
+
+/// Increases the rate counters of all the networks
+pub async fn increase_rate_counters(
+    redis: &dyn KvStore,
+    realm: &str, keys: &[IpRateKey], exp: Duration
+) -> Result<()> {
+    for key in keys {
+        rate_limits::increate_rate_counter(redis, realm, &key.key, exp).await?;
+    }
+
+    Ok(())
+}
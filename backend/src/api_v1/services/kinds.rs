@@ -0,0 +1,153 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use validator::ValidationError;
+use anyhow::{Context, Result};
+use crate::{
+    api_v1::{
+        repos::CertStore,
+        services::locale::Language
+    },
+    configs::ApiSettings
+};
+
+/// The kind of the certificates created before the catalogue and of the requests without a kind
+pub const DEFAULT_CERT_KIND: &str = "classic";
+
+/// An entry of the certificate kinds catalogue
+#[derive(Clone, Debug, Deserialize)]
+pub struct CertKind {
+    /// The identifier stored in the certificates, e.g. "new-year-2027"
+    pub id: String,
+    /// The names by the language code, e.g. {"uk": "Новорічний пупсик", "en": "New Year pupsik"}
+    pub names: HashMap<String, String>,
+    /// The kind can't be chosen before this time
+    #[serde(default)]
+    pub available_from: Option<DateTime<Utc>>,
+    /// The kind can't be chosen after this time, the certificates stay
+    #[serde(default)]
+    pub available_until: Option<DateTime<Utc>>,
+    /// The greatest amount of the certificates of the kind
+    #[serde(default)]
+    pub cap: Option<u64>,
+    /// The titles the form suggests for the kind
+    #[serde(default)]
+    pub default_titles: Vec<String>,
+    /// How many days the certificates of the kind are valid, 0 makes them never expire
+    /// The CERT_VALIDITY_DAYS period is used if it's not set
+    #[serde(default)]
+    pub validity_days: Option<u64>
+}
+
+impl CertKind {
+    /// Returns true if the time is inside the availability window
+    pub fn is_available_at(&self, now: DateTime<Utc>) -> bool {
+        self.available_from.is_none_or(|from| from <= now)
+            && self.available_until.is_none_or(|until| now < until)
+    }
+
+    /// Returns the name in the language, the name in any other language or the identifier
+    pub fn localized_name(&self, language: Language) -> String {
+        self.names.get(language.as_str())
+            .or_else(|| self.names.get(Language::default().as_str()))
+            .or_else(|| self.names.values().next())
+            .cloned()
+            .unwrap_or(self.id.clone())
+    }
+
+    /// Returns how long the certificates of the kind are valid, None if they never expire
+    pub fn validity(&self, settings: &ApiSettings) -> Option<Duration> {
+        match self.validity_days {
+            Some(0) => None,
+            Some(days) => Some(Duration::days(days as i64)),
+            None => default_validity(settings)
+        }
+    }
+}
+
+/// The context of the request validation, the catalogue depends on the time and the settings
+pub struct KindsContext {
+    pub now: DateTime<Utc>,
+    pub settings: Arc<ApiSettings>
+}
+
+fn default_catalogue() -> Vec<CertKind> {
+    vec![CertKind {
+        id: DEFAULT_CERT_KIND.to_string(),
+        names: HashMap::from([
+            ("uk".to_string(), "Класичний пупсик".to_string()),
+            ("en".to_string(), "Classic pupsik".to_string())
+        ]),
+        available_from: None,
+        available_until: None,
+        cap: None,
+        default_titles: Vec::new(),
+        validity_days: None
+    }]
+}
+
+fn default_validity(settings: &ApiSettings) -> Option<Duration> {
+    settings.cert_validity.and_then(|validity| Duration::from_std(validity).ok())
+}
+
+/// Reads the catalogue from the CERT_KINDS_FILE file or returns the built-in one with the classic kind only
+/// It's read once at the start, an unreadable or invalid file stops the server
+pub fn load_catalogue(path: Option<&Path>) -> Result<Vec<CertKind>> {
+    let Some(path) = path else {
+        return Ok(default_catalogue());
+    };
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the certificate kinds from {}", path.display()))?;
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse the certificate kinds from {}", path.display()))
+}
+
+/// Returns the catalogue loaded into the settings
+pub fn get_catalogue(settings: &ApiSettings) -> &[CertKind] {
+    &settings.cert_kinds
+}
+
+/// Returns the kinds that can be chosen at the time
+pub fn get_active_kinds(settings: &ApiSettings, now: DateTime<Utc>) -> Vec<CertKind> {
+    get_catalogue(settings)
+        .iter()
+        .filter(|kind| kind.is_available_at(now))
+        .cloned()
+        .collect()
+}
+
+/// Returns the kind if it can be chosen at the time
+pub fn find_active_kind(settings: &ApiSettings, id: &str, now: DateTime<Utc>) -> Option<CertKind> {
+    get_active_kinds(settings, now)
+        .into_iter()
+        .find(|kind| kind.id == id)
+}
+
+/// Returns how long the certificates of the kind are valid, the kinds that left the catalogue use the default period
+pub fn get_validity(settings: &ApiSettings, id: &str) -> Option<Duration> {
+    match get_catalogue(settings).iter().find(|kind| kind.id == id) {
+        Some(kind) => kind.validity(settings),
+        None => default_validity(settings)
+    }
+}
+
+/// Checks that the kind is in the active catalogue
+pub fn validate_cert_kind(kind: &str, context: &KindsContext) -> Result<(), ValidationError> {
+    match find_active_kind(&context.settings, kind, context.now) {
+        Some(_) => Ok(()),
+        None => Err(ValidationError::new("inactive_cert_kind"))
+    }
+}
+
+/// Returns how many certificates of the kind can still be created, None if the kind has no cap
+pub async fn get_remaining(cert_repo: &dyn CertStore, kind: &CertKind) -> Result<Option<u64>> {
+    let Some(cap) = kind.cap else {
+        return Ok(None);
+    };
+
+    let count = cert_repo.count_by_kind(kind.id.clone()).await?;
+
+    Ok(Some(cap.saturating_sub(count)))
+}
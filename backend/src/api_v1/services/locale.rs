@@ -0,0 +1,199 @@
+use actix_web::{HttpRequest, http::header};
+
+/// The languages of the API messages
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Language {
+    #[default]
+    Uk,
+    En
+}
+
+impl Language {
+    /// Parses a language tag like "uk", "uk-UA" or "en-US" by its primary subtag
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag
+            .trim()
+            .split(['-', '_'])
+            .next()?
+            .to_lowercase();
+
+        match primary.as_str() {
+            "uk" | "ua" => Some(Self::Uk),
+            "en" => Some(Self::En),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Uk => "uk",
+            Self::En => "en"
+        }
+    }
+}
+
+/// Returns the first supported language from the Accept-Language header value in the order of the q-values
+fn parse_accept_language(value: &str) -> Option<Language> {
+    let mut preferences: Vec<(f32, Language)> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let language = Language::parse(parts.next()?)?;
+
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+
+            (quality > 0.0).then_some((quality, language))
+        })
+        .collect();
+
+    // The sort is stable, so the header order decides between equal q-values
+    preferences.sort_by(|a, b| b.0.total_cmp(&a.0));
+    preferences.first().map(|(_, language)| *language)
+}
+
+/// Chooses the language of the response
+/// The "lang" query parameter wins over the Accept-Language header, Ukrainian is the fallback
+pub fn negotiate_language(request: &HttpRequest) -> Language {
+    let from_query = request.query_string()
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == "lang")
+        .and_then(|(_, value)| Language::parse(value));
+
+    if let Some(language) = from_query {
+        return language;
+    }
+
+    request.headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_accept_language)
+        .unwrap_or_default()
+}
+
+/// Returns the message template by the error code
+/// Placeholders look like {name} and are replaced by `interpolate`
+fn get_template(language: Language, code_error: &str) -> Option<&'static str> {
+    match language {
+        Language::Uk => match code_error {
+            "page_not_found" => Some("Сторінку не знайдено"),
+            "bad_request" => Some("Некоректний запит: неправильне значення ({what_invalid})"),
+            "resource_not_found" => Some("Не знайдено: {what}"),
+            "internal_server_error" => Some("Щось пішло не так ({what})"),
+            "email_rate_limit" => Some("Забагато запитів для цієї пошти. Спробуйте через {how_much} с"),
+            "ip_rate_limit" => Some("Забагато запитів з Вашої IP-адреси. Спробуйте через {how_much} с"),
+            "invalid_route" => Some("Неправильний маршрут: код призначений для {correct_route}"),
+            "invalid_code" => Some("Неправильний код"),
+            "invalid_token" => Some("Код застарів або токен недійсний"),
+            "already_exists" => Some("Вже існує: {what}"),
+            "tries_out" => Some("Спроби закінчилися. Пошту заблоковано на {how_much} с"),
+            "invalid_email" => Some("Неправильна адреса електронної пошти"),
+            "payload_too_large" => Some("Перевищено розмір запиту ({bytes_limit} байт)"),
+            "requests_rate_limit" => Some("Забагато запитів. Зачекайте трохи"),
+            "unauthorized" => Some("Відсутній або недійсний токен авторизації"),
+            "invalid_challenge" => Some("Розв'язок перевірки відсутній, застарів або неправильний"),
+            "idempotency_key_reused" => Some("Цей ключ ідемпотентності вже використано для іншого запиту"),
+            "idempotency_key_in_progress" => Some("Запит з цим ключем ідемпотентності ще обробляється"),
+            "kind_unavailable" => Some("Цей вид сертифіката більше недоступний"),
+            "cert_quota_exceeded" => Some("Для цієї пошти вже створено максимальну кількість сертифікатів ({limit})"),
+            _ => None
+        },
+        Language::En => match code_error {
+            "page_not_found" => Some("Page not found"),
+            "bad_request" => Some("Bad request: invalid {what_invalid}"),
+            "resource_not_found" => Some("Resource not found: {what} not found"),
+            "internal_server_error" => Some("Something went wrong with {what}"),
+            "email_rate_limit" => Some("Email rate limit hit. Retry in {how_much}s"),
+            "ip_rate_limit" => Some("IP rate limit hit. Retry in {how_much}s"),
+            "invalid_route" => Some("Invalid route: the correct route is {correct_route}"),
+            "invalid_code" => Some("Invalid code"),
+            "invalid_token" => Some("Code is outdated or invalid token"),
+            "already_exists" => Some("The entry for {what} already exists"),
+            "tries_out" => Some("The number of attempts has ended. Email blocked for {how_much}s"),
+            "invalid_email" => Some("Invalid email"),
+            "payload_too_large" => Some("The body payload limit ({bytes_limit} bytes) reached"),
+            "requests_rate_limit" => Some("Requests rate limit hit"),
+            "unauthorized" => Some("Missing or invalid authorization token"),
+            "invalid_challenge" => Some("Missing, expired or invalid challenge solution"),
+            "idempotency_key_reused" => Some("The idempotency key was already used for another request"),
+            "idempotency_key_in_progress" => Some("The request with this idempotency key is still processed"),
+            "kind_unavailable" => Some("The certificate kind is no longer available"),
+            "cert_quota_exceeded" => Some("The email already has the maximum number of certificates ({limit})"),
+            _ => None
+        }
+    }
+}
+
+/// Translates the subject of an error (the `what` fields), returns the subject as is if there is no translation
+pub fn translate_subject(language: Language, subject: &str) -> String {
+    let translation = match language {
+        Language::En => None,
+        Language::Uk => match subject {
+            "DB" => Some("база даних"),
+            "cache storage" => Some("сховище кешу"),
+            "broker" => Some("черга листів"),
+            "body" => Some("тіло запиту"),
+            "query" => Some("параметри запиту"),
+            "field values" => Some("значення полів"),
+            "email field value" => Some("адреса електронної пошти"),
+            "id field value" => Some("ідентифікатор"),
+            "new email field value" => Some("нова адреса електронної пошти"),
+            "serial number" => Some("серійний номер"),
+            "cursor" => Some("курсор"),
+            "certificate" => Some("сертифікат"),
+            "public certificate" => Some("публічний сертифікат"),
+            "certificate with this ID" => Some("сертифікат з цим ідентифікатором"),
+            "certificate linked with this email" => Some("сертифікат, пов'язаний з цією поштою"),
+            "code record" => Some("код підтвердження"),
+            "code verification" => Some("перевірка коду"),
+            "IP address" => Some("IP-адреса"),
+            "erasure receipt" => Some("квитанція про видалення"),
+            "job" => Some("завдання"),
+            "session" => Some("сесія"),
+            "transfer" => Some("передача сертифіката"),
+            "idempotency key" => Some("ключ ідемпотентності"),
+            "response body" => Some("тіло відповіді"),
+            "serialization" => Some("серіалізація"),
+            "webhook" => Some("вебхук"),
+            "webhook ID" => Some("ідентифікатор вебхука"),
+            "webhook delivery" => Some("доставка вебхука"),
+            "receipt ID" => Some("ідентифікатор квитанції"),
+            "personal data collection" => Some("збирання персональних даних"),
+            "personal data erasure" => Some("видалення персональних даних"),
+            "personal data serialization" => Some("серіалізація персональних даних"),
+            "CSV header" => Some("заголовок CSV"),
+            "CSV header (expected email, name and title columns)" => Some("заголовок CSV (очікуються стовпці email, name і title)"),
+            "CSV length (too many rows)" => Some("довжина CSV (забагато рядків)"),
+            "date (expected YYYY-MM-DD)" => Some("параметр date (очікується формат YYYY-MM-DD)"),
+            "interval (expected day, week or month)" => Some("параметр interval (очікується day, week або month)"),
+            "range (from is later than to)" => Some("діапазон (from пізніше за to)"),
+            "range (too many buckets)" => Some("діапазон (забагато інтервалів)"),
+            "order (expected newest or random)" => Some("параметр order (очікується newest або random)"),
+            "offset (too deep page)" => Some("параметр offset (надто далека сторінка)"),
+            "q (expected 2 to 100 characters)" => Some("параметр q (очікується від 2 до 100 символів)"),
+            "on_duplicate (expected skip or update)" => Some("параметр on_duplicate (очікується skip або update)"),
+            _ => None
+        }
+    };
+
+    translation.map_or(subject.to_string(), |value| value.to_string())
+}
+
+/// Replaces the {name} placeholders of the template with the values
+fn interpolate(template: &str, values: &[(&str, String)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |message, (name, value)| {
+            message.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+/// Returns the localized message by the error code
+/// Falls back to English if the language has no such message, returns None if English has no such message too
+pub fn get_message(language: Language, code_error: &str, values: &[(&str, String)]) -> Option<String> {
+    get_template(language, code_error)
+        .or_else(|| get_template(Language::En, code_error))
+        .map(|template| interpolate(template, values))
+}
@@ -2,3 +2,19 @@ pub mod codes;
 pub mod rate_limits;
 pub mod cache;
 pub mod email;
+pub mod admin;
+pub mod webhooks;
+pub mod timeseries;
+pub mod personal_data;
+pub mod locale;
+pub mod search;
+pub mod cert_cache;
+pub mod scheduler;
+pub mod sessions;
+pub mod challenge;
+pub mod client_ip;
+pub mod ip_limits;
+pub mod idempotency;
+pub mod kinds;
+pub mod transfers;
+pub mod expiry;
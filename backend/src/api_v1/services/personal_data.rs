@@ -0,0 +1,227 @@
+use chrono::{DateTime, Duration, Utc};
+use sha2::{Digest, Sha256};
+use short_uuid::ShortUuid;
+use uuid::Uuid;
+use anyhow::Result;
+use crate::api_v1::{
+    repos::{
+        CertStore,
+        ErasureReceiptModel,
+        ErasureRepo,
+        KvStore,
+        WebhookRepo
+    },
+    services::{
+        cache,
+        cert_cache,
+        codes,
+        email::EMAIL_JOBS_KEY,
+        expiry,
+        idempotency,
+        rate_limits,
+        transfers,
+        webhooks::{
+            self,
+            WebhookEvent
+        }
+    },
+    types::{
+        personal_data::{
+            PersonalDataBundle,
+            PersonalDataCertificate,
+            PersonalDataRateLimit,
+            PersonalDataStoredResponse,
+            PersonalDataTransfer
+        },
+        redis::EmailTask
+    }
+};
+
+/// Returns the queued email tasks addressed to the email address as raw queue elements
+async fn find_email_jobs(
+    redis: &dyn KvStore,
+    email: &str
+) -> Result<Vec<(String, EmailTask)>> {
+    let jobs = redis.list_all(EMAIL_JOBS_KEY.to_string()).await?;
+
+    Ok(
+        jobs
+            .into_iter()
+            .filter_map(|raw| {
+                let task: EmailTask = serde_json::from_str(&raw).ok()?;
+                (task.email == email).then_some((raw, task))
+            })
+            .collect()
+    )
+}
+
+/// Returns the hex SHA-256 hash of the normalized email address
+/// The hash stays in the data base for the operators, the public receipt never shows it
+pub fn hash_email(email: &str) -> String {
+    hex::encode(Sha256::digest(email.trim().to_lowercase().as_bytes()))
+}
+
+/// Collects everything stored about the email address in the data base and the Redis storage
+pub async fn collect_personal_data(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    email: &str,
+    now: DateTime<Utc>
+) -> Result<PersonalDataBundle> {
+    let certificates = cert_repo.find_certs_by_email(email.to_string())
+        .await?
+        .into_iter()
+        .map(|cert| PersonalDataCertificate {
+            id: ShortUuid::from_uuid(&cert.id).to_string(),
+            name: cert.name,
+            title: cert.title,
+            kind: cert.kind,
+            created_at: cert.created_at.to_rfc3339(),
+            expires_at: cert.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+            public: cert.is_public
+        })
+        .collect();
+
+    let mut rate_limits = Vec::new();
+    for key in rate_limits::find_rate_keys(redis, email).await? {
+        let value = redis.get_value::<u64>(key.clone()).await.unwrap_or(None);
+        let (_, expires_at) = redis.get_ttl(key.clone()).await?;
+
+        rate_limits.push(PersonalDataRateLimit {
+            key,
+            value,
+            expires_at: expires_at.to_rfc3339()
+        });
+    }
+
+    let mut pending_code = false;
+    for key in codes::get_code_keys(email) {
+        pending_code |= redis.get_value::<String>(key)
+            .await?
+            .is_some();
+    }
+
+    // The address of the other side isn't exported, only the certificate and the direction
+    let pending_transfers = transfers::find_transfers_of_email(redis, email)
+        .await?
+        .into_iter()
+        .map(|(cert_id, transfer)| PersonalDataTransfer {
+            certificate: ShortUuid::from_uuid(&cert_id).to_string(),
+            direction: if transfer.old_email == email { "outgoing" } else { "incoming" }.to_string()
+        })
+        .collect();
+
+    let stored_responses = idempotency::find_records_of_email(redis, email)
+        .await?
+        .into_iter()
+        .map(|(key, record)| PersonalDataStoredResponse {
+            key,
+            status: record.response.as_ref().map(|response| response.status),
+            body: record.response.map(|response| response.body)
+        })
+        .collect();
+
+    // Only the purposes are exported, the letters may contain confirmation codes
+    let queued_emails = find_email_jobs(redis, email)
+        .await?
+        .into_iter()
+        .map(|(_, task)| task.purpose)
+        .collect();
+
+    Ok(PersonalDataBundle {
+        email: email.to_string(),
+        generated_at: now.to_rfc3339(),
+        certificates,
+        rate_limits,
+        pending_code,
+        pending_transfers,
+        stored_responses,
+        queued_emails
+    })
+}
+
+/// Removes everything stored about the email address and saves an auditable receipt
+/// The certificate deletion leaves only an anonymous record for the statistics
+pub async fn erase_personal_data(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    webhook_repo: &WebhookRepo,
+    erasure_repo: &ErasureRepo,
+    email: &str,
+    now: DateTime<Utc>
+) -> Result<ErasureReceiptModel> {
+    // Remove the certificates and the webhook deliveries that carry their data
+    let mut certificates_removed = 0;
+    let mut webhook_deliveries_removed = 0;
+
+    for cert in cert_repo.find_certs_by_email(email.to_string()).await? {
+        let short_id = ShortUuid::from_uuid(&cert.id).to_string();
+
+        let removed = cert_repo.remove_cert_by_id_and_email(cert.id, email.to_string()).await?;
+        webhook_deliveries_removed += webhook_repo.remove_deliveries_mentioning(&short_id).await?;
+
+        if removed > 0 {
+            certificates_removed += removed;
+
+            let _ = cert_cache::invalidate_cert(redis, &cert.id).await;
+
+            // The expired certificates aren't counted already
+            if !expiry::is_expired(cert.expires_at, now) {
+                let _ = redis.increase_by(
+                    cache::get_key("stats:users_count"),
+                    -(removed as i64),
+                    Duration::days(1)
+                ).await;
+            }
+
+            // Partners are told only the serial number, so they can remove their copies too
+            let _ = webhooks::dispatch_event(
+                redis,
+                webhook_repo,
+                WebhookEvent::CertDeleted,
+                serde_json::json!({ "id": short_id })
+            ).await;
+        }
+    }
+
+    // Remove the rate limits, lockouts and the pending codes
+    let mut cache_keys_removed = 0;
+
+    for key in rate_limits::find_rate_keys(redis, email).await? {
+        cache_keys_removed += redis.delete_by_key(key).await?;
+    }
+
+    for key in codes::get_code_keys(email) {
+        cache_keys_removed += redis.delete_by_key(key).await?;
+    }
+
+    // The transfers and the stored responses of the retries carry the address too
+    for (cert_id, _) in transfers::find_transfers_of_email(redis, email).await? {
+        cache_keys_removed += transfers::remove_pending_transfer(redis, &cert_id).await?;
+    }
+
+    for (key, _) in idempotency::find_records_of_email(redis, email).await? {
+        cache_keys_removed += redis.delete_by_key(key).await?;
+    }
+
+    // Remove the letters that are still waiting in the queue
+    let mut email_jobs_removed = 0;
+
+    for (raw, _) in find_email_jobs(redis, email).await? {
+        email_jobs_removed += redis.list_remove(EMAIL_JOBS_KEY.to_string(), raw).await?;
+    }
+
+    let receipt = ErasureReceiptModel {
+        id: Uuid::new_v4(),
+        email_hash: hash_email(email),
+        certificates_removed: certificates_removed as i32,
+        cache_keys_removed: cache_keys_removed as i32,
+        email_jobs_removed: email_jobs_removed as i32,
+        webhook_deliveries_removed: webhook_deliveries_removed as i32,
+        erased_at: now
+    };
+
+    erasure_repo.create_receipt(receipt.clone()).await?;
+
+    Ok(receipt)
+}
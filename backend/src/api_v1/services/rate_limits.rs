@@ -1,6 +1,6 @@
 use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
-use crate::api_v1::repos::RedisRepo;
+use crate::api_v1::repos::KvStore;
 
 /// Turns a realm and rate key into the specialized Redis key
 pub fn get_key(
@@ -11,7 +11,7 @@ pub fn get_key(
 
 /// Returns the value of the rate counter
 pub async fn get_rate_counter(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     realm: &str, key: &str
 ) -> u64 {
     redis.get_value(get_key(realm, key)).await
@@ -21,7 +21,7 @@ pub async fn get_rate_counter(
 
 /// Increases the rate counter
 pub async fn increate_rate_counter(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     realm: &str, key: &str, exp: Duration
 ) -> Result<u64> {
     redis.increase_by_one(get_key(realm, key), exp).await
@@ -30,7 +30,7 @@ pub async fn increate_rate_counter(
 /// Checks if the value of the rate counter is larger then a number
 /// Used to check if the rate limit hitted
 pub async fn check_rate_counter(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     realm: &str, key: &str, limit: u64
 ) -> bool {
     get_rate_counter(redis, realm, key).await < limit
@@ -38,7 +38,7 @@ pub async fn check_rate_counter(
 
 /// Returns the time and UTC expiration time to the rate counter reset
 pub async fn get_rate_time(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     realm: &str, key: &str
 ) -> Result<(Duration, DateTime<Utc>)> {
     redis.get_ttl(get_key(realm, key)).await
@@ -46,10 +46,32 @@ pub async fn get_rate_time(
 
 /// Resets the rate counter
 pub async fn reset_rate_counter(
-    redis: &RedisRepo,
+    redis: &dyn KvStore,
     realm: &str, key: &str
 ) -> Result<()> {
     redis.delete_by_key(get_key(realm, key)).await?;
 
     Ok(())
 }
+
+/// Escapes the glob-style special characters, so the value matches only itself in a SCAN pattern
+fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Returns the Redis keys of all rate counters (and lockouts) by the rate key in every realm
+pub async fn find_rate_keys(
+    redis: &dyn KvStore,
+    key: &str
+) -> Result<Vec<String>> {
+    redis.scan_keys(get_key("*", &escape_glob(key))).await
+}
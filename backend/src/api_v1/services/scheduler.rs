@@ -0,0 +1,214 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use uuid::Uuid;
+use anyhow::Result;
+use crate::{
+    api_v1::{
+        repos::{
+            CertStore,
+            KvStore
+        },
+        services::{
+            cache,
+            expiry
+        },
+        types::redis::JobRun
+    },
+    configs,
+    utils::{
+        clock::Clock,
+        cron::CronSchedule,
+        log_error::ResultLogger
+    }
+};
+
+/// Only the replica that holds the lease runs the jobs
+pub const SCHEDULER_LEADER_KEY: &str = "scheduler:leader";
+/// The lease outlives a few ticks, so a short Redis hiccup doesn't hand the jobs over
+const LEADER_LEASE_SECONDS: i64 = 30;
+const TICK_SECONDS: u64 = 10;
+/// The amount of the latest runs kept for every job
+pub const JOB_HISTORY_LENGTH: i64 = 50;
+
+#[derive(Clone, Copy)]
+pub enum ScheduledJob {
+    ReconcileUsersCount,
+    PurgeDeletionRecords,
+    SendExpiryReminders
+}
+
+impl ScheduledJob {
+    pub fn all() -> &'static [ScheduledJob] {
+        &[Self::ReconcileUsersCount, Self::PurgeDeletionRecords, Self::SendExpiryReminders]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReconcileUsersCount => "reconcile_users_count",
+            Self::PurgeDeletionRecords => "purge_deletion_records",
+            Self::SendExpiryReminders => "send_expiry_reminders"
+        }
+    }
+
+    /// The cron expression of the job in UTC
+    pub fn schedule_expression(&self) -> &'static str {
+        match self {
+            Self::ReconcileUsersCount => "*/10 * * * *",
+            Self::PurgeDeletionRecords => "30 3 * * *",
+            Self::SendExpiryReminders => "0 9 * * *"
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .find(|job| job.as_str() == name)
+            .copied()
+    }
+
+    pub fn schedule(&self) -> CronSchedule {
+        CronSchedule::parse(self.schedule_expression())
+            .expect("Invalid built-in job schedule")
+    }
+
+    /// Runs the job and returns a short summary of the work done
+    async fn run(&self, redis: &dyn KvStore, cert_repo: &dyn CertStore, now: DateTime<Utc>) -> Result<String> {
+        match self {
+            Self::ReconcileUsersCount => {
+                // The counter is changed incrementally, so it drifts after failures and expirations
+                let count = cert_repo.count_active(now).await?;
+                cache::set_cache(redis, "stats:users_count", count, Duration::days(1)).await?;
+
+                Ok(format!("users count is {}", count))
+            },
+            Self::PurgeDeletionRecords => {
+                let retention = Duration::from_std(configs::get_deletion_records_retention())?;
+                let rolled_up = cert_repo.roll_up_deletion_records_before(now - retention).await?;
+
+                Ok(format!("rolled up {} deletion records", rolled_up))
+            },
+            Self::SendExpiryReminders => {
+                let sent = expiry::send_expiry_reminders(redis, cert_repo, now).await?;
+
+                Ok(format!("sent {} expiry reminders", sent))
+            }
+        }
+    }
+}
+
+fn get_last_run_key(job: ScheduledJob) -> String {
+    format!("scheduler:last_run:{}", job.as_str())
+}
+
+pub fn get_history_key(job: ScheduledJob) -> String {
+    format!("scheduler:history:{}", job.as_str())
+}
+
+/// Returns the time when the job was last started by any replica
+pub async fn get_last_run(redis: &dyn KvStore, job: ScheduledJob) -> Result<Option<DateTime<Utc>>> {
+    let timestamp: Option<i64> = redis.get_value(get_last_run_key(job)).await?;
+
+    Ok(timestamp.and_then(|a| DateTime::from_timestamp(a, 0)))
+}
+
+/// Returns the latest runs of the job, the newest first
+pub async fn get_history(redis: &dyn KvStore, job: ScheduledJob) -> Result<Vec<JobRun>> {
+    let raw_runs = redis.list_all(get_history_key(job)).await?;
+
+    Ok(
+        raw_runs
+            .iter()
+            .filter_map(|raw| serde_json::from_str(raw).ok())
+            .collect()
+    )
+}
+
+/// Runs the job and saves the result to the history
+pub async fn run_job(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    clock: &dyn Clock,
+    job: ScheduledJob, instance: &str
+) -> Result<JobRun> {
+    let started_at = clock.now();
+
+    // The start is saved first, so a replica that takes the lease during a long run doesn't repeat the job
+    redis.set_value(get_last_run_key(job), started_at.timestamp(), Duration::days(30), true).await?;
+
+    let result = job.run(redis, cert_repo, started_at).await;
+    let run = JobRun {
+        job: job.as_str().to_string(),
+        instance: instance.to_string(),
+        started_at: started_at.timestamp(),
+        finished_at: clock.now().timestamp(),
+        succeeded: result.is_ok(),
+        details: match &result {
+            Ok(summary) => summary.clone(),
+            Err(e) => e.to_string()
+        }
+    };
+
+    match &result {
+        Ok(summary) => info!("Scheduled job {} finished: {}", job.as_str(), summary),
+        Err(e) => warn!("Scheduled job {} failed: {}", job.as_str(), e)
+    }
+
+    let history_key = get_history_key(job);
+    redis.lpush(history_key.clone(), serde_json::to_string(&run)?).await?;
+    redis.list_trim(history_key, 0, JOB_HISTORY_LENGTH - 1).await?;
+
+    Ok(run)
+}
+
+/// Runs the built-in jobs by their schedules while this replica holds the leader lease
+/// Every replica runs the loop, the lease makes sure a job isn't run by several replicas at once
+pub async fn run_scheduler(redis: Arc<dyn KvStore>, cert_repo: Arc<dyn CertStore>, clock: Arc<dyn Clock>) {
+    let instance = Uuid::new_v4().to_string();
+    let mut is_leader = false;
+
+    info!("Job scheduler started as {}", instance);
+
+    loop {
+        let has_lease = redis.acquire_lease(
+            SCHEDULER_LEADER_KEY.to_string(),
+            instance.clone(),
+            Duration::seconds(LEADER_LEASE_SECONDS)
+        )
+            .await
+            .unwrap_or(false);
+
+        if has_lease != is_leader {
+            is_leader = has_lease;
+            info!("Job scheduler {} is {} the leader", instance, if is_leader { "now" } else { "no longer" });
+        }
+
+        if is_leader {
+            let now = clock.now();
+
+            for job in ScheduledJob::all() {
+                let last_run = match get_last_run(redis.as_ref(), *job).await {
+                    Ok(last_run) => last_run,
+                    Err(_) => continue
+                };
+
+                // A job that never ran waits for its next time, missed times are run once
+                let is_due = match last_run {
+                    Some(last_run) => job.schedule().next_after(last_run).is_some_and(|a| a <= now),
+                    None => {
+                        let _ = redis.set_value(get_last_run_key(*job), now.timestamp(), Duration::days(30), false).await;
+                        false
+                    }
+                };
+
+                if is_due {
+                    let _ = run_job(redis.as_ref(), cert_repo.as_ref(), clock.as_ref(), *job, &instance)
+                        .await
+                        .log_with_place_on_error("run_scheduler");
+                }
+            }
+        }
+
+        tokio::time::sleep(StdDuration::from_secs(TICK_SECONDS)).await;
+    }
+}
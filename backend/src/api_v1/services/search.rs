@@ -0,0 +1,132 @@
+use crate::utils::smart_trim::smart_trim;
+
+/// The Ukrainian national transliteration, multi-letter sequences go first so they win over single letters
+const LATIN_TO_CYRILLIC: &[(&str, &str)] = &[
+    ("shch", "щ"), ("zgh", "зг"),
+    ("zh", "ж"), ("kh", "х"), ("ts", "ц"), ("ch", "ч"), ("sh", "ш"),
+    ("ya", "я"), ("yu", "ю"), ("ye", "є"), ("yi", "ї"),
+    ("ia", "я"), ("iu", "ю"), ("ie", "є"),
+    ("a", "а"), ("b", "б"), ("v", "в"), ("h", "г"), ("g", "ґ"), ("d", "д"), ("e", "е"),
+    ("z", "з"), ("y", "и"), ("i", "і"), ("j", "й"), ("k", "к"), ("l", "л"), ("m", "м"),
+    ("n", "н"), ("o", "о"), ("p", "п"), ("r", "р"), ("s", "с"), ("t", "т"), ("u", "у"),
+    ("f", "ф"), ("c", "ц"), ("w", "в"), ("x", "кс"), ("q", "к"),
+];
+
+const CYRILLIC_TO_LATIN: &[(char, &str)] = &[
+    ('а', "a"), ('б', "b"), ('в', "v"), ('г', "h"), ('ґ', "g"), ('д', "d"), ('е', "e"),
+    ('є', "ie"), ('ж', "zh"), ('з', "z"), ('и', "y"), ('і', "i"), ('ї', "i"), ('й', "i"),
+    ('к', "k"), ('л', "l"), ('м', "m"), ('н', "n"), ('о', "o"), ('п', "p"), ('р', "r"),
+    ('с', "s"), ('т', "t"), ('у', "u"), ('ф', "f"), ('х', "kh"), ('ц', "ts"), ('ч', "ch"),
+    ('ш', "sh"), ('щ', "shch"), ('ь', ""), ('ю', "iu"), ('я', "ia"), ('\'', ""), ('’', ""),
+    ('ы', "y"), ('э', "e"), ('ё', "io"), ('ъ', ""),
+];
+
+/// The minimal length of a query, shorter ones match almost everything
+pub const MIN_QUERY_LENGTH: usize = 2;
+/// The maximal length of a query
+pub const MAX_QUERY_LENGTH: usize = 100;
+
+fn is_cyrillic(c: char) -> bool {
+    ('\u{0400}'..='\u{04FF}').contains(&c)
+}
+
+/// Transliterates the Cyrillic letters of the text into Latin ones, keeps other characters
+pub fn to_latin(text: &str) -> String {
+    text
+        .chars()
+        .map(|c| {
+            CYRILLIC_TO_LATIN
+                .iter()
+                .find(|(cyrillic, _)| *cyrillic == c)
+                .map_or(c.to_string(), |(_, latin)| latin.to_string())
+        })
+        .collect()
+}
+
+/// Transliterates the Latin letters of the text into Cyrillic ones, keeps other characters
+pub fn to_cyrillic(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() * 2);
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        match LATIN_TO_CYRILLIC.iter().find(|(latin, _)| rest.starts_with(latin)) {
+            Some((latin, cyrillic)) => {
+                result.push_str(cyrillic);
+                rest = &rest[latin.len()..];
+            },
+            None => {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    result
+}
+
+/// Normalizes the search query and returns it with its transliterated variant
+/// A mostly Cyrillic query gets a Latin variant and vice versa, so "Taras" finds "Тарас" and the other way around
+pub fn get_query_variants(query: &str) -> Option<(String, String)> {
+    let normalized = smart_trim(query).to_lowercase();
+    let length = normalized.chars().count();
+
+    if !(MIN_QUERY_LENGTH..=MAX_QUERY_LENGTH).contains(&length) {
+        return None;
+    }
+
+    let cyrillic_count = normalized.chars().filter(|c| is_cyrillic(*c)).count();
+    let latin_count = normalized.chars().filter(|c| c.is_ascii_alphabetic()).count();
+
+    let variant = if cyrillic_count >= latin_count {
+        to_latin(&normalized)
+    } else {
+        to_cyrillic(&normalized)
+    };
+
+    Some((normalized, variant))
+}
+
+/// Returns the character ranges [start, end) of the words in the text that start with any of the query words
+/// Character offsets are used, so the client can slice the text without knowing the UTF-8 encoding
+pub fn find_highlights(text: &str, query_variants: &[&str]) -> Vec<[usize; 2]> {
+    let query_words: Vec<&str> = query_variants
+        .iter()
+        .flat_map(|variant| variant.split_whitespace())
+        .collect();
+
+    let mut highlights = Vec::new();
+    let mut word_start: Option<usize> = None;
+    let chars: Vec<char> = text.chars().collect();
+
+    for index in 0..=chars.len() {
+        let is_word_char = chars.get(index).is_some_and(|c| c.is_alphanumeric() || *c == '\'' || *c == '’');
+
+        match (word_start, is_word_char) {
+            (None, true) => word_start = Some(index),
+            (Some(start), false) => {
+                let word: String = chars[start..index].iter().collect::<String>().to_lowercase();
+                let latin_word = to_latin(&word);
+
+                let matches = query_words.iter().any(|query_word| {
+                    // Longer query words may differ in the last letter because of the declension
+                    let length = query_word.chars().count();
+                    let prefix: String = if length > 3 {
+                        query_word.chars().take(length - 1).collect()
+                    } else {
+                        query_word.to_string()
+                    };
+
+                    word.starts_with(&prefix) || latin_word.starts_with(&prefix)
+                });
+
+                if matches {
+                    highlights.push([start, index]);
+                }
+                word_start = None;
+            },
+            _ => {}
+        }
+    }
+
+    highlights
+}
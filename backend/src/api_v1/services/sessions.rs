@@ -0,0 +1,90 @@
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use anyhow::{Result, anyhow};
+use crate::api_v1::repos::KvStore;
+
+/// The claims of the signed session token (JWT, HS256)
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SessionClaims {
+    /// The ID of the certificate that the session manages
+    pub sub: String,
+    pub email: String,
+    /// The ID of the session that the revocation list refers to
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64
+}
+
+impl SessionClaims {
+    pub fn cert_id(&self) -> Option<Uuid> {
+        Uuid::parse_str(&self.sub).ok()
+    }
+}
+
+/// Returns the revocation list key of the session
+/// Every revoked session has its own key that expires together with the session
+pub fn get_revoked_key(session_id: &str) -> String {
+    format!("session_revoked:{}", session_id)
+}
+
+/// Signs a new session that manages the certificate until the lifetime passes
+pub fn issue_session(
+    secret: &str,
+    cert_id: &Uuid, email: &str,
+    now: DateTime<Utc>, lifetime: Duration
+) -> Result<(String, SessionClaims)> {
+    let claims = SessionClaims {
+        sub: cert_id.to_string(),
+        email: email.to_string(),
+        jti: Uuid::new_v4().simple().to_string(),
+        iat: now.timestamp(),
+        exp: (now + lifetime).timestamp()
+    };
+
+    let token = jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes())
+    )?;
+
+    Ok((token, claims))
+}
+
+/// Checks the signature of the session token and returns its claims if it hasn't expired yet
+/// The expiration is checked with the passed time instead of the system one
+pub fn decode_session(secret: &str, token: &str, now: DateTime<Utc>) -> Result<SessionClaims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.validate_exp = false;
+
+    let claims = jsonwebtoken::decode::<SessionClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation
+    )?.claims;
+
+    if claims.exp <= now.timestamp() {
+        return Err(anyhow!("The session has expired"));
+    }
+
+    Ok(claims)
+}
+
+/// Returns true if the session was revoked before it expired
+pub async fn is_revoked(redis: &dyn KvStore, claims: &SessionClaims) -> Result<bool> {
+    Ok(
+        redis.get_string(get_revoked_key(&claims.jti))
+            .await?
+            .is_some()
+    )
+}
+
+/// Adds the session to the revocation list until the session expires by itself
+pub async fn revoke_session(redis: &dyn KvStore, claims: &SessionClaims, now: DateTime<Utc>) -> Result<()> {
+    let time_left = Duration::seconds(claims.exp - now.timestamp()).max(Duration::seconds(1));
+
+    redis.set_value(get_revoked_key(&claims.jti), 1, time_left, true).await?;
+
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use anyhow::Result;
+use crate::api_v1::{
+    repos::{
+        CertStore,
+        KvStore
+    },
+    services::cache
+};
+
+/// The maximum amount of buckets that can be requested at once
+pub const MAX_BUCKETS: usize = 366;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interval {
+    Day,
+    Week,
+    Month
+}
+
+impl Interval {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            _ => None
+        }
+    }
+
+    /// Returns the name of the interval that is also a PostgreSQL date_trunc unit
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month"
+        }
+    }
+
+    /// Returns the first day of the bucket that contains the date
+    /// Weeks start on Monday
+    pub fn truncate(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Month => date.with_day(1).unwrap_or(date)
+        }
+    }
+
+    /// Returns the first day of the next bucket
+    pub fn next(&self, bucket_start: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => bucket_start + Duration::days(1),
+            Self::Week => bucket_start + Duration::weeks(1),
+            Self::Month => bucket_start
+                .checked_add_months(Months::new(1))
+                .unwrap_or(bucket_start)
+        }
+    }
+
+    /// Returns the first day of the bucket that is `count` buckets before the bucket of the date
+    pub fn go_back(&self, date: NaiveDate, count: u32) -> NaiveDate {
+        let bucket_start = self.truncate(date);
+
+        match self {
+            Self::Day => bucket_start - Duration::days(count as i64),
+            Self::Week => bucket_start - Duration::weeks(count as i64),
+            Self::Month => bucket_start
+                .checked_sub_months(Months::new(count))
+                .unwrap_or(bucket_start)
+        }
+    }
+}
+
+pub struct Bucket {
+    pub start: NaiveDate,
+    pub created: u64,
+    pub deleted: u64
+}
+
+/// Converts the date into the UTC midnight of the date
+pub fn date_to_utc(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+}
+
+/// Returns the first days of the buckets that cover the date range
+/// Stops after MAX_BUCKETS + 1 buckets, so too wide ranges can be detected cheaply
+pub fn get_bucket_starts(
+    interval: Interval,
+    from: NaiveDate, to: NaiveDate
+) -> Vec<NaiveDate> {
+    let mut starts = Vec::new();
+    let mut current = interval.truncate(from);
+
+    while current <= to && starts.len() <= MAX_BUCKETS {
+        starts.push(current);
+        current = interval.next(current);
+    }
+
+    starts
+}
+
+/// Returns the amount of created and deleted certificates per bucket
+/// Closed buckets are immutable, so they are cached in the Redis storage and computed only once
+pub async fn get_buckets(
+    redis: &dyn KvStore,
+    cert_repo: &dyn CertStore,
+    interval: Interval, bucket_starts: &[NaiveDate], now: DateTime<Utc>
+) -> Result<Vec<Bucket>> {
+    let cache_key = format!("stats:timeseries:{}", interval.as_str());
+    let fields: Vec<String> = bucket_starts
+        .iter()
+        .map(|start| start.to_string())
+        .collect();
+
+    // Receive the cached buckets
+    let cached_values = cache::get_cache_fields(redis, &cache_key, fields)
+        .await
+        .unwrap_or_default();
+
+    let mut buckets: Vec<Bucket> = Vec::with_capacity(bucket_starts.len());
+    let mut missing: Vec<usize> = Vec::new();
+
+    for (index, start) in bucket_starts.iter().enumerate() {
+        let cached = cached_values
+            .get(index)
+            .cloned()
+            .flatten()
+            .and_then(|value| {
+                let (created, deleted) = value.split_once(":")?;
+                Some((created.parse::<u64>().ok()?, deleted.parse::<u64>().ok()?))
+            });
+
+        match cached {
+            Some((created, deleted)) => buckets.push(Bucket { start: *start, created, deleted }),
+            None => {
+                missing.push(index);
+                buckets.push(Bucket { start: *start, created: 0, deleted: 0 });
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(buckets);
+    }
+
+    // Compute the missing buckets from the data base
+    let from = date_to_utc(bucket_starts[missing[0]]);
+    let to = date_to_utc(interval.next(bucket_starts[*missing.last().unwrap()]));
+
+    let created_counts = cert_repo.count_created_by_buckets(interval.as_str(), from, to).await?;
+    let deleted_counts = cert_repo.count_deleted_by_buckets(interval.as_str(), from, to).await?;
+
+    let mut to_cache = HashMap::new();
+
+    for index in missing {
+        let bucket = &mut buckets[index];
+
+        bucket.created = created_counts
+            .iter()
+            .find(|(start, _)| start.date() == bucket.start)
+            .map_or(0, |(_, count)| *count);
+        bucket.deleted = deleted_counts
+            .iter()
+            .find(|(start, _)| start.date() == bucket.start)
+            .map_or(0, |(_, count)| *count);
+
+        // Only the buckets that have ended won't change anymore
+        if date_to_utc(interval.next(bucket.start)) <= now {
+            to_cache.insert(bucket.start.to_string(), format!("{}:{}", bucket.created, bucket.deleted));
+        }
+    }
+
+    let _ = cache::set_cache_fields(redis, &cache_key, to_cache, Duration::days(30)).await;
+
+    Ok(buckets)
+}
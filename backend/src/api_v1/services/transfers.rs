@@ -0,0 +1,71 @@
+use chrono::Duration;
+use uuid::Uuid;
+use anyhow::Result;
+use crate::api_v1::{
+    repos::KvStore,
+    types::redis::PendingTransfer
+};
+
+/// The transfer waits for the new holder as long as the code sent to them lives
+const PENDING_TRANSFER_LIFETIME_HOURS: i64 = 24;
+
+fn get_key(cert_id: &Uuid) -> String {
+    format!("transfer:{}", cert_id)
+}
+
+/// Remembers the transfer confirmed by the current holder, a newer transfer of the certificate replaces it
+pub async fn save_pending_transfer(
+    redis: &dyn KvStore,
+    cert_id: &Uuid, transfer: &PendingTransfer
+) -> Result<()> {
+    redis.set_string(
+        get_key(cert_id),
+        serde_json::to_string(transfer)?,
+        Duration::hours(PENDING_TRANSFER_LIFETIME_HOURS),
+        true
+    ).await?;
+
+    Ok(())
+}
+
+/// Returns the transfer of the certificate that waits for the new holder
+pub async fn get_pending_transfer(
+    redis: &dyn KvStore,
+    cert_id: &Uuid
+) -> Result<Option<PendingTransfer>> {
+    let Some(raw) = redis.get_string(get_key(cert_id)).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(serde_json::from_str(&raw)?))
+}
+
+/// Forgets the transfer after it's completed or the personal data is erased
+/// Returns 1 if the transfer was removed and 0 if there was none
+pub async fn remove_pending_transfer(
+    redis: &dyn KvStore,
+    cert_id: &Uuid
+) -> Result<u64> {
+    redis.delete_by_key(get_key(cert_id)).await
+}
+
+/// Returns the transfers that the email address gives or receives by the certificate IDs
+pub async fn find_transfers_of_email(
+    redis: &dyn KvStore,
+    email: &str
+) -> Result<Vec<(Uuid, PendingTransfer)>> {
+    let mut transfers = Vec::new();
+
+    for key in redis.scan_keys("transfer:*".to_string()).await? {
+        let Some(cert_id) = key.strip_prefix("transfer:").and_then(|id| Uuid::parse_str(id).ok()) else {
+            continue;
+        };
+
+        if let Some(transfer) = get_pending_transfer(redis, &cert_id).await?
+            && (transfer.old_email == email || transfer.new_email == email) {
+            transfers.push((cert_id, transfer));
+        }
+    }
+
+    Ok(transfers)
+}
@@ -0,0 +1,272 @@
+use std::{sync::Arc, time::Duration as StdDuration};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use rand::prelude::*;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+use uuid::Uuid;
+use validator::ValidationError;
+use anyhow::Result;
+use crate::{
+    api_v1::{
+        repos::{
+            KvStore,
+            WebhookDeliveryModel,
+            WebhookModel,
+            WebhookRepo
+        },
+        services::codes::EMAIL_TOKEN_SYMBOLS,
+        types::webhooks::WebhookPayload
+    },
+    utils::log_error::ResultLogger
+};
+
+/// The Redis sorted set with the delivery IDs scored by the time of the next attempt
+pub const WEBHOOK_JOBS_KEY: &str = "webhook_jobs";
+
+/// The events that can be subscribed to
+pub const SUBSCRIBABLE_EVENTS: &[&str] = &["cert.created", "cert.updated", "cert.deleted"];
+
+/// The delivery is considered failed after this number of attempts
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+
+/// The delay before the second attempt, every next attempt waits twice as long
+const FIRST_RETRY_DELAY_SECONDS: i64 = 10;
+
+pub const DELIVERY_STATUS_PENDING: &str = "pending";
+pub const DELIVERY_STATUS_SUCCEEDED: &str = "succeeded";
+pub const DELIVERY_STATUS_FAILED: &str = "failed";
+
+pub enum WebhookEvent {
+    CertCreated,
+    CertUpdated,
+    CertDeleted,
+    Test
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CertCreated => "cert.created",
+            Self::CertUpdated => "cert.updated",
+            Self::CertDeleted => "cert.deleted",
+            Self::Test => "webhook.test"
+        }
+    }
+}
+
+/// Checks if every event in the list can be subscribed to
+pub fn validate_webhook_events(events: &[String]) -> Result<(), ValidationError> {
+    if events.is_empty() {
+        return Err(ValidationError::new("no_events"));
+    }
+
+    if events.iter().any(|event| !SUBSCRIBABLE_EVENTS.contains(&event.as_str())) {
+        return Err(ValidationError::new("unknown_event"));
+    }
+
+    Ok(())
+}
+
+/// Generates a random secret used to sign the payloads of the webhook
+pub fn generate_webhook_secret() -> String {
+    let mut rng = OsRng;
+
+    let secret: String = (0..48).map(|_| {
+        let random_index = rng.gen_range(0..EMAIL_TOKEN_SYMBOLS.len());
+        EMAIL_TOKEN_SYMBOLS[random_index] as char
+    }).collect();
+
+    format!("whsec_{}", secret)
+}
+
+/// Signs the "{timestamp}.{body}" string with HMAC-SHA256 and returns the hex digest
+/// The receiver recomputes the signature and rejects requests with an old timestamp to prevent replays
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Returns the delay before the next attempt after the specified number of failed attempts
+fn get_retry_delay(attempts: i32) -> Duration {
+    Duration::seconds(FIRST_RETRY_DELAY_SECONDS << (attempts - 1).clamp(0, 16))
+}
+
+/// Saves the delivery of the event to the log and schedules it to be sent by the delivery worker
+pub async fn dispatch_to_webhook(
+    redis: &dyn KvStore,
+    webhook_repo: &WebhookRepo,
+    webhook: &WebhookModel, event: &WebhookEvent, data: serde_json::Value
+) -> Result<WebhookDeliveryModel> {
+    let now = Utc::now();
+    let delivery_id = Uuid::new_v4();
+
+    let payload = serde_json::to_string(&WebhookPayload {
+        id: delivery_id.to_string(),
+        event: event.as_str().to_string(),
+        created_at: now.timestamp() as u64,
+        data
+    })?;
+
+    let delivery = WebhookDeliveryModel {
+        id: delivery_id,
+        webhook_id: webhook.id,
+        event: event.as_str().to_string(),
+        payload,
+        status: DELIVERY_STATUS_PENDING.to_string(),
+        attempts: 0,
+        response_code: None,
+        last_error: None,
+        created_at: now,
+        next_attempt_at: Some(now),
+        delivered_at: None
+    };
+
+    webhook_repo.create_delivery(delivery.clone()).await?;
+    redis.schedule(WEBHOOK_JOBS_KEY.to_string(), delivery_id.to_string(), now).await?;
+
+    Ok(delivery)
+}
+
+/// Dispatches the event to every webhook subscribed to it
+pub async fn dispatch_event(
+    redis: &dyn KvStore,
+    webhook_repo: &WebhookRepo,
+    event: WebhookEvent, data: serde_json::Value
+) -> Result<()> {
+    let webhooks = webhook_repo.find_webhooks_by_event(event.as_str()).await?;
+
+    for webhook in webhooks {
+        let _ = dispatch_to_webhook(redis, webhook_repo, &webhook, &event, data.clone())
+            .await
+            .log_with_place_on_error("dispatch_event");
+    }
+
+    Ok(())
+}
+
+/// Sends the delivery to the webhook URL and saves the result of the attempt
+/// Reschedules the delivery with an exponential delay if the attempt failed
+async fn attempt_delivery(
+    client: &reqwest::Client,
+    redis: &dyn KvStore,
+    webhook_repo: &WebhookRepo,
+    delivery_id: Uuid
+) -> Result<()> {
+    let mut delivery = match webhook_repo.find_delivery_by_id(delivery_id).await? {
+        Some(delivery) if delivery.status == DELIVERY_STATUS_PENDING => delivery,
+        _ => {
+            return Ok(());
+        }
+    };
+
+    let webhook = match webhook_repo.find_webhook_by_id(delivery.webhook_id).await? {
+        Some(webhook) => webhook,
+        None => {
+            return Ok(());
+        }
+    };
+
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_payload(&webhook.secret, timestamp, &delivery.payload);
+
+    let response = client.post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", "Pupsiks-Webhooks/1.0")
+        .header("X-Pupsiks-Event", &delivery.event)
+        .header("X-Pupsiks-Delivery", delivery.id.to_string())
+        .header("X-Pupsiks-Timestamp", timestamp.to_string())
+        .header("X-Pupsiks-Signature", format!("sha256={}", signature))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    delivery.attempts += 1;
+
+    match response {
+        Ok(response) if response.status().is_success() => {
+            delivery.status = DELIVERY_STATUS_SUCCEEDED.to_string();
+            delivery.response_code = Some(response.status().as_u16() as i32);
+            delivery.last_error = None;
+            delivery.next_attempt_at = None;
+            delivery.delivered_at = Some(Utc::now());
+        },
+        failed => {
+            match failed {
+                Ok(response) => {
+                    delivery.response_code = Some(response.status().as_u16() as i32);
+                    delivery.last_error = Some(format!("Unexpected status code {}", response.status()));
+                },
+                Err(err) => {
+                    delivery.response_code = None;
+                    delivery.last_error = Some(err.to_string());
+                }
+            }
+
+            if delivery.attempts >= MAX_DELIVERY_ATTEMPTS {
+                delivery.status = DELIVERY_STATUS_FAILED.to_string();
+                delivery.next_attempt_at = None;
+                warn!("Webhook delivery {} failed after {} attempts", delivery.id, delivery.attempts);
+            } else {
+                let next_attempt_at = Utc::now() + get_retry_delay(delivery.attempts);
+                delivery.next_attempt_at = Some(next_attempt_at);
+
+                redis.schedule(WEBHOOK_JOBS_KEY.to_string(), delivery.id.to_string(), next_attempt_at).await?;
+            }
+        }
+    }
+
+    webhook_repo.update_delivery_attempt(&delivery).await?;
+
+    Ok(())
+}
+
+/// Schedules every pending delivery again at its next attempt time
+/// The worker takes the deliveries off the schedule before sending them, so the ones taken before a crash or a restart
+/// would be lost otherwise, a delivery that was sent but not saved is sent once more
+pub async fn requeue_pending_deliveries(redis: &dyn KvStore, webhook_repo: &WebhookRepo) -> Result<u64> {
+    let pending_deliveries = webhook_repo.find_deliveries_by_status(DELIVERY_STATUS_PENDING).await?;
+
+    for delivery in &pending_deliveries {
+        let next_attempt_at = delivery.next_attempt_at.unwrap_or(Utc::now());
+        redis.schedule(WEBHOOK_JOBS_KEY.to_string(), delivery.id.to_string(), next_attempt_at).await?;
+    }
+
+    Ok(pending_deliveries.len() as u64)
+}
+
+/// Endlessly takes the due deliveries from the schedule and sends them
+pub async fn run_delivery_worker(redis: Arc<dyn KvStore>, webhook_repo: WebhookRepo) {
+    let client = reqwest::Client::builder()
+        .timeout(StdDuration::from_secs(10))
+        .build()
+        .expect("Failed to build the webhooks HTTP client");
+
+    info!("Webhook delivery worker started");
+
+    if let Ok(requeued) = requeue_pending_deliveries(redis.as_ref(), &webhook_repo)
+        .await
+        .log_with_place_on_error("run_delivery_worker") {
+        info!("Requeued {} pending webhook deliveries", requeued);
+    }
+
+    loop {
+        let due_deliveries = redis.take_due(WEBHOOK_JOBS_KEY.to_string(), 20)
+            .await
+            .unwrap_or_default();
+
+        for delivery_id in due_deliveries {
+            if let Ok(uuid) = Uuid::parse_str(&delivery_id) {
+                let _ = attempt_delivery(&client, redis.as_ref(), &webhook_repo, uuid)
+                    .await
+                    .log_with_place_on_error("run_delivery_worker");
+            }
+        }
+
+        tokio::time::sleep(StdDuration::from_secs(1)).await;
+    }
+}
@@ -2,13 +2,27 @@ use actix_web::{
     body::BoxBody, 
     error, 
     http::{
-        header::ContentType, 
+        header::{
+            self, 
+            ContentType
+        }, 
         StatusCode
     }, 
-    HttpResponse
+    HttpResponse, 
+    HttpResponseBuilder
 };
 use derive_more::derive::{Display, Error};
-use crate::api_v1::types::responses::fail::*;
+use serde_json::{Map, Value};
+use crate::api_v1::{
+    services::locale::{
+        self, 
+        Language
+    }, 
+    types::responses::fail::*
+};
+
+/// The media type of the RFC 7807 error responses
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
 
 #[derive(Debug, Display, Error)]
 pub enum Errors {
@@ -75,30 +89,148 @@ pub enum Errors {
     },
 
     #[display("Requests rate limit hit")]
-    RequestsRateLimit
+    RequestsRateLimit,
+
+    #[display("Missing or invalid authorization token")]
+    Unauthorized,
+
+    #[display("Missing, expired or invalid challenge solution")]
+    InvalidChallenge,
+
+    #[display("The idempotency key was already used for another request")]
+    IdempotencyKeyReused,
+
+    #[display("The request with this idempotency key is still processed")]
+    IdempotencyKeyInProgress,
+
+    #[display("The certificate kind is no longer available")]
+    KindUnavailable,
+
+    #[display("The email already has the maximum number of certificates ({limit})")]
+    CertQuotaExceeded {
+        limit: u64
+    }
 }
 
 impl Errors {
-    /// Generates and returns the JSON message error
-    fn get_response_body(&self) -> BoxBody {
+    /// Returns the message in the language, the English display message is the last fallback
+    pub fn localized_message(&self, language: Language) -> String {
+        let (code_error, values): (&str, Vec<(&str, String)>) = match self {
+            Self::PageNotFound { .. } => ("page_not_found", vec![]),
+            Self::BadRequest { what_invalid } => ("bad_request", vec![("what_invalid", locale::translate_subject(language, what_invalid))]),
+            Self::ResourceNotFound { what } => ("resource_not_found", vec![("what", locale::translate_subject(language, what))]),
+            Self::InternalServer { what } => ("internal_server_error", vec![("what", locale::translate_subject(language, what))]),
+            Self::EmailRateLimit { how_much, .. } => ("email_rate_limit", vec![("how_much", how_much.to_string())]),
+            Self::IPRateLimit { how_much, .. } => ("ip_rate_limit", vec![("how_much", how_much.to_string())]),
+            Self::InvalidRoute { correct_route } => ("invalid_route", vec![("correct_route", correct_route.to_string())]),
+            Self::InvalidCode => ("invalid_code", vec![]),
+            Self::InvalidToken => ("invalid_token", vec![]),
+            Self::AlreadyExists { what } => ("already_exists", vec![("what", locale::translate_subject(language, what))]),
+            Self::TriesOut { how_much, .. } => ("tries_out", vec![("how_much", how_much.to_string())]),
+            Self::InvalidEmail => ("invalid_email", vec![]),
+            Self::PayloadTooLarge { bytes_limit } => ("payload_too_large", vec![("bytes_limit", bytes_limit.to_string())]),
+            Self::RequestsRateLimit => ("requests_rate_limit", vec![]),
+            Self::Unauthorized => ("unauthorized", vec![]),
+            Self::InvalidChallenge => ("invalid_challenge", vec![]),
+            Self::IdempotencyKeyReused => ("idempotency_key_reused", vec![]),
+            Self::IdempotencyKeyInProgress => ("idempotency_key_in_progress", vec![]),
+            Self::KindUnavailable => ("kind_unavailable", vec![]),
+            Self::CertQuotaExceeded { limit } => ("cert_quota_exceeded", vec![("limit", limit.to_string())])
+        };
+
+        locale::get_message(language, code_error, &values)
+            .unwrap_or_else(|| self.to_string())
+    }
+
+    /// Generates and returns the JSON message error in the language
+    fn get_response_json(&self, language: Language) -> Value {
+        match self {
+            Self::PageNotFound { endpoints } => serde_json::to_value(PageNotFoundErrorResponse::new(
+                endpoints.map_or(None, |a| Some(PageNotFoundErrorResponse::endpoints_to_vec(a))),
+                language
+            )).unwrap(),
+            Self::BadRequest { what_invalid } => serde_json::to_value(BadRequestErrorResponse::new(what_invalid, language)).unwrap(),
+            Self::ResourceNotFound { what } => serde_json::to_value(ResourceNotFoundErrorResponse::new(what, language)).unwrap(),
+            Self::InternalServer { what } => serde_json::to_value(InternalServerErrorResponse::new(what, language)).unwrap(),
+            Self::EmailRateLimit { how_much, timestamp } => serde_json::to_value(EmailRateLimitErrorResponse::new(*how_much, *timestamp, language)).unwrap(),
+            Self::IPRateLimit { how_much, timestamp } => serde_json::to_value(IPRateLimitErrorResponse::new(*how_much, *timestamp, language)).unwrap(),
+            Self::InvalidRoute { correct_route } => serde_json::to_value(InvalidRouteErrorResponse::new(correct_route, language)).unwrap(),
+            Self::InvalidCode => serde_json::to_value(InvalidCodeErrorResponse::new(language)).unwrap(),
+            Self::InvalidToken => serde_json::to_value(InvalidTokenErrorResponse::new(language)).unwrap(),
+            Self::AlreadyExists { what } => serde_json::to_value(AlreadyExistsErrorResponse::new(what, language)).unwrap(),
+            Self::TriesOut { how_much, timestamp } => serde_json::to_value(TriesOutErrorResponse::new(*how_much, *timestamp, language)).unwrap(),
+            Self::InvalidEmail => serde_json::to_value(InvalidEmailErrorResponse::new(language)).unwrap(),
+            Self::PayloadTooLarge { bytes_limit } => serde_json::to_value(PayloadTooLargeErrorResponse::new(*bytes_limit, language)).unwrap(),
+            Self::RequestsRateLimit => serde_json::to_value(RequestsRateLimitErrorResponse::new(language)).unwrap(),
+            Self::Unauthorized => serde_json::to_value(UnauthorizedErrorResponse::new(language)).unwrap(),
+            Self::InvalidChallenge => serde_json::to_value(InvalidChallengeErrorResponse::new(language)).unwrap(),
+            Self::IdempotencyKeyReused => serde_json::to_value(IdempotencyKeyReusedErrorResponse::new(language)).unwrap(),
+            Self::IdempotencyKeyInProgress => serde_json::to_value(IdempotencyKeyInProgressErrorResponse::new(language)).unwrap(),
+            Self::KindUnavailable => serde_json::to_value(KindUnavailableErrorResponse::new(language)).unwrap(),
+            Self::CertQuotaExceeded { limit } => serde_json::to_value(CertQuotaExceededErrorResponse::new(*limit, language)).unwrap()
+        }
+    }
+
+    /// Returns how many seconds the client should wait before retrying, every 429 error has it
+    pub fn retry_after(&self) -> Option<u32> {
         match self {
-            Self::PageNotFound { endpoints } => BoxBody::new(serde_json::to_string(&PageNotFoundErrorResponse::new(
-                endpoints.map_or(None, |a| Some(PageNotFoundErrorResponse::endpoints_to_vec(a)))
-            )).unwrap()),
-            Self::BadRequest { what_invalid } => BoxBody::new(serde_json::to_string(&BadRequestErrorResponse::new(what_invalid)).unwrap()),
-            Self::ResourceNotFound { what } => BoxBody::new(serde_json::to_string(&ResourceNotFoundErrorResponse::new(what)).unwrap()),
-            Self::InternalServer { what } => BoxBody::new(serde_json::to_string(&InternalServerErrorResponse::new(what)).unwrap()),
-            Self::EmailRateLimit { how_much, timestamp } => BoxBody::new(serde_json::to_string(&EmailRateLimitErrorResponse::new(*how_much, *timestamp)).unwrap()),
-            Self::IPRateLimit { how_much, timestamp } => BoxBody::new(serde_json::to_string(&IPRateLimitErrorResponse::new(*how_much, *timestamp)).unwrap()),
-            Self::InvalidRoute { correct_route } => BoxBody::new(serde_json::to_string(&InvalidRouteErrorResponse::new(correct_route)).unwrap()),
-            Self::InvalidCode => BoxBody::new(serde_json::to_string(&InvalidCodeErrorResponse::new()).unwrap()),
-            Self::InvalidToken => BoxBody::new(serde_json::to_string(&InvalidTokenErrorResponse::new()).unwrap()),
-            Self::AlreadyExists { what } => BoxBody::new(serde_json::to_string(&AlreadyExistsErrorResponse::new(what)).unwrap()),
-            Self::TriesOut { how_much, timestamp } => BoxBody::new(serde_json::to_string(&TriesOutErrorResponse::new(*how_much, *timestamp)).unwrap()),
-            Self::InvalidEmail => BoxBody::new(serde_json::to_string(&InvalidEmailErrorResponse::new()).unwrap()),
-            Self::PayloadTooLarge { bytes_limit } => BoxBody::new(serde_json::to_string(&PayloadTooLargeErrorResponse::new(*bytes_limit)).unwrap()),
-            Self::RequestsRateLimit => BoxBody::new(serde_json::to_string(&RequestsRateLimitErrorResponse::new()).unwrap())
+            Self::EmailRateLimit { how_much, .. } => Some(*how_much),
+            Self::IPRateLimit { how_much, .. } => Some(*how_much),
+            Self::TriesOut { how_much, .. } => Some(*how_much),
+            // The requests are counted per second
+            Self::RequestsRateLimit => Some(1),
+            _ => None
+        }
+    }
+
+    /// Builds the RFC 7807 representation, the members of the usual body except the message become the extension members
+    fn get_problem_details(&self, language: Language, instance: &str) -> ProblemDetailsResponse {
+        let status = error::ResponseError::status_code(self);
+        let mut extensions = match self.get_response_json(language) {
+            Value::Object(members) => members,
+            _ => Map::new()
+        };
+        extensions.remove("message");
+
+        let code_error = extensions.remove("code_error")
+            .and_then(|code_error| code_error.as_str().map(str::to_string))
+            .unwrap_or_default();
+
+        ProblemDetailsResponse::new(
+            code_error,
+            status,
+            self.localized_message(language),
+            instance,
+            extensions
+        )
+    }
+
+    /// Generates the error response in the language
+    pub fn localized_response(&self, language: Language) -> HttpResponse<BoxBody> {
+        self.build_response(language)
+            .content_type(ContentType::json())
+            .body(self.get_response_json(language).to_string())
+    }
+
+    /// Generates the "application/problem+json" error response in the language
+    /// The instance is the path of the request that failed
+    pub fn problem_response(&self, language: Language, instance: &str) -> HttpResponse<BoxBody> {
+        self.build_response(language)
+            .content_type(PROBLEM_JSON_CONTENT_TYPE)
+            .body(serde_json::to_string(&self.get_problem_details(language, instance)).unwrap())
+    }
+
+    fn build_response(&self, language: Language) -> HttpResponseBuilder {
+        let mut builder = HttpResponse::build(error::ResponseError::status_code(self));
+        builder
+            .insert_header((header::CONTENT_LANGUAGE, language.as_str()))
+            .insert_header((header::VARY, "Accept-Language, Accept"));
+
+        if let Some(seconds) = self.retry_after() {
+            builder.insert_header((header::RETRY_AFTER, seconds.to_string()));
         }
+
+        builder
     }
 }
 
@@ -118,13 +250,19 @@ impl error::ResponseError for Errors {
             Self::TriesOut { .. } => StatusCode::TOO_MANY_REQUESTS,
             Self::InvalidEmail { .. } => StatusCode::BAD_REQUEST,
             Self::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
-            Self::RequestsRateLimit { .. } => StatusCode::TOO_MANY_REQUESTS
+            Self::RequestsRateLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::InvalidChallenge => StatusCode::FORBIDDEN,
+            Self::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::IdempotencyKeyInProgress => StatusCode::CONFLICT,
+            Self::KindUnavailable => StatusCode::CONFLICT,
+            Self::CertQuotaExceeded { .. } => StatusCode::CONFLICT
         }
     }
 
+    /// The language of the request is unknown here, so the response is in the fallback language
+    /// The localization middleware renders it again in the negotiated language
     fn error_response(&self) -> HttpResponse<BoxBody> {
-        HttpResponse::build(self.status_code())
-            .content_type(ContentType::json())
-            .body(self.get_response_body())
+        self.localized_response(Language::default())
     }
 }
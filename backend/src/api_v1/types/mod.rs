@@ -2,3 +2,5 @@ pub mod responses;
 pub mod requests;
 pub mod redis;
 pub mod errors;
+pub mod webhooks;
+pub mod personal_data;
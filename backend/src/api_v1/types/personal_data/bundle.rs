@@ -0,0 +1,47 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct PersonalDataCertificate {
+    pub id: String,
+    pub name: String,
+    pub title: String,
+    pub kind: String,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub public: bool
+}
+
+#[derive(Serialize)]
+pub struct PersonalDataRateLimit {
+    pub key: String,
+    pub value: Option<u64>,
+    pub expires_at: String
+}
+
+#[derive(Serialize)]
+pub struct PersonalDataTransfer {
+    pub certificate: String,
+    /// "outgoing" if the address gives the certificate away, "incoming" if it receives it
+    pub direction: String
+}
+
+/// A response kept for the retries of a request with an idempotency key
+#[derive(Serialize)]
+pub struct PersonalDataStoredResponse {
+    pub key: String,
+    pub status: Option<u16>,
+    pub body: Option<String>
+}
+
+/// Everything stored about an email address
+#[derive(Serialize)]
+pub struct PersonalDataBundle {
+    pub email: String,
+    pub generated_at: String,
+    pub certificates: Vec<PersonalDataCertificate>,
+    pub rate_limits: Vec<PersonalDataRateLimit>,
+    pub pending_code: bool,
+    pub pending_transfers: Vec<PersonalDataTransfer>,
+    pub stored_responses: Vec<PersonalDataStoredResponse>,
+    pub queued_emails: Vec<String>
+}
@@ -0,0 +1,3 @@
+mod bundle;
+
+pub use bundle::*;
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct EmailTask {
     pub purpose: String,
     pub email: String,
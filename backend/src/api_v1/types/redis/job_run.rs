@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// A record of the scheduled job run kept in the run history
+#[derive(Serialize, Deserialize)]
+pub struct JobRun {
+    pub job: String,
+    pub instance: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub succeeded: bool,
+    pub details: String
+}
@@ -1,3 +1,7 @@
 mod email_task;
+mod job_run;
+mod pending_transfer;
 
 pub use email_task::*;
+pub use job_run::*;
+pub use pending_transfer::*;
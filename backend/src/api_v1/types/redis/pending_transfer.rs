@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// The transfer confirmed by the current holder and waiting for the code sent to the new email address
+#[derive(Serialize, Deserialize)]
+pub struct PendingTransfer {
+    pub old_email: String,
+    pub new_email: String
+}
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+use validator::Validate;
+use crate::{
+    utils::smart_trim::smart_trim,
+    api_v1::types::requests::{
+        CERT_NAME_MAX_LENGTH, 
+        CERT_NAME_MIN_LENGTH, 
+        CERT_TITLE_MAX_LENGTH, 
+        CERT_TITLE_MIN_LENGTH
+    }
+};
+
+#[derive(Deserialize, Debug)]
+pub struct BulkIssuanceQuery {
+    /// "skip" (default) or "update" the certificates of the emails that already have one
+    pub on_duplicate: Option<String>,
+    /// Whether to list the new certificates in the public gallery
+    pub public: Option<bool>,
+    /// The kind of the new certificates from the active catalogue, "classic" by default
+    pub kind: Option<String>
+}
+
+/// A CSV row of the bulk issuance, validated by the same rules as CreateCertRequest
+#[derive(Deserialize, Validate, Debug)]
+pub struct BulkCertRow {
+    #[validate(email)]
+    pub email: String,
+    #[validate(length(min = CERT_NAME_MIN_LENGTH, max = CERT_NAME_MAX_LENGTH))]
+    pub name: String,
+    #[validate(length(min = CERT_TITLE_MIN_LENGTH, max = CERT_TITLE_MAX_LENGTH))]
+    pub title: String
+}
+
+impl BulkCertRow {
+    pub fn trim(&self) -> Self {
+        Self {
+            email: smart_trim(&self.email),
+            name: smart_trim(&self.name),
+            title: smart_trim(&self.title),
+        }
+    }
+}
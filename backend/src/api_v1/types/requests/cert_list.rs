@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct CertListQuery {
+    /// "newest" (default) or "random"
+    pub order: Option<String>,
+    pub limit: Option<u64>,
+    pub cursor: Option<String>
+}
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct CertSearchQuery {
+    pub q: String,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>
+}
@@ -2,24 +2,49 @@ use serde::Deserialize;
 use validator::Validate;
 use crate::{
     utils::smart_trim::smart_trim,
-    api_v1::services::codes::{
-        validate_email_code, 
-        validate_email_token
+    api_v1::services::{
+        codes::{
+            validate_email_code, 
+            validate_email_token
+        }, 
+        kinds::{
+            DEFAULT_CERT_KIND, 
+            KindsContext, 
+            validate_cert_kind
+        }
     }
 };
 
+pub const CERT_NAME_MIN_LENGTH: u64 = 1;
+pub const CERT_NAME_MAX_LENGTH: u64 = 200;
+pub const CERT_TITLE_MIN_LENGTH: u64 = 5;
+pub const CERT_TITLE_MAX_LENGTH: u64 = 100;
+
+fn default_kind() -> String {
+    DEFAULT_CERT_KIND.to_string()
+}
+
+/// Validated with the time of the request, the kind must be in the active catalogue
 #[derive(Deserialize, Validate, Debug)]
+#[validate(context = KindsContext)]
 pub struct CreateCertRequest {
     #[validate(email)]
     pub email: String,
-    #[validate(length(min = 1, max = 200))]
+    #[validate(length(min = CERT_NAME_MIN_LENGTH, max = CERT_NAME_MAX_LENGTH))]
     pub name: String,
-    #[validate(length(min = 5, max = 100))]
+    #[validate(length(min = CERT_TITLE_MIN_LENGTH, max = CERT_TITLE_MAX_LENGTH))]
     pub title: String,
+    /// The kind from the catalogue, the classic one by default
+    #[serde(default = "default_kind")]
+    #[validate(custom(function = "validate_cert_kind", use_context))]
+    pub kind: String,
     #[validate(custom(function = "validate_email_code"))]
     pub code: String,
     #[validate(custom(function = "validate_email_token"))]
-    pub token: String
+    pub token: String,
+    /// Whether to list the certificate in the public gallery
+    #[serde(default)]
+    pub public: bool
 }
 
 impl CreateCertRequest {
@@ -28,8 +53,10 @@ impl CreateCertRequest {
             email: smart_trim(&self.email),
             name: smart_trim(&self.name),
             title: smart_trim(&self.title),
+            kind: smart_trim(&self.kind),
             code: smart_trim(&self.code),
             token: smart_trim(&self.token),
+            public: self.public,
         }
     }
 }
@@ -0,0 +1,26 @@
+use serde::Deserialize;
+use validator::Validate;
+use crate::{
+    utils::smart_trim::smart_trim,
+    api_v1::services::webhooks::validate_webhook_events
+};
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct CreateWebhookRequest {
+    #[validate(url, length(max = 500))]
+    pub url: String,
+    #[validate(custom(function = "validate_webhook_events"))]
+    pub events: Vec<String>
+}
+
+impl CreateWebhookRequest {
+    pub fn trim(&self) -> Self {
+        Self {
+            url: smart_trim(&self.url),
+            events: self.events
+                .iter()
+                .map(|event| smart_trim(event))
+                .collect(),
+        }
+    }
+}
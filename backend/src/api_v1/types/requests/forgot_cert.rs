@@ -6,12 +6,19 @@ use crate::utils::smart_trim::smart_trim;
 pub struct ForgotCertRequest {
     #[validate(email)]
     pub email: String,
+    /// The proof-of-work challenge from GET /api/v1/challenge and its solution
+    #[serde(default)]
+    pub challenge: String,
+    #[serde(default)]
+    pub solution: String
 }
 
 impl ForgotCertRequest {
     pub fn trim(&self) -> Self {
         Self {
             email: smart_trim(&self.email),
+            challenge: self.challenge.trim().to_string(),
+            solution: self.solution.trim().to_string(),
         }
     }
 }
@@ -2,8 +2,28 @@ mod send_code;
 mod create_cert;
 mod delete_cert;
 mod forgot_cert;
+mod create_webhook;
+mod stats_timeseries;
+mod update_visibility;
+mod cert_list;
+mod personal_data;
+mod bulk_issuance;
+mod cert_search;
+mod session;
+mod transfer_cert;
+mod renew_cert;
 
 pub use send_code::*;
 pub use create_cert::*;
 pub use delete_cert::*;
 pub use forgot_cert::*;
+pub use create_webhook::*;
+pub use stats_timeseries::*;
+pub use update_visibility::*;
+pub use cert_list::*;
+pub use personal_data::*;
+pub use bulk_issuance::*;
+pub use cert_search::*;
+pub use session::*;
+pub use transfer_cert::*;
+pub use renew_cert::*;
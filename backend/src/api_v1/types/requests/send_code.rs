@@ -11,13 +11,39 @@ pub enum SendCodePurposes {
     ConfirmDeletion{
         id: String,
     },
+    #[serde(rename = "visibility")]
+    ConfirmVisibility{
+        id: String,
+    },
+    #[serde(rename = "export")]
+    ConfirmExport,
+    #[serde(rename = "erase")]
+    ConfirmErasure,
+    #[serde(rename = "session")]
+    ConfirmSession{
+        id: String,
+    },
+    #[serde(rename = "transfer")]
+    ConfirmTransfer{
+        id: String,
+    },
+    #[serde(rename = "renew")]
+    ConfirmRenewal{
+        id: String,
+    },
 }
 
 impl ToString for SendCodePurposes {
     fn to_string(&self) -> String {
         match self {
             &Self::ConfirmCreation => "create".to_string(),
-            &Self::ConfirmDeletion { .. } => "delete".to_string()
+            &Self::ConfirmDeletion { .. } => "delete".to_string(),
+            &Self::ConfirmVisibility { .. } => "visibility".to_string(),
+            &Self::ConfirmExport => "export".to_string(),
+            &Self::ConfirmErasure => "erase".to_string(),
+            &Self::ConfirmSession { .. } => "session".to_string(),
+            &Self::ConfirmTransfer { .. } => "transfer".to_string(),
+            &Self::ConfirmRenewal { .. } => "renew".to_string()
         }
     }
 }
@@ -26,7 +52,12 @@ impl ToString for SendCodePurposes {
 pub struct SendCodeRequest {
     pub purpose: SendCodePurposes,
     #[validate(email)]
-    pub email: String
+    pub email: String,
+    /// The proof-of-work challenge from GET /api/v1/challenge and its solution
+    #[serde(default)]
+    pub challenge: String,
+    #[serde(default)]
+    pub solution: String
 }
 
 impl SendCodeRequest {
@@ -34,6 +65,8 @@ impl SendCodeRequest {
         Self {
             purpose: self.purpose.clone(),
             email: smart_trim(&self.email),
+            challenge: self.challenge.trim().to_string(),
+            solution: self.solution.trim().to_string(),
         }
     }
 }
@@ -0,0 +1,8 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct StatsTimeseriesQuery {
+    pub interval: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>
+}
@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use validator::Validate;
+use crate::{
+    utils::smart_trim::smart_trim,
+    api_v1::services::codes::{
+        validate_email_code, 
+        validate_email_token
+    }
+};
+
+/// Confirmed with the code sent to the current email address of the certificate
+#[derive(Deserialize, Validate, Debug)]
+pub struct TransferCertRequest {
+    #[validate(email)]
+    pub email: String,
+    #[validate(email)]
+    pub new_email: String,
+    #[validate(custom(function = "validate_email_code"))]
+    pub code: String,
+    #[validate(custom(function = "validate_email_token"))]
+    pub token: String
+}
+
+impl TransferCertRequest {
+    pub fn trim(&self) -> Self {
+        Self {
+            email: smart_trim(&self.email),
+            new_email: smart_trim(&self.new_email),
+            code: smart_trim(&self.code),
+            token: smart_trim(&self.token),
+        }
+    }
+}
+
+/// Confirmed with the code sent to the new email address
+#[derive(Deserialize, Validate, Debug)]
+pub struct ConfirmTransferRequest {
+    #[validate(email)]
+    pub email: String,
+    #[validate(custom(function = "validate_email_code"))]
+    pub code: String,
+    #[validate(custom(function = "validate_email_token"))]
+    pub token: String
+}
+
+impl ConfirmTransferRequest {
+    pub fn trim(&self) -> Self {
+        Self {
+            email: smart_trim(&self.email),
+            code: smart_trim(&self.code),
+            token: smart_trim(&self.token),
+        }
+    }
+}
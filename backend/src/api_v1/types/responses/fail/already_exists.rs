@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct AlreadyExistsErrorResponse {
@@ -8,10 +11,10 @@ pub struct AlreadyExistsErrorResponse {
 }
 
 impl AlreadyExistsErrorResponse {
-    pub fn new(what: &'static str) -> Self {
+    pub fn new(what: &'static str, language: Language) -> Self {
         Self { 
             code_error: "already_exists".to_string(),
-            message: Errors::AlreadyExists { what }.to_string(), 
+            message: Errors::AlreadyExists { what }.localized_message(language), 
         }
     }
 }
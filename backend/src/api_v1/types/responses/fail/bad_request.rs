@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct BadRequestErrorResponse {
@@ -8,10 +11,10 @@ pub struct BadRequestErrorResponse {
 }
 
 impl BadRequestErrorResponse {
-    pub fn new(what_invalid: &'static str) -> Self {
+    pub fn new(what_invalid: &'static str, language: Language) -> Self {
         Self {
             code_error: "bad_request".to_string(),
-            message: Errors::BadRequest { what_invalid: what_invalid }.to_string()
+            message: Errors::BadRequest { what_invalid }.localized_message(language)
         }
     }
 }
@@ -0,0 +1,22 @@
+use serde::Serialize;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
+
+#[derive(Serialize)]
+pub struct CertQuotaExceededErrorResponse {
+    pub code_error: String,
+    pub message: String,
+    pub limit: u64
+}
+
+impl CertQuotaExceededErrorResponse {
+    pub fn new(limit: u64, language: Language) -> Self {
+        Self {
+            code_error: "cert_quota_exceeded".to_string(),
+            message: Errors::CertQuotaExceeded { limit }.localized_message(language),
+            limit
+        }
+    }
+}
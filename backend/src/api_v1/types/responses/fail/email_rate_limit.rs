@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct EmailRateLimitErrorResponse {
@@ -9,10 +12,10 @@ pub struct EmailRateLimitErrorResponse {
 }
 
 impl EmailRateLimitErrorResponse {
-    pub fn new(how_much: u32, timestamp: u64) -> Self {
+    pub fn new(how_much: u32, timestamp: u64, language: Language) -> Self {
         Self {
             code_error: "email_rate_limit".to_string(),
-            message: Errors::EmailRateLimit { how_much, timestamp }.to_string(),
+            message: Errors::EmailRateLimit { how_much, timestamp }.localized_message(language),
             timestamp
         }
     }
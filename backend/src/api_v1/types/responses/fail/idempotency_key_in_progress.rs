@@ -0,0 +1,20 @@
+use serde::Serialize;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
+
+#[derive(Serialize)]
+pub struct IdempotencyKeyInProgressErrorResponse {
+    pub code_error: String,
+    pub message: String,
+}
+
+impl IdempotencyKeyInProgressErrorResponse {
+    pub fn new(language: Language) -> Self {
+        Self { 
+            code_error: "idempotency_key_in_progress".to_string(),
+            message: Errors::IdempotencyKeyInProgress.localized_message(language), 
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
+
+#[derive(Serialize)]
+pub struct IdempotencyKeyReusedErrorResponse {
+    pub code_error: String,
+    pub message: String,
+}
+
+impl IdempotencyKeyReusedErrorResponse {
+    pub fn new(language: Language) -> Self {
+        Self { 
+            code_error: "idempotency_key_reused".to_string(),
+            message: Errors::IdempotencyKeyReused.localized_message(language), 
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct InternalServerErrorResponse {
@@ -8,10 +11,10 @@ pub struct InternalServerErrorResponse {
 }
 
 impl InternalServerErrorResponse {
-    pub fn new(what: &'static str) -> Self {
+    pub fn new(what: &'static str, language: Language) -> Self {
         Self { 
             code_error: "internal_server_error".to_string(),
-            message: Errors::InternalServer { what }.to_string(), 
+            message: Errors::InternalServer { what }.localized_message(language), 
         }
     }
 }
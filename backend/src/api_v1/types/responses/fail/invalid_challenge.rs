@@ -0,0 +1,20 @@
+use serde::Serialize;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
+
+#[derive(Serialize)]
+pub struct InvalidChallengeErrorResponse {
+    pub code_error: String,
+    pub message: String,
+}
+
+impl InvalidChallengeErrorResponse {
+    pub fn new(language: Language) -> Self {
+        Self { 
+            code_error: "invalid_challenge".to_string(),
+            message: Errors::InvalidChallenge.localized_message(language), 
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct InvalidCodeErrorResponse {
@@ -8,10 +11,10 @@ pub struct InvalidCodeErrorResponse {
 }
 
 impl InvalidCodeErrorResponse {
-    pub fn new() -> Self {
+    pub fn new(language: Language) -> Self {
         Self { 
             code_error: "invalid_code".to_string(),
-            message: Errors::InvalidCode.to_string(), 
+            message: Errors::InvalidCode.localized_message(language), 
         }
     }
 }
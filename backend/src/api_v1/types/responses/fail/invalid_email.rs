@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct InvalidEmailErrorResponse {
@@ -8,10 +11,10 @@ pub struct InvalidEmailErrorResponse {
 }
 
 impl InvalidEmailErrorResponse {
-    pub fn new() -> Self {
+    pub fn new(language: Language) -> Self {
         Self { 
             code_error: "invalid_email".to_string(),
-            message: Errors::InvalidEmail.to_string(), 
+            message: Errors::InvalidEmail.localized_message(language), 
         }
     }
 }
@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct InvalidRouteErrorResponse {
@@ -8,10 +11,10 @@ pub struct InvalidRouteErrorResponse {
 }
 
 impl InvalidRouteErrorResponse {
-    pub fn new(correct_route: &'static str) -> Self {
+    pub fn new(correct_route: &'static str, language: Language) -> Self {
         Self {
             code_error: "invalid_route".to_string(),
-            message: Errors::InvalidRoute { correct_route: correct_route }.to_string(),
+            message: Errors::InvalidRoute { correct_route }.localized_message(language),
         }
     }
 }
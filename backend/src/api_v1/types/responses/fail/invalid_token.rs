@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct InvalidTokenErrorResponse {
@@ -8,10 +11,10 @@ pub struct InvalidTokenErrorResponse {
 }
 
 impl InvalidTokenErrorResponse {
-    pub fn new() -> Self {
+    pub fn new(language: Language) -> Self {
         Self { 
             code_error: "invalid_token".to_string(),
-            message: Errors::InvalidToken.to_string(), 
+            message: Errors::InvalidToken.localized_message(language), 
         }
     }
 }
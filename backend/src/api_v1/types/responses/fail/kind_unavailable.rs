@@ -0,0 +1,20 @@
+use serde::Serialize;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
+
+#[derive(Serialize)]
+pub struct KindUnavailableErrorResponse {
+    pub code_error: String,
+    pub message: String,
+}
+
+impl KindUnavailableErrorResponse {
+    pub fn new(language: Language) -> Self {
+        Self { 
+            code_error: "kind_unavailable".to_string(),
+            message: Errors::KindUnavailable.localized_message(language), 
+        }
+    }
+}
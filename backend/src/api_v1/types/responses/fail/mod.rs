@@ -12,6 +12,13 @@ mod tries_out;
 mod invalid_email;
 mod payload_too_large;
 mod requests_rate_limit;
+mod unauthorized;
+mod invalid_challenge;
+mod idempotency_key_reused;
+mod idempotency_key_in_progress;
+mod kind_unavailable;
+mod cert_quota_exceeded;
+mod problem_details;
 
 pub use bad_request::*;
 pub use email_rate_limit::*;
@@ -27,3 +34,10 @@ pub use tries_out::*;
 pub use invalid_email::*;
 pub use payload_too_large::*;
 pub use requests_rate_limit::*;
+pub use unauthorized::*;
+pub use invalid_challenge::*;
+pub use idempotency_key_reused::*;
+pub use idempotency_key_in_progress::*;
+pub use kind_unavailable::*;
+pub use cert_quota_exceeded::*;
+pub use problem_details::*;
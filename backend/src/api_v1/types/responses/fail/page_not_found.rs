@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct PageNotFoundErrorResponse {
@@ -9,10 +12,10 @@ pub struct PageNotFoundErrorResponse {
 }
 
 impl PageNotFoundErrorResponse {
-    pub fn new(endpoints: Option<Vec<String>>) -> Self {
+    pub fn new(endpoints: Option<Vec<String>>, language: Language) -> Self {
         Self { 
             code_error: "page_not_found".to_string(),
-            message: Errors::PageNotFound { endpoints: None }.to_string(), 
+            message: Errors::PageNotFound { endpoints: None }.localized_message(language), 
             endpoints: endpoints
         }
     }
@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct PayloadTooLargeErrorResponse {
@@ -8,10 +11,10 @@ pub struct PayloadTooLargeErrorResponse {
 }
 
 impl PayloadTooLargeErrorResponse {
-    pub fn new(bytes_limit: usize) -> Self {
+    pub fn new(bytes_limit: usize, language: Language) -> Self {
         Self { 
             code_error: "payload_too_large".to_string(),
-            message: Errors::PayloadTooLarge { bytes_limit }.to_string(), 
+            message: Errors::PayloadTooLarge { bytes_limit }.localized_message(language), 
         }
     }
 }
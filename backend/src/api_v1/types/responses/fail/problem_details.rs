@@ -0,0 +1,38 @@
+use actix_web::http::StatusCode;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The RFC 7807 representation of an error
+#[derive(Serialize)]
+pub struct ProblemDetailsResponse {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub instance: String,
+    /// The same code as in the usual error body, so the clients can switch gradually
+    pub code_error: String,
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>
+}
+
+impl ProblemDetailsResponse {
+    pub fn new(
+        code_error: String,
+        status: StatusCode,
+        detail: String,
+        instance: &str,
+        extensions: Map<String, Value>
+    ) -> Self {
+        Self {
+            problem_type: format!("urn:pupsiks:problem:{}", code_error),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail,
+            instance: instance.to_string(),
+            code_error,
+            extensions
+        }
+    }
+}
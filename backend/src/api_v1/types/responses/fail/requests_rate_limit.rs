@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct RequestsRateLimitErrorResponse {
@@ -8,10 +11,10 @@ pub struct RequestsRateLimitErrorResponse {
 }
 
 impl RequestsRateLimitErrorResponse {
-    pub fn new() -> Self {
+    pub fn new(language: Language) -> Self {
         Self { 
             code_error: "requests_rate_limit".to_string(),
-            message: Errors::RequestsRateLimit.to_string(), 
+            message: Errors::RequestsRateLimit.localized_message(language), 
         }
     }
 }
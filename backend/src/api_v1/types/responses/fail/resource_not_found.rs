@@ -1,5 +1,8 @@
 use serde::Serialize;
-use crate::api_v1::types::errors::Errors;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
 
 #[derive(Serialize)]
 pub struct ResourceNotFoundErrorResponse {
@@ -8,10 +11,10 @@ pub struct ResourceNotFoundErrorResponse {
 }
 
 impl ResourceNotFoundErrorResponse {
-    pub fn new(what: &'static str) -> Self {
+    pub fn new(what: &'static str, language: Language) -> Self {
         Self { 
             code_error: "resource_not_found".to_string(),
-            message: Errors::ResourceNotFound { what }.to_string(), 
+            message: Errors::ResourceNotFound { what }.localized_message(language), 
         }
     }
 }
@@ -0,0 +1,20 @@
+use serde::Serialize;
+use crate::api_v1::{
+    services::locale::Language, 
+    types::errors::Errors
+};
+
+#[derive(Serialize)]
+pub struct UnauthorizedErrorResponse {
+    pub code_error: String,
+    pub message: String,
+}
+
+impl UnauthorizedErrorResponse {
+    pub fn new(language: Language) -> Self {
+        Self { 
+            code_error: "unauthorized".to_string(),
+            message: Errors::Unauthorized.localized_message(language), 
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::Serialize;
+use sea_orm::prelude::Uuid;
+use short_uuid::ShortUuid;
+
+#[derive(Serialize)]
+pub struct BulkRowResult {
+    /// The line number in the CSV file, the header is line 1
+    pub row: u64,
+    pub email: Option<String>,
+    /// "created", "updated", "skipped" or "failed"
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>
+}
+
+impl BulkRowResult {
+    pub fn success(row: u64, email: String, status: &'static str, id: &Uuid) -> Self {
+        Self {
+            row,
+            email: Some(email),
+            status,
+            id: Some(ShortUuid::from_uuid(id).to_string()),
+            error: None
+        }
+    }
+
+    pub fn failed(row: u64, email: Option<String>, error: &str) -> Self {
+        Self {
+            row,
+            email,
+            status: "failed",
+            id: None,
+            error: Some(error.to_string())
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BulkIssuanceResponse {
+    pub created: u64,
+    pub updated: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub rows: Vec<BulkRowResult>
+}
+
+impl BulkIssuanceResponse {
+    pub fn new(rows: Vec<BulkRowResult>) -> Self {
+        let count = |status: &str| rows.iter().filter(|row| row.status == status).count() as u64;
+
+        Self {
+            created: count("created"),
+            updated: count("updated"),
+            skipped: count("skipped"),
+            failed: count("failed"),
+            rows
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use serde::Serialize;
+use crate::api_v1::services::{
+    kinds::CertKind,
+    locale::Language
+};
+
+#[derive(Serialize)]
+pub struct CertKindResponse {
+    pub id: String,
+    pub name: String,
+    pub default_titles: Vec<String>,
+    pub available_from: Option<u64>,
+    pub available_until: Option<u64>,
+    pub cap: Option<u64>,
+    /// How many certificates of the kind can still be created, null if the kind has no cap
+    pub remaining: Option<u64>
+}
+
+impl CertKindResponse {
+    pub fn new(kind: CertKind, remaining: Option<u64>, language: Language) -> Self {
+        Self {
+            name: kind.localized_name(language),
+            id: kind.id,
+            default_titles: kind.default_titles,
+            available_from: kind.available_from.map(|time| time.timestamp() as u64),
+            available_until: kind.available_until.map(|time| time.timestamp() as u64),
+            cap: kind.cap,
+            remaining
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CertKindsResponse {
+    pub kinds: Vec<CertKindResponse>
+}
+
+impl CertKindsResponse {
+    pub fn new(kinds: Vec<CertKindResponse>) -> Self {
+        Self {
+            kinds
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use serde::Serialize;
+use crate::api_v1::types::responses::success::CertificateResponse;
+
+#[derive(Serialize)]
+pub struct CertListResponse {
+    pub certs: Vec<CertificateResponse>,
+    pub next_cursor: Option<String>
+}
+
+impl CertListResponse {
+    pub fn new(certs: Vec<CertificateResponse>, next_cursor: Option<String>) -> Self {
+        Self {
+            certs,
+            next_cursor
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DailyCertResponse {
+    pub date: String,
+    pub cert: CertificateResponse
+}
+
+impl DailyCertResponse {
+    pub fn new(date: String, cert: CertificateResponse) -> Self {
+        Self {
+            date,
+            cert
+        }
+    }
+}
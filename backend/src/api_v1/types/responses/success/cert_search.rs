@@ -0,0 +1,48 @@
+use sea_orm::prelude::Uuid;
+use serde::Serialize;
+use short_uuid::ShortUuid;
+
+/// Character ranges [start, end) of the matched words
+#[derive(Serialize)]
+pub struct CertHighlights {
+    pub name: Vec<[usize; 2]>,
+    pub title: Vec<[usize; 2]>
+}
+
+#[derive(Serialize)]
+pub struct CertSearchResult {
+    pub id: String,
+    pub name: String,
+    pub title: String,
+    pub rank: f64,
+    pub highlights: CertHighlights
+}
+
+impl CertSearchResult {
+    pub fn new(id: &Uuid, name: String, title: String, rank: f64, highlights: CertHighlights) -> Self {
+        Self {
+            id: ShortUuid::from_uuid(id).to_string(),
+            name,
+            title,
+            rank,
+            highlights
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CertSearchResponse {
+    pub query: String,
+    pub results: Vec<CertSearchResult>,
+    pub next_offset: Option<u64>
+}
+
+impl CertSearchResponse {
+    pub fn new(query: String, results: Vec<CertSearchResult>, next_offset: Option<u64>) -> Self {
+        Self {
+            query,
+            results,
+            next_offset
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use sea_orm::prelude::Uuid;
+use serde::Serialize;
+use short_uuid::ShortUuid;
+
+#[derive(Serialize)]
+pub struct CertVisibilityResponse {
+    pub id: String,
+    pub public: bool
+}
+
+impl CertVisibilityResponse {
+    pub fn new(id: &Uuid, public: bool) -> Self {
+        Self {
+            id: ShortUuid::from_uuid(id).to_string(),
+            public
+        }
+    }
+}
@@ -1,20 +1,42 @@
+use chrono::{DateTime, Utc};
 use sea_orm::prelude::Uuid;
 use serde::Serialize;
 use short_uuid::ShortUuid;
+use crate::api_v1::services::expiry;
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CertStatus {
+    Active,
+    Expired
+}
 
 #[derive(Serialize)]
 pub struct CertificateResponse {
     pub id: String,
     pub name: String,
     pub title: String,
+    pub kind: String,
+    pub status: CertStatus,
+    /// The Unix time the certificate expires at, null if it never expires
+    pub expires_at: Option<u64>,
 }
 
 impl CertificateResponse {
-    pub fn new(id: &Uuid, name: String, title: String) -> Self {
+    pub fn new(
+        id: &Uuid, name: String, title: String, kind: String,
+        expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>
+    ) -> Self {
         Self {
             id: ShortUuid::from_uuid(id).to_string(),
             name,
-            title
+            title,
+            kind,
+            status: match expiry::is_expired(expires_at, now) {
+                true => CertStatus::Expired,
+                false => CertStatus::Active
+            },
+            expires_at: expires_at.map(|time| time.timestamp() as u64)
         }
     }
 }
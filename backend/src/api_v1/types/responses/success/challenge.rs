@@ -0,0 +1,19 @@
+use serde::Serialize;
+use crate::api_v1::services::challenge::Challenge;
+
+#[derive(Serialize)]
+pub struct ChallengeResponse {
+    pub challenge: String,
+    pub difficulty: u32,
+    pub expires_at: u64
+}
+
+impl ChallengeResponse {
+    pub fn new(challenge: Challenge) -> Self {
+        Self {
+            challenge: challenge.challenge,
+            difficulty: challenge.difficulty,
+            expires_at: challenge.expires_at as u64
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use serde::Serialize;
+use crate::api_v1::repos::ErasureReceiptModel;
+
+/// The receipt is public, so it has nothing that confirms the erased email address
+#[derive(Serialize)]
+pub struct ErasureReceiptResponse {
+    pub id: String,
+    pub certificates_removed: i32,
+    pub cache_keys_removed: i32,
+    pub email_jobs_removed: i32,
+    pub webhook_deliveries_removed: i32,
+    pub erased_at: u64
+}
+
+impl ErasureReceiptResponse {
+    pub fn new(receipt: &ErasureReceiptModel) -> Self {
+        Self {
+            id: receipt.id.to_string(),
+            certificates_removed: receipt.certificates_removed,
+            cache_keys_removed: receipt.cache_keys_removed,
+            email_jobs_removed: receipt.email_jobs_removed,
+            webhook_deliveries_removed: receipt.webhook_deliveries_removed,
+            erased_at: receipt.erased_at.timestamp() as u64
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use serde::Serialize;
+use crate::api_v1::types::redis::JobRun;
+
+#[derive(Serialize)]
+pub struct JobRunResponse {
+    pub instance: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub succeeded: bool,
+    pub details: String
+}
+
+impl JobRunResponse {
+    pub fn new(run: &JobRun) -> Self {
+        Self {
+            instance: run.instance.clone(),
+            started_at: run.started_at as u64,
+            finished_at: run.finished_at as u64,
+            succeeded: run.succeeded,
+            details: run.details.clone()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct JobResponse {
+    pub name: String,
+    pub schedule: String,
+    pub last_run_at: Option<u64>,
+    pub next_run_at: Option<u64>,
+    pub runs: Vec<JobRunResponse>
+}
+
+#[derive(Serialize)]
+pub struct JobListResponse {
+    pub leader: Option<String>,
+    pub jobs: Vec<JobResponse>
+}
+
+impl JobListResponse {
+    pub fn new(leader: Option<String>, jobs: Vec<JobResponse>) -> Self {
+        Self {
+            leader,
+            jobs
+        }
+    }
+}
@@ -3,9 +3,33 @@ mod code_sent;
 mod users_count;
 mod cert_id;
 mod cert_email;
+mod webhook;
+mod webhook_delivery;
+mod timeseries;
+mod cert_list;
+mod cert_visibility;
+mod erasure_receipt;
+mod bulk_issuance;
+mod cert_search;
+mod job;
+mod session;
+mod challenge;
+mod cert_kind;
 
 pub use certificate::*;
 pub use code_sent::*;
 pub use users_count::*;
 pub use cert_id::*;
 pub use cert_email::*;
+pub use webhook::*;
+pub use webhook_delivery::*;
+pub use timeseries::*;
+pub use cert_list::*;
+pub use cert_visibility::*;
+pub use erasure_receipt::*;
+pub use bulk_issuance::*;
+pub use cert_search::*;
+pub use job::*;
+pub use session::*;
+pub use challenge::*;
+pub use cert_kind::*;
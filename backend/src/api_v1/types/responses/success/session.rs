@@ -0,0 +1,44 @@
+use sea_orm::prelude::Uuid;
+use serde::Serialize;
+use short_uuid::ShortUuid;
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub session: String,
+    pub id: String,
+    pub expires_at: u64
+}
+
+impl SessionResponse {
+    pub fn new(session: String, id: &Uuid, expires_at: u64) -> Self {
+        Self {
+            session,
+            id: ShortUuid::from_uuid(id).to_string(),
+            expires_at
+        }
+    }
+}
+
+/// The certificate that the session manages
+#[derive(Serialize)]
+pub struct MeResponse {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub title: String,
+    pub public: bool,
+    pub session_expires_at: u64
+}
+
+impl MeResponse {
+    pub fn new(id: &Uuid, email: String, name: String, title: String, public: bool, session_expires_at: u64) -> Self {
+        Self {
+            id: ShortUuid::from_uuid(id).to_string(),
+            email,
+            name,
+            title,
+            public,
+            session_expires_at
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+use crate::api_v1::services::timeseries::Bucket;
+
+#[derive(Serialize)]
+pub struct StatsTimeseriesBucket {
+    pub start: String,
+    pub created: u64,
+    pub deleted: u64,
+    pub net: i64,
+    pub total: u64,
+    pub growth_rate: Option<f64>
+}
+
+#[derive(Serialize)]
+pub struct StatsTimeseriesTotals {
+    pub created: u64,
+    pub deleted: u64,
+    pub net: i64,
+    pub total_at_start: u64,
+    pub total_at_end: u64,
+    pub growth_rate: Option<f64>
+}
+
+#[derive(Serialize)]
+pub struct StatsTimeseriesResponse {
+    pub interval: String,
+    pub from: String,
+    pub to: String,
+    pub buckets: Vec<StatsTimeseriesBucket>,
+    pub totals: StatsTimeseriesTotals
+}
+
+/// Returns the relative change of the value, None if there was nothing to grow from
+fn growth_rate(before: u64, after: u64) -> Option<f64> {
+    if before == 0 {
+        None
+    } else {
+        Some((after as f64 - before as f64) / before as f64)
+    }
+}
+
+impl StatsTimeseriesResponse {
+    /// Computes the running totals and growth rates from the amount of certificates before the first bucket
+    pub fn new(interval: &str, from: String, to: String, total_at_start: u64, buckets: Vec<Bucket>) -> Self {
+        let mut total = total_at_start;
+        let mut created = 0;
+        let mut deleted = 0;
+
+        let buckets = buckets
+            .into_iter()
+            .map(|bucket| {
+                let total_before = total;
+                total = (total + bucket.created).saturating_sub(bucket.deleted);
+                created += bucket.created;
+                deleted += bucket.deleted;
+
+                StatsTimeseriesBucket {
+                    start: bucket.start.to_string(),
+                    created: bucket.created,
+                    deleted: bucket.deleted,
+                    net: bucket.created as i64 - bucket.deleted as i64,
+                    total,
+                    growth_rate: growth_rate(total_before, total)
+                }
+            })
+            .collect();
+
+        Self {
+            interval: interval.to_string(),
+            from,
+            to,
+            buckets,
+            totals: StatsTimeseriesTotals {
+                created,
+                deleted,
+                net: created as i64 - deleted as i64,
+                total_at_start,
+                total_at_end: total,
+                growth_rate: growth_rate(total_at_start, total)
+            }
+        }
+    }
+}
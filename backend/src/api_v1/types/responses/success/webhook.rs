@@ -0,0 +1,46 @@
+use serde::Serialize;
+use crate::api_v1::repos::WebhookModel;
+
+#[derive(Serialize)]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>
+}
+
+impl WebhookResponse {
+    /// Builds the response without the signing secret
+    pub fn new(webhook: &WebhookModel) -> Self {
+        Self {
+            id: webhook.id.to_string(),
+            url: webhook.url.clone(),
+            events: webhook.events.clone(),
+            created_at: webhook.created_at.timestamp() as u64,
+            secret: None
+        }
+    }
+
+    /// Builds the response with the signing secret, used only once right after the creation
+    pub fn with_secret(webhook: &WebhookModel) -> Self {
+        Self {
+            secret: Some(webhook.secret.clone()),
+            ..Self::new(webhook)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct WebhookListResponse {
+    pub webhooks: Vec<WebhookResponse>
+}
+
+impl WebhookListResponse {
+    pub fn new(webhooks: Vec<WebhookResponse>) -> Self {
+        Self {
+            webhooks
+        }
+    }
+}
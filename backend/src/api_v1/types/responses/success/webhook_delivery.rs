@@ -0,0 +1,46 @@
+use serde::Serialize;
+use crate::api_v1::repos::WebhookDeliveryModel;
+
+#[derive(Serialize)]
+pub struct WebhookDeliveryResponse {
+    pub id: String,
+    pub webhook_id: String,
+    pub event: String,
+    pub status: String,
+    pub attempts: i32,
+    pub response_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: u64,
+    pub next_attempt_at: Option<u64>,
+    pub delivered_at: Option<u64>
+}
+
+impl WebhookDeliveryResponse {
+    pub fn new(delivery: &WebhookDeliveryModel) -> Self {
+        Self {
+            id: delivery.id.to_string(),
+            webhook_id: delivery.webhook_id.to_string(),
+            event: delivery.event.clone(),
+            status: delivery.status.clone(),
+            attempts: delivery.attempts,
+            response_code: delivery.response_code,
+            last_error: delivery.last_error.clone(),
+            created_at: delivery.created_at.timestamp() as u64,
+            next_attempt_at: delivery.next_attempt_at.map(|a| a.timestamp() as u64),
+            delivered_at: delivery.delivered_at.map(|a| a.timestamp() as u64)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDeliveryResponse>
+}
+
+impl WebhookDeliveriesResponse {
+    pub fn new(deliveries: Vec<WebhookDeliveryResponse>) -> Self {
+        Self {
+            deliveries
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod payload;
+
+pub use payload::*;
@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct WebhookPayload {
+    pub id: String,
+    pub event: String,
+    pub created_at: u64,
+    pub data: serde_json::Value
+}
@@ -0,0 +1,381 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    sync::Arc
+};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use short_uuid::ShortUuid;
+use uuid::Uuid;
+
+use backend::{
+    api_v1::{
+        repos::{
+            CertModel,
+            CertRepo,
+            CertStore,
+            CreationError,
+            KvStore,
+            RedisRepo,
+            WebhookRepo
+        },
+        services::{
+            cache,
+            cert_cache,
+            email::EMAIL_JOBS_KEY,
+            expiry,
+            kinds::DEFAULT_CERT_KIND,
+            rate_limits,
+            webhooks::{
+                self,
+                WebhookEvent
+            }
+        },
+        types::{
+            redis::EmailTask,
+            responses::success::CertificateResponse
+        }
+    },
+    connections,
+    utils::uuid::get_uuid
+};
+
+const EXPORT_BATCH_SIZE: u64 = 500;
+
+/// Maintenance tool of the Pupsiks backend
+/// Uses the same data base and Redis connection settings as the server
+#[derive(Parser)]
+#[command(name = "pupsctl", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage certificates
+    #[command(subcommand)]
+    Cert(CertCommand),
+
+    /// Manage cached statistics
+    #[command(subcommand)]
+    Stats(StatsCommand),
+
+    /// Manage rate limits
+    #[command(subcommand)]
+    RateLimit(RateLimitCommand),
+
+    /// Inspect the email queue
+    #[command(subcommand)]
+    Queue(QueueCommand),
+
+    /// Export all certificates as JSON Lines
+    Export {
+        /// Output file, stdout by default
+        #[arg(short, long)]
+        output: Option<String>
+    },
+
+    /// Import certificates from JSON Lines, the existing ones are skipped
+    Import {
+        /// Input file, stdin by default
+        #[arg(short, long)]
+        input: Option<String>
+    }
+}
+
+#[derive(Subcommand)]
+enum CertCommand {
+    /// Show a certificate by the serial number (short or full UUID) or all certificates of the email address
+    Get {
+        serial_or_email: String
+    },
+
+    /// Delete a certificate by the serial number (short or full UUID) or the only certificate of the email address
+    Delete {
+        serial_or_email: String
+    }
+}
+
+#[derive(Subcommand)]
+enum StatsCommand {
+    /// Recompute the cached amount of certificates from the data base
+    RecomputeUsersCount
+}
+
+#[derive(Subcommand)]
+enum RateLimitCommand {
+    /// Remove all rate counters and lockouts of the email address
+    Clear {
+        email: String
+    }
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// Show the letters waiting in the email queue
+    Emails {
+        /// Show only the amount of letters
+        #[arg(long)]
+        count: bool
+    }
+}
+
+/// A certificate line of the backup file
+#[derive(Serialize, Deserialize)]
+struct CertRecord {
+    id: Uuid,
+    email: String,
+    name: String,
+    title: String,
+    /// The backups made before the kinds have no kind, so they are classic
+    #[serde(default = "default_kind")]
+    kind: String,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    public: bool,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    /// The backups made before the expiration have no expiration, so the certificates never expire
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>
+}
+
+fn default_kind() -> String {
+    DEFAULT_CERT_KIND.to_string()
+}
+
+impl From<CertModel> for CertRecord {
+    fn from(cert: CertModel) -> Self {
+        Self {
+            id: cert.id,
+            email: cert.email,
+            name: cert.name,
+            title: cert.title,
+            kind: cert.kind,
+            created_at: cert.created_at,
+            public: cert.is_public,
+            updated_at: cert.updated_at,
+            expires_at: cert.expires_at
+        }
+    }
+}
+
+impl From<CertRecord> for CertModel {
+    fn from(record: CertRecord) -> Self {
+        Self {
+            id: record.id,
+            email: record.email,
+            name: record.name,
+            title: record.title,
+            kind: record.kind,
+            created_at: record.created_at,
+            is_public: record.public,
+            updated_at: record.updated_at,
+            expires_at: record.expires_at
+        }
+    }
+}
+
+/// Receives a certificate by the serial number or all certificates of the email address
+async fn find_certs(cert_repo: &dyn CertStore, serial_or_email: &str) -> Result<Vec<CertModel>> {
+    if serial_or_email.contains('@') {
+        return cert_repo.find_certs_by_email(serial_or_email.trim().to_string()).await;
+    }
+
+    let id = get_uuid(serial_or_email)
+        .ok_or(anyhow!("\"{}\" is neither a serial number nor an email address", serial_or_email))?;
+
+    Ok(cert_repo.find_cert_by_id(id).await?.into_iter().collect())
+}
+
+fn print_cert(cert: &CertModel) {
+    println!("Serial:     {}", ShortUuid::from_uuid(&cert.id));
+    println!("UUID:       {}", cert.id);
+    println!("Email:      {}", cert.email);
+    println!("Name:       {}", cert.name);
+    println!("Title:      {}", cert.title);
+    println!("Kind:       {}", cert.kind);
+    println!("Created at: {}", cert.created_at.to_rfc3339());
+    println!("Expires at: {}", cert.expires_at.map_or("never".to_string(), |expires_at| expires_at.to_rfc3339()));
+    println!("Public:     {}", cert.is_public);
+}
+
+/// Sets the cached amount of certificates to the real one and returns it
+async fn recompute_users_count(redis: &dyn KvStore, cert_repo: &dyn CertStore) -> Result<u64> {
+    let count = cert_repo.count_active(Utc::now()).await?;
+
+    cache::set_cache(redis, "stats:users_count", count, Duration::days(1)).await?;
+
+    Ok(count)
+}
+
+async fn run(command: Command) -> Result<()> {
+    let database = Arc::new(connections::get_database_connection().await?);
+    connections::sync_database_schema(&database).await?;
+    let redis = RedisRepo::new(Arc::new(connections::get_redis_client().await?));
+    let cert_repo = CertRepo::new(database.clone());
+    let webhook_repo = WebhookRepo::new(database);
+
+    match command {
+        Command::Cert(CertCommand::Get { serial_or_email }) => {
+            let certs = find_certs(&cert_repo, &serial_or_email).await?;
+            if certs.is_empty() {
+                return Err(anyhow!("Certificate not found"));
+            }
+
+            for (index, cert) in certs.iter().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+
+                print_cert(cert);
+            }
+        },
+        Command::Cert(CertCommand::Delete { serial_or_email }) => {
+            let mut certs = find_certs(&cert_repo, &serial_or_email).await?;
+            if certs.len() > 1 {
+                return Err(anyhow!("The email address has {} certificates, delete them by the serial numbers", certs.len()));
+            }
+
+            let cert = certs.pop().ok_or(anyhow!("Certificate not found"))?;
+
+            let deletion_count = cert_repo.remove_cert_by_id(cert.id).await?;
+            if deletion_count == 0 {
+                return Err(anyhow!("Certificate not found"));
+            }
+
+            // Keep the caches and the webhook subscribers consistent with the API deletion
+            let now = Utc::now();
+            let _ = cert_cache::invalidate_cert(&redis, &cert.id).await;
+
+            if !expiry::is_expired(cert.expires_at, now) {
+                let _ = redis.increase_by(
+                    cache::get_key("stats:users_count"),
+                    -1,
+                    Duration::days(1)
+                ).await;
+            }
+
+            let _ = webhooks::dispatch_event(
+                &redis,
+                &webhook_repo,
+                WebhookEvent::CertDeleted,
+                serde_json::to_value(&CertificateResponse::new(
+                    &cert.id,
+                    cert.name.clone(),
+                    cert.title.clone(),
+                    cert.kind.clone(),
+                    cert.expires_at,
+                    now
+                )).unwrap_or_default()
+            ).await;
+
+            println!("Deleted certificate {}", ShortUuid::from_uuid(&cert.id));
+        },
+        Command::Stats(StatsCommand::RecomputeUsersCount) => {
+            let count = recompute_users_count(&redis, &cert_repo).await?;
+
+            println!("Users count is {}", count);
+        },
+        Command::RateLimit(RateLimitCommand::Clear { email }) => {
+            let mut removed = 0;
+            for key in rate_limits::find_rate_keys(&redis, email.trim()).await? {
+                removed += redis.delete_by_key(key).await?;
+            }
+
+            println!("Removed {} rate limit records", removed);
+        },
+        Command::Queue(QueueCommand::Emails { count }) => {
+            if count {
+                println!("{}", redis.list_len(EMAIL_JOBS_KEY.to_string()).await?);
+                return Ok(());
+            }
+
+            // The replacements may contain confirmation codes, so they are not shown
+            let jobs = redis.list_all(EMAIL_JOBS_KEY.to_string()).await?;
+            for raw in jobs.iter().rev() {
+                match serde_json::from_str::<EmailTask>(raw) {
+                    Ok(task) => println!("{:<12} {}", task.purpose, task.email),
+                    Err(_) => println!("{:<12} {}", "<invalid>", raw)
+                }
+            }
+
+            println!("Total: {}", jobs.len());
+        },
+        Command::Export { output } => {
+            let mut writer: Box<dyn Write> = match output {
+                Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(BufWriter::new(io::stdout().lock()))
+            };
+
+            let mut cursor = None;
+            let mut exported = 0;
+
+            loop {
+                let batch = cert_repo.find_certs_after_id(cursor, EXPORT_BATCH_SIZE).await?;
+                let Some(last) = batch.last() else {
+                    break;
+                };
+                cursor = Some(last.id);
+
+                for cert in batch {
+                    writeln!(writer, "{}", serde_json::to_string(&CertRecord::from(cert))?)?;
+                    exported += 1;
+                }
+            }
+
+            writer.flush()?;
+            eprintln!("Exported {} certificates", exported);
+        },
+        Command::Import { input } => {
+            let reader: Box<dyn BufRead> = match input {
+                Some(path) => Box::new(BufReader::new(File::open(path)?)),
+                None => Box::new(BufReader::new(io::stdin().lock()))
+            };
+
+            let mut imported = 0;
+            let mut skipped = 0;
+
+            for (index, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let record: CertRecord = serde_json::from_str(&line)
+                    .map_err(|e| anyhow!("Line {}: {}", index + 1, e))?;
+
+                // The backup is restored as is, the quota applies only to the new certificates
+                if cert_repo.find_cert_by_id(record.id).await?.is_some() {
+                    skipped += 1;
+                    continue;
+                }
+
+                match cert_repo.create_cert(record.into(), u64::MAX, None).await {
+                    Ok(_) => imported += 1,
+                    Err(CreationError::QuotaExceeded | CreationError::KindCapReached) => skipped += 1,
+                    Err(CreationError::Another(e)) => return Err(e.context(format!("Line {}", index + 1)))
+                }
+            }
+
+            let count = recompute_users_count(&redis, &cert_repo).await?;
+
+            eprintln!("Imported {} certificates, skipped {} existing, users count is {}", imported, skipped, count);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli.command).await {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,8 +1,20 @@
-use std::env;
+use std::{env, net::IpAddr, path::PathBuf, time::Duration};
 use fred::prelude::Config;
+use ipnet::IpNet;
+use anyhow::Result;
+use crate::{
+    api_v1::services::kinds::{self, CertKind},
+    tls::acme::AcmeChallenge
+};
 
-/// Generates a Postgres connection URL from the DB_USER and DB_PASS environment variables
+/// Returns the data base connection URL from the DATABASE_URL environment variable
+/// A `sqlite://` URL switches the storage to SQLite, e.g. `sqlite:///data/pupsiks.db?mode=rwc`
+/// Without the variable a Postgres connection URL is generated from the DB_USER and DB_PASS environment variables
 pub fn get_db_url() -> String {
+    if let Some(url) = env::var("DATABASE_URL").ok().filter(|url| !url.trim().is_empty()) {
+        return url;
+    }
+
     let user = env::var("DB_USER").unwrap();
     let pass = env::var("DB_PASS").unwrap();
 
@@ -13,3 +25,315 @@ pub fn get_db_url() -> String {
 pub fn get_redis_config() -> Config {
     Config::from_url("redis://redis:6379").unwrap()
 }
+
+/// Returns the token that grants access to the administrative endpoints from the ADMIN_TOKEN environment variable
+/// Returns None if the variable is not set or empty, so the administrative endpoints are disabled
+pub fn get_admin_token() -> Option<String> {
+    env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+}
+
+/// Returns the key that signs the certificate management sessions from the SESSION_SECRET environment variable
+/// Returns None if the variable is not set or empty, so the sessions are disabled
+pub fn get_session_secret() -> Option<String> {
+    env::var("SESSION_SECRET")
+        .ok()
+        .filter(|secret| !secret.trim().is_empty())
+}
+
+/// Returns how long a certificate management session lasts
+/// Reads the SESSION_LIFETIME_MINUTES environment variable, 30 minutes by default
+pub fn get_session_lifetime() -> Duration {
+    env::var("SESSION_LIFETIME_MINUTES")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|minutes| Duration::from_secs(minutes * 60))
+        .unwrap_or(Duration::from_secs(30 * 60))
+}
+
+/// Returns the key that signs the proof-of-work challenges from the CHALLENGE_SECRET environment variable
+/// Returns None if the variable is not set or empty, then every replica signs with its own random key
+pub fn get_challenge_secret() -> Option<String> {
+    env::var("CHALLENGE_SECRET")
+        .ok()
+        .filter(|secret| !secret.trim().is_empty())
+}
+
+/// Returns the smallest and the largest number of the leading zero bits the challenge solutions need
+/// Reads the CHALLENGE_MIN_DIFFICULTY and CHALLENGE_MAX_DIFFICULTY environment variables, 16 and 22 bits by default
+pub fn get_challenge_difficulty_range() -> (u32, u32) {
+    let read = |name: &str, default: u32| env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .unwrap_or(default)
+        .min(64);
+
+    let min = read("CHALLENGE_MIN_DIFFICULTY", 16);
+    let max = read("CHALLENGE_MAX_DIFFICULTY", 22).max(min);
+
+    (min, max)
+}
+
+/// Reads a comma separated list of the prefix lengths, the invalid and too long ones are skipped
+fn read_prefixes(name: &str, default: &str, max_length: u8) -> Vec<u8> {
+    let mut prefixes: Vec<u8> = env::var(name)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or(default.to_string())
+        .split(',')
+        .filter_map(|prefix| prefix.trim().trim_start_matches('/').parse::<u8>().ok())
+        .filter(|prefix| *prefix <= max_length)
+        .collect();
+
+    // From the narrowest network to the widest one
+    prefixes.sort_unstable_by(|a, b| b.cmp(a));
+    prefixes.dedup();
+
+    prefixes
+}
+
+/// Returns the prefix lengths of the IPv4 networks that the IP rate limits are counted by, from the narrowest to the widest
+/// Reads the RATE_LIMIT_IPV4_PREFIXES environment variable, "32,24" by default
+pub fn get_ipv4_limit_prefixes() -> Vec<u8> {
+    read_prefixes("RATE_LIMIT_IPV4_PREFIXES", "32,24", 32)
+}
+
+/// Returns the prefix lengths of the IPv6 networks that the IP rate limits are counted by, from the narrowest to the widest
+/// Reads the RATE_LIMIT_IPV6_PREFIXES environment variable, "64,48" by default
+pub fn get_ipv6_limit_prefixes() -> Vec<u8> {
+    read_prefixes("RATE_LIMIT_IPV6_PREFIXES", "64,48", 128)
+}
+
+/// Returns how many times the limit of a network is larger than the limit of the previous narrower one
+/// Reads the RATE_LIMIT_SUBNET_FACTOR environment variable, 4 by default
+pub fn get_subnet_limit_factor() -> u64 {
+    env::var("RATE_LIMIT_SUBNET_FACTOR")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|factor| *factor > 0)
+        .unwrap_or(4)
+}
+
+/// Parses a network in the CIDR notation, a single address is a network too
+fn parse_network(value: &str) -> Option<IpNet> {
+    value.parse::<IpNet>()
+        .ok()
+        .or_else(|| value.parse::<IpAddr>().ok().map(IpNet::from))
+        .map(|network| network.trunc())
+}
+
+/// Returns the networks with higher IP rate limits and how many times their limits are larger
+/// Reads the RATE_LIMIT_ALLOWLIST environment variable, a comma separated list of CIDRs with optional factors
+/// e.g. "192.0.2.0/24=20,2001:db8::/32", the factor is 10 by default
+pub fn get_ip_allowlist() -> Vec<(IpNet, u64)> {
+    let Ok(value) = env::var("RATE_LIMIT_ALLOWLIST") else {
+        return vec![];
+    };
+
+    value.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (network, factor) = match entry.split_once('=') {
+                Some((network, factor)) => (network.trim(), factor.trim().parse::<u64>().ok().filter(|factor| *factor > 0)),
+                None => (entry, Some(10))
+            };
+
+            match (parse_network(network), factor) {
+                (Some(network), Some(factor)) => Some((network, factor)),
+                _ => {
+                    log::warn!("Invalid RATE_LIMIT_ALLOWLIST entry {} is skipped", entry);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Returns the networks of the reverse proxies which forwarded headers are trusted
+/// Reads the TRUSTED_PROXIES environment variable, a comma separated list of CIDRs, "none" trusts nobody
+/// Only the loopback is trusted by default, a proxy in the private network must be listed
+/// Otherwise every host of the network could pretend to be any client
+pub fn get_trusted_proxies() -> Vec<IpNet> {
+    let value = env::var("TRUSTED_PROXIES")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or("127.0.0.0/8,::1/128".to_string());
+
+    if value.trim().eq_ignore_ascii_case("none") {
+        return vec![];
+    }
+
+    value.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let network = parse_network(entry);
+            if network.is_none() {
+                log::warn!("Invalid TRUSTED_PROXIES entry {} is skipped", entry);
+            }
+
+            network
+        })
+        .collect()
+}
+
+/// Returns the origins of the other sites that may call the API from a browser
+/// Reads the CORS_ALLOWED_ORIGINS environment variable, a comma separated list like "https://example.com", nobody by default
+pub fn get_cors_allowed_origins() -> Vec<String> {
+    env::var("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect()
+}
+
+/// Returns the path of the certificate kinds catalogue from the CERT_KINDS_FILE environment variable
+/// Returns None if the variable is not set, then the built-in catalogue with the classic kind is used
+pub fn get_cert_kinds_file() -> Option<PathBuf> {
+    env::var("CERT_KINDS_FILE")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns how many certificates one email address may have, at least one
+/// Reads the CERTS_PER_EMAIL_LIMIT environment variable, 5 by default
+pub fn get_certs_per_email_limit() -> u64 {
+    env::var("CERTS_PER_EMAIL_LIMIT")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(5)
+        .max(1)
+}
+
+/// Returns the domains of the built-in TLS certificate from the TLS_DOMAINS environment variable, a comma separated list
+/// Returns an empty list if the variable is not set, then the built-in TLS is disabled
+pub fn get_tls_domains() -> Vec<String> {
+    env::var("TLS_DOMAINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|domain| domain.trim().to_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+/// Returns the port of the HTTPS listener from the TLS_PORT environment variable, 8443 by default
+pub fn get_tls_port() -> u16 {
+    env::var("TLS_PORT")
+        .ok()
+        .and_then(|value| value.trim().parse::<u16>().ok())
+        .unwrap_or(8443)
+}
+
+/// Returns the directory where the ACME account and the certificate are stored
+/// Reads the TLS_STORAGE_DIR environment variable, "/data/tls" by default
+pub fn get_tls_storage_dir() -> PathBuf {
+    env::var("TLS_STORAGE_DIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or(PathBuf::from("/data/tls"))
+}
+
+/// Returns the directory URL of the ACME server from the ACME_DIRECTORY_URL environment variable
+/// Let's Encrypt is used by default, a local test server like Pebble can be used instead
+pub fn get_acme_directory_url() -> String {
+    env::var("ACME_DIRECTORY_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .unwrap_or("https://acme-v02.api.letsencrypt.org/directory".to_string())
+}
+
+/// Returns the email address the ACME server sends the expiration warnings to
+/// Reads the ACME_CONTACT_EMAIL environment variable
+pub fn get_acme_contact_email() -> Option<String> {
+    env::var("ACME_CONTACT_EMAIL")
+        .ok()
+        .filter(|email| !email.trim().is_empty())
+}
+
+/// Returns the ACME challenge from the ACME_CHALLENGE environment variable, "http-01" or "tls-alpn-01" (by default)
+pub fn get_acme_challenge() -> AcmeChallenge {
+    match env::var("ACME_CHALLENGE").unwrap_or_default().trim().to_lowercase().as_str() {
+        "http-01" => AcmeChallenge::Http01,
+        _ => AcmeChallenge::TlsAlpn01
+    }
+}
+
+/// Returns how long the server stays unready before it starts draining connections on shutdown
+/// Reads the SHUTDOWN_GRACE_SECONDS environment variable, 5 seconds by default
+pub fn get_shutdown_grace_period() -> Duration {
+    env::var("SHUTDOWN_GRACE_SECONDS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(5))
+}
+
+/// Returns how long the deletion records are kept before the scheduler rolls them up into the daily counts
+/// Reads the DELETION_RECORDS_RETENTION_DAYS environment variable, 365 days by default
+pub fn get_deletion_records_retention() -> Duration {
+    env::var("DELETION_RECORDS_RETENTION_DAYS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+        .unwrap_or(Duration::from_secs(365 * 24 * 60 * 60))
+}
+
+/// Returns how long the new and renewed certificates are valid, a kind of the catalogue may have its own period
+/// Reads the CERT_VALIDITY_DAYS environment variable, the certificates never expire if it's not set or 0
+pub fn get_cert_validity() -> Option<Duration> {
+    env::var("CERT_VALIDITY_DAYS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|days| *days > 0)
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+}
+
+/// Returns how long before the expiration the holder receives the reminder
+/// Reads the EXPIRY_REMINDER_DAYS environment variable, 14 days by default
+pub fn get_expiry_reminder_period() -> Duration {
+    env::var("EXPIRY_REMINDER_DAYS")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|days| Duration::from_secs(days * 24 * 60 * 60))
+        .unwrap_or(Duration::from_secs(14 * 24 * 60 * 60))
+}
+
+/// The settings the API handlers read, they are passed to the API scopes as the app data
+/// The server reads them from the environment once, the tests build their own
+#[derive(Clone, Debug)]
+pub struct ApiSettings {
+    pub session_secret: Option<String>,
+    pub cors_allowed_origins: Vec<String>,
+    /// The certificate kinds catalogue, loaded from CERT_KINDS_FILE once
+    pub cert_kinds: Vec<CertKind>,
+    pub certs_per_email_limit: u64,
+    pub cert_validity: Option<Duration>,
+    /// The networks with higher IP rate limits and how many times their limits are larger
+    pub ip_allowlist: Vec<(IpNet, u64)>,
+    pub subnet_limit_factor: u64,
+    pub ipv4_limit_prefixes: Vec<u8>,
+    pub ipv6_limit_prefixes: Vec<u8>
+}
+
+impl ApiSettings {
+    /// Reads the settings from the environment variables
+    /// Returns an error if the certificate kinds catalogue can't be loaded
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            session_secret: get_session_secret(),
+            cors_allowed_origins: get_cors_allowed_origins(),
+            cert_kinds: kinds::load_catalogue(get_cert_kinds_file().as_deref())?,
+            certs_per_email_limit: get_certs_per_email_limit(),
+            cert_validity: get_cert_validity(),
+            ip_allowlist: get_ip_allowlist(),
+            subnet_limit_factor: get_subnet_limit_factor(),
+            ipv4_limit_prefixes: get_ipv4_limit_prefixes(),
+            ipv6_limit_prefixes: get_ipv6_limit_prefixes()
+        })
+    }
+}
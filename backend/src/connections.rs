@@ -3,7 +3,11 @@ use fred::prelude::*;
 use anyhow::Result;
 use sea_orm::{Database, DatabaseConnection};
 use crate::{
-    api_v1::register_cert_in_db_schema, 
+    api_v1::{
+        create_extra_db_indexes, 
+        drop_unique_email_constraint, 
+        register_models_in_db_schema
+    }, 
     configs
 };
 
@@ -23,12 +27,21 @@ pub async fn get_redis_client() -> Result<Client> {
     Ok(redis)
 }
 
-/// Estabilishes connection to the PostgreSQL server and returns a client SeaORM interface
+/// Estabilishes connection to the PostgreSQL server or opens the SQLite file and returns a client SeaORM interface
+/// The schema is synchronized separately, see `healthcheck::sync_schema_until_applied`
 pub async fn get_database_connection() -> Result<DatabaseConnection> {
     let db: DatabaseConnection = Database::connect(configs::get_db_url()).await?;
-    register_cert_in_db_schema(
-        db.get_schema_builder()
-    ).sync(&db).await?;
 
     Ok(db)
 }
+
+/// Brings the tables and the indexes of the data base to the registered models
+pub async fn sync_database_schema(db: &DatabaseConnection) -> Result<()> {
+    register_models_in_db_schema(
+        db.get_schema_builder()
+    ).sync(db).await?;
+    drop_unique_email_constraint(db).await?;
+    create_extra_db_indexes(db).await?;
+
+    Ok(())
+}
@@ -1,23 +1,143 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering}
+    },
+    time::{Duration, Instant}
+};
 
-use actix_web::{HttpResponse, HttpResponseBuilder, Resource, http::StatusCode, web};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, dev::ServerHandle, http::StatusCode, web};
+use chrono::{DateTime, Utc};
 use fred::prelude::*;
+use log::{error, info};
 use sea_orm::DatabaseConnection;
+use serde::Serialize;
 
-use crate::utils::log_error::ResultLogger;
+use crate::{api_v1, configs, connections, utils::log_error::ResultLogger};
 
-/// Checks the database and redis connections
+/// How long to wait before the next attempt to synchronize the data base schema
+const SCHEMA_SYNC_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The state of the process that is shared between the health endpoints and the shutdown handler
+pub struct HealthState {
+    started_at: DateTime<Utc>,
+    started_instant: Instant,
+    migrations_applied: AtomicBool,
+    shutting_down: AtomicBool
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            started_instant: Instant::now(),
+            migrations_applied: AtomicBool::new(false),
+            shutting_down: AtomicBool::new(false)
+        }
+    }
+
+    /// Marks the data base schema as synchronized with the models
+    pub fn set_migrations_applied(&self) {
+        self.migrations_applied.store(true, Ordering::SeqCst);
+    }
+
+    /// Makes the readiness check fail, so no new traffic is routed to the process
+    pub fn set_shutting_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Serialize)]
+struct DependencyHealth {
+    healthy: bool,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>
+}
+
+#[derive(Serialize)]
+struct QueueDepth {
+    name: &'static str,
+    depth: u64
+}
+
+#[derive(Serialize)]
+struct BuildInfo {
+    version: &'static str,
+    commit: &'static str,
+    profile: &'static str
+}
+
+#[derive(Serialize)]
+struct HealthDetailsResponse {
+    ready: bool,
+    shutting_down: bool,
+    migrations_applied: bool,
+    started_at: u64,
+    uptime_seconds: u64,
+    build: BuildInfo,
+    database: DependencyHealth,
+    redis: DependencyHealth,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queues: Option<Vec<QueueDepth>>
+}
+
+/// Pings the data base and measures the round trip
+async fn check_database(db: &DatabaseConnection) -> DependencyHealth {
+    let start = Instant::now();
+    let result = db.ping().await.log_with_place_on_error("check_database");
+
+    DependencyHealth {
+        healthy: result.is_ok(),
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+        error: result.err().map(|e| e.to_string())
+    }
+}
+
+/// Pings the Redis server and measures the round trip
+async fn check_redis(redis: &Client) -> DependencyHealth {
+    let start = Instant::now();
+    let result = redis.ping::<String>(None).await.log_with_place_on_error("check_redis");
+
+    DependencyHealth {
+        healthy: result.is_ok(),
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+        error: result.err().map(|e| e.to_string())
+    }
+}
+
+/// Checks if the process is alive
+/// Doesn't touch any dependency, so a data base or Redis blip doesn't make Docker restart the process
+pub async fn livez_endpoint() -> HttpResponse {
+    HttpResponseBuilder::new(StatusCode::OK)
+        .body("Alive")
+}
+
+/// Checks if the process can serve traffic: dependencies are reachable, migrations are applied
+/// and the server isn't shutting down
 /// Returns simple text messages
-/// Useful for Docker healthcheck
-pub async fn healthcheck_endpoint(
+pub async fn readyz_endpoint(
+    state: web::Data<Arc<HealthState>>,
     db: web::Data<Arc<DatabaseConnection>>,
     redis: web::Data<Arc<Client>>
 ) -> HttpResponse {
-    if let Err(_) = db.ping().await.log_with_place_on_error("healthcheck_endpoint") {
-        HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR)
+    if state.shutting_down.load(Ordering::SeqCst) {
+        HttpResponseBuilder::new(StatusCode::SERVICE_UNAVAILABLE)
+            .body("Server is shutting down")
+    } else if !state.migrations_applied.load(Ordering::SeqCst) {
+        HttpResponseBuilder::new(StatusCode::SERVICE_UNAVAILABLE)
+            .body("Database migrations are not applied")
+    } else if db.ping().await.log_with_place_on_error("readyz_endpoint").is_err() {
+        HttpResponseBuilder::new(StatusCode::SERVICE_UNAVAILABLE)
             .body("Database connection is unhealthy")
-    } else if let Err(_) = redis.ping::<String>(None).await.log_with_place_on_error("healthcheck_endpoint") {
-        HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR)
+    } else if redis.ping::<String>(None).await.log_with_place_on_error("readyz_endpoint").is_err() {
+        HttpResponseBuilder::new(StatusCode::SERVICE_UNAVAILABLE)
             .body("Redis connection is unhealthy")
     } else {
         HttpResponseBuilder::new(StatusCode::OK)
@@ -25,15 +145,132 @@ pub async fn healthcheck_endpoint(
     }
 }
 
-/// Adds the /healthcheck endpoint that checks the database and redis connections
-pub fn healthcheck_resource(
+/// Returns the detailed health report for operators
+/// Requires the administrative token, because it reveals the build and the internal queues
+pub async fn health_details_endpoint(
+    request: HttpRequest,
+    state: web::Data<Arc<HealthState>>,
+    db: web::Data<Arc<DatabaseConnection>>,
+    redis: web::Data<Arc<Client>>
+) -> HttpResponse {
+    if !api_v1::is_admin_request(&request) {
+        return HttpResponseBuilder::new(StatusCode::UNAUTHORIZED)
+            .body("Missing or invalid authorization token");
+    }
+
+    let database = check_database(db.as_ref()).await;
+    let redis_health = check_redis(redis.as_ref()).await;
+
+    let queues = if redis_health.healthy {
+        api_v1::get_queue_depths(redis.get_ref().clone())
+            .await
+            .log_with_place_on_error("health_details_endpoint")
+            .ok()
+            .map(|depths| depths
+                .into_iter()
+                .map(|(name, depth)| QueueDepth { name, depth })
+                .collect()
+            )
+    } else {
+        None
+    };
+
+    let shutting_down = state.shutting_down.load(Ordering::SeqCst);
+    let migrations_applied = state.migrations_applied.load(Ordering::SeqCst);
+    let ready = !shutting_down && migrations_applied && database.healthy && redis_health.healthy;
+
+    let response = HealthDetailsResponse {
+        ready,
+        shutting_down,
+        migrations_applied,
+        started_at: state.started_at.timestamp() as u64,
+        uptime_seconds: state.started_instant.elapsed().as_secs(),
+        build: BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: option_env!("BUILD_COMMIT").unwrap_or("unknown"),
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" }
+        },
+        database,
+        redis: redis_health,
+        queues
+    };
+
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    HttpResponseBuilder::new(status)
+        .json(response)
+}
+
+/// Adds the health endpoints
+/// /livez checks the process only, /readyz checks the dependencies, /healthz/details is the authenticated report
+/// /healthcheck is kept as an alias of /readyz for the existing deployments
+pub fn health_config(
+    state: Arc<HealthState>,
     db: Arc<DatabaseConnection>,
     redis: Arc<Client>
-) -> Resource {
-    let healthcheck_resource = web::resource("/healthcheck")
-        .app_data(web::Data::new(db))
-        .app_data(web::Data::new(redis))
-        .route(web::get().to(healthcheck_endpoint));
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |config| {
+        config
+            .app_data(web::Data::new(state))
+            .app_data(web::Data::new(db))
+            .app_data(web::Data::new(redis))
+            .service(web::resource("/livez").route(web::get().to(livez_endpoint)))
+            .service(web::resource("/readyz").route(web::get().to(readyz_endpoint)))
+            .service(web::resource("/healthcheck").route(web::get().to(readyz_endpoint)))
+            .service(web::resource("/healthz/details").route(web::get().to(health_details_endpoint)));
+    }
+}
+
+/// Synchronizes the data base schema and marks the migrations applied only after it succeeds
+/// The failed attempts are retried, the readiness check fails until one of them succeeds
+pub async fn sync_schema_until_applied(state: Arc<HealthState>, database: Arc<DatabaseConnection>) {
+    loop {
+        match connections::sync_database_schema(&database).await {
+            Ok(()) => {
+                info!("The data base schema is synchronized");
+                state.set_migrations_applied();
+                return;
+            },
+            Err(e) => error!("Failed to synchronize the data base schema: {}", e)
+        }
+
+        actix_web::rt::time::sleep(SCHEMA_SYNC_RETRY_INTERVAL).await;
+    }
+}
+
+/// Waits for SIGTERM or SIGINT and stops the server gracefully
+/// Readiness fails first, so the load balancer stops routing new requests before the connections are drained
+pub async fn shutdown_on_signal(server: ServerHandle, state: Arc<HealthState>) {
+    wait_for_signal().await;
+
+    info!("Shutdown signal received, marking the server as unready");
+    state.set_shutting_down();
+
+    actix_web::rt::time::sleep(configs::get_shutdown_grace_period()).await;
+
+    info!("Draining connections");
+    server.stop(true).await;
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = sigterm.recv() => {},
+        _ = tokio::signal::ctrl_c() => {}
+    }
+}
 
-    healthcheck_resource
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
 }
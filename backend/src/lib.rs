@@ -0,0 +1,6 @@
+pub mod configs;
+pub mod connections;
+pub mod api_v1;
+pub mod utils;
+pub mod healthcheck;
+pub mod tls;
@@ -2,11 +2,25 @@ use std::sync::Arc;
 use actix_web::{App, HttpServer, middleware::Logger};
 use env_logger::Env;
 
-mod configs;
-mod connections;
-mod api_v1;
-mod utils;
-mod healthcheck;
+use backend::{
+    api_v1::{
+        self, 
+        repos::{
+            CertRepo, 
+            CertStore, 
+            KvStore, 
+            RedisRepo
+        }
+    }, 
+    configs, 
+    connections, 
+    healthcheck, 
+    tls, 
+    utils::clock::{
+        Clock, 
+        SystemClock
+    }
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -22,16 +36,58 @@ async fn main() -> std::io::Result<()> {
     let db_arc = Arc::new(db);
     let redis_arc = Arc::new(redis);
 
+    // The API works with the storages through the traits, so the tests can replace them
+    let cert_store: Arc<dyn CertStore> = Arc::new(CertRepo::new(db_arc.clone()));
+    let kv_store: Arc<dyn KvStore> = Arc::new(RedisRepo::new(redis_arc.clone()));
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let settings = Arc::new(configs::ApiSettings::from_env().unwrap());
+    let rate_limit_counters = api_v1::RateLimitCounters::default();
+
+    // The readiness check waits for the schema, the server answers the health checks meanwhile
+    let health_state = Arc::new(healthcheck::HealthState::new());
+    actix_web::rt::spawn(healthcheck::sync_schema_until_applied(health_state.clone(), db_arc.clone()));
+
+    // Start the background workers
+    api_v1::spawn_background_workers(db_arc.clone(), cert_store.clone(), kv_store.clone(), clock.clone());
+
+    // Obtain and renew the TLS certificate in the background if the built-in TLS is enabled
+    let tls_state = tls::init_tls_state();
+    if let Some(state) = &tls_state {
+        actix_web::rt::spawn(tls::acme::run_certificate_manager(state.clone(), configs::get_acme_challenge()));
+    }
+
     // Create and configurate Actix web server
-    HttpServer::new(move || {
+    let server_health_state = health_state.clone();
+    let server_tls_state = tls_state.clone();
+    let mut server = HttpServer::new(move || {
         let logger_middleware = Logger::default();
+        let tls_state = server_tls_state.clone();
 
         App::new()
             .wrap(logger_middleware)
-            .service(healthcheck::healthcheck_resource(db_arc.clone(), redis_arc.clone()))
-            .service(api_v1::api_v1_scope(db_arc.clone(), redis_arc.clone()))
+            .configure(healthcheck::health_config(server_health_state.clone(), db_arc.clone(), redis_arc.clone()))
+            .configure(move |config| {
+                if let Some(state) = tls_state {
+                    tls::acme_challenge_config(state)(config);
+                }
+            })
+            .service(api_v1::api_v1_scope(db_arc.clone(), cert_store.clone(), kv_store.clone(), clock.clone(), settings.clone(), rate_limit_counters.clone()))
+            .service(api_v1::api_v2_scope(db_arc.clone(), cert_store.clone(), kv_store.clone(), clock.clone(), settings.clone(), rate_limit_counters.clone()))
     })
-        .bind(("0.0.0.0", 8080))?
-        .run()
-        .await
+        .bind(("0.0.0.0", 8080))?;
+
+    // The plain HTTP port stays for the health checks and the HTTP-01 challenges
+    if let Some(state) = &tls_state {
+        server = server.bind_rustls_0_23(("0.0.0.0", configs::get_tls_port()), tls::server_config(state))?;
+    }
+
+    let server = server
+        .disable_signals()
+        .shutdown_timeout(30)
+        .run();
+
+    // Flip the readiness before draining the connections on shutdown
+    actix_web::rt::spawn(healthcheck::shutdown_on_signal(server.handle(), health_state));
+
+    server.await
 }
@@ -0,0 +1,389 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration
+};
+use anyhow::{Result, anyhow};
+use chrono::{TimeDelta, Utc};
+use instant_acme::{
+    Account, AuthorizationStatus, ChallengeType, Identifier, KeyAuthorization, NewAccount, NewOrder, OrderStatus
+};
+use log::{error, info};
+use rcgen::{CertificateParams, CustomExtension, DistinguishedName, KeyPair};
+use rustls::{
+    crypto::ring::sign::any_supported_type,
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+    sign::CertifiedKey
+};
+use crate::{
+    configs,
+    tls::{
+        resolver::CertResolver,
+        storage::{TlsStorage, parse_certificate}
+    }
+};
+
+/// The certificate is renewed when less than this amount of days is left
+const RENEW_BEFORE_DAYS: i64 = 30;
+
+/// How often the expiration is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// How long to wait after a failed order before the next attempt
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How many times the order state is polled before the order is given up
+const MAX_POLL_ATTEMPTS: u32 = 30;
+
+/// The way the ACME server checks that the domains belong to us
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcmeChallenge {
+    /// The token is served at http://{domain}/.well-known/acme-challenge/{token}
+    Http01,
+    /// A special certificate is shown to the connections with the "acme-tls/1" ALPN protocol on the TLS port
+    TlsAlpn01
+}
+
+impl AcmeChallenge {
+    fn challenge_type(&self) -> ChallengeType {
+        match self {
+            Self::Http01 => ChallengeType::Http01,
+            Self::TlsAlpn01 => ChallengeType::TlsAlpn01
+        }
+    }
+}
+
+/// Everything the certificate manager shares with the server
+pub struct TlsState {
+    pub resolver: Arc<CertResolver>,
+    /// The HTTP-01 key authorizations by the token
+    pub http_challenges: RwLock<HashMap<String, String>>,
+    storage: TlsStorage,
+    domains: Vec<String>
+}
+
+impl TlsState {
+    pub fn new(domains: Vec<String>, storage: TlsStorage) -> Self {
+        Self {
+            resolver: Arc::new(CertResolver::new()),
+            http_challenges: RwLock::new(HashMap::new()),
+            storage,
+            domains
+        }
+    }
+
+    pub fn get_http_challenge(&self, token: &str) -> Option<String> {
+        self.http_challenges.read().unwrap().get(token).cloned()
+    }
+
+    /// Loads the stored certificate, so the server can start serving before the manager checks it
+    pub fn load_stored_certificate(&self) {
+        match self.storage.load_certificate() {
+            Ok(Some(stored)) => {
+                info!("Loaded the stored TLS certificate, it expires at {}", stored.expires_at);
+                self.resolver.set_certificate(stored.certified_key);
+            },
+            Ok(None) => info!("There is no stored TLS certificate yet"),
+            Err(e) => error!("Failed to load the stored TLS certificate: {}", e)
+        }
+    }
+}
+
+/// Returns the ACME account, a new one is registered on the first start
+async fn get_account(state: &TlsState) -> Result<Account> {
+    if let Some(credentials) = state.storage.load_account()? {
+        return Ok(Account::from_credentials(credentials).await?);
+    }
+
+    let contact = configs::get_acme_contact_email()
+        .map(|email| vec![format!("mailto:{}", email)])
+        .unwrap_or_default();
+    let contact: Vec<&str> = contact.iter().map(String::as_str).collect();
+
+    let (account, credentials) = Account::create(
+        &NewAccount {
+            contact: &contact,
+            terms_of_service_agreed: true,
+            only_return_existing: false
+        },
+        &configs::get_acme_directory_url(),
+        None
+    ).await?;
+
+    state.storage.save_account(&credentials)?;
+    info!("Registered a new ACME account");
+
+    Ok(account)
+}
+
+/// Builds the self-signed certificate that proves the domain for TLS-ALPN-01
+fn tls_alpn_certificate(domain: &str, key_authorization_digest: &[u8]) -> Result<Arc<CertifiedKey>> {
+    let mut params = CertificateParams::new(vec![domain.to_string()])?;
+    params.custom_extensions = vec![CustomExtension::new_acme_identifier(key_authorization_digest)];
+
+    let key_pair = KeyPair::generate()?;
+    let cert = params.self_signed(&key_pair)?;
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_pair.serialize_der()));
+
+    Ok(Arc::new(CertifiedKey::new(vec![cert.der().clone()], any_supported_type(&key)?)))
+}
+
+/// The challenge proofs published for one order
+/// They are removed when the order stops waiting for them, also when it fails halfway
+struct PublishedChallenges<'a> {
+    state: &'a TlsState,
+    /// The domains with their HTTP-01 tokens
+    proofs: Vec<(String, String)>
+}
+
+impl<'a> PublishedChallenges<'a> {
+    fn new(state: &'a TlsState) -> Self {
+        Self { state, proofs: Vec::new() }
+    }
+
+    fn publish(&mut self, challenge: AcmeChallenge, domain: &str, token: &str, key_authorization: &KeyAuthorization) -> Result<()> {
+        match challenge {
+            AcmeChallenge::Http01 => {
+                self.state.http_challenges
+                    .write()
+                    .unwrap()
+                    .insert(token.to_string(), key_authorization.as_str().to_string());
+            },
+            AcmeChallenge::TlsAlpn01 => {
+                self.state.resolver.set_challenge(
+                    domain,
+                    tls_alpn_certificate(domain, key_authorization.digest().as_ref())?
+                );
+            }
+        }
+
+        self.proofs.push((domain.to_string(), token.to_string()));
+
+        Ok(())
+    }
+}
+
+impl Drop for PublishedChallenges<'_> {
+    fn drop(&mut self) {
+        for (domain, token) in &self.proofs {
+            self.state.http_challenges.write().unwrap().remove(token);
+            self.state.resolver.remove_challenge(domain);
+        }
+    }
+}
+
+/// Orders a certificate for all the domains, stores it and makes the server use it
+async fn order_certificate(state: &TlsState, challenge: AcmeChallenge) -> Result<()> {
+    let account = get_account(state).await?;
+
+    let identifiers: Vec<Identifier> = state.domains
+        .iter()
+        .map(|domain| Identifier::Dns(domain.clone()))
+        .collect();
+    let mut order = account.new_order(&NewOrder { identifiers: &identifiers }).await?;
+
+    // Publish the proofs of all the pending authorizations
+    let mut published = PublishedChallenges::new(state);
+    let mut challenge_urls = Vec::new();
+
+    for authorization in order.authorizations().await? {
+        match authorization.status {
+            AuthorizationStatus::Pending => {},
+            AuthorizationStatus::Valid => continue,
+            status => return Err(anyhow!("The authorization is {:?}", status))
+        }
+
+        let domain = match &authorization.identifier {
+            Identifier::Dns(domain) => domain,
+            #[allow(unreachable_patterns)]
+            identifier => return Err(anyhow!("Unsupported identifier {:?}", identifier))
+        };
+        let acme_challenge = authorization.challenges
+            .iter()
+            .find(|acme_challenge| acme_challenge.r#type == challenge.challenge_type())
+            .ok_or(anyhow!("The ACME server doesn't offer the {:?} challenge", challenge))?;
+        let key_authorization = order.key_authorization(acme_challenge);
+
+        published.publish(challenge, domain, &acme_challenge.token, &key_authorization)?;
+        challenge_urls.push(acme_challenge.url.clone());
+    }
+
+    for url in &challenge_urls {
+        order.set_challenge_ready(url).await?;
+    }
+
+    // Wait until the ACME server validates the proofs
+    let mut status = OrderStatus::Pending;
+    for attempt in 0..MAX_POLL_ATTEMPTS {
+        status = order.refresh().await?.status;
+
+        if status != OrderStatus::Pending {
+            break;
+        }
+
+        actix_web::rt::time::sleep(Duration::from_secs(2u64.pow(attempt.min(4)))).await;
+    }
+
+    // The proofs aren't needed after the validation
+    drop(published);
+
+    if status != OrderStatus::Ready {
+        return Err(anyhow!("The order is {:?} instead of ready", status));
+    }
+
+    // Send the certificate signing request with a new key
+    let mut params = CertificateParams::new(state.domains.clone())?;
+    params.distinguished_name = DistinguishedName::new();
+    let key_pair = KeyPair::generate()?;
+    let csr = params.serialize_request(&key_pair)?;
+
+    order.finalize(csr.der()).await?;
+
+    let mut cert_chain_pem = None;
+    for _ in 0..MAX_POLL_ATTEMPTS {
+        cert_chain_pem = order.certificate().await?;
+
+        if cert_chain_pem.is_some() {
+            break;
+        }
+
+        actix_web::rt::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let cert_chain_pem = cert_chain_pem.ok_or(anyhow!("The certificate wasn't issued in time"))?;
+    let key_pem = key_pair.serialize_pem();
+
+    let stored = parse_certificate(cert_chain_pem.as_bytes(), key_pem.as_bytes())?;
+    state.storage.save_certificate(&cert_chain_pem, &key_pem)?;
+    state.resolver.set_certificate(stored.certified_key);
+
+    info!("Obtained a TLS certificate for {}, it expires at {}", state.domains.join(", "), stored.expires_at);
+
+    Ok(())
+}
+
+/// Returns true if there is no valid certificate or it expires soon
+fn needs_renewal(state: &TlsState) -> bool {
+    match state.storage.load_certificate() {
+        Ok(Some(stored)) => stored.expires_at - Utc::now() < TimeDelta::days(RENEW_BEFORE_DAYS),
+        _ => true
+    }
+}
+
+/// Obtains the certificate on the first start and renews it in the background
+/// The new certificate is used by the next handshakes, the server isn't restarted
+pub async fn run_certificate_manager(state: Arc<TlsState>, challenge: AcmeChallenge) {
+    loop {
+        let interval = if needs_renewal(&state) {
+            info!("Ordering a TLS certificate for {} with {:?}", state.domains.join(", "), challenge);
+
+            match order_certificate(&state, challenge).await {
+                Ok(()) => CHECK_INTERVAL,
+                Err(e) => {
+                    error!("Failed to obtain a TLS certificate: {}", e);
+                    RETRY_INTERVAL
+                }
+            }
+        } else {
+            CHECK_INTERVAL
+        };
+
+        actix_web::rt::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, NaiveDate};
+    use x509_parser::{
+        oid_registry::Oid,
+        prelude::{FromDer, GeneralName, X509Certificate}
+    };
+    use crate::tls::storage::tests::{TempDir, self_signed_pem};
+    use super::*;
+
+    fn state(dir: &TempDir) -> TlsState {
+        TlsState::new(vec!["api.example.com".to_string()], TlsStorage::new(dir.0.clone()))
+    }
+
+    fn store_expiring_at(state: &TlsState, not_after: NaiveDate) {
+        let (cert_pem, key_pem) = self_signed_pem("api.example.com", not_after);
+        state.storage.save_certificate(&cert_pem, &key_pem).unwrap();
+    }
+
+    #[test]
+    fn renews_missing_and_expiring_certificates() {
+        let dir = TempDir::new();
+        let state = state(&dir);
+        let today = Utc::now().date_naive();
+
+        assert!(needs_renewal(&state));
+
+        store_expiring_at(&state, today + Days::new(RENEW_BEFORE_DAYS as u64 - 5));
+        assert!(needs_renewal(&state));
+
+        store_expiring_at(&state, today + Days::new(RENEW_BEFORE_DAYS as u64 + 5));
+        assert!(!needs_renewal(&state));
+
+        // The stored certificate is served right after the start
+        assert!(!state.resolver.has_certificate());
+        state.load_stored_certificate();
+        assert!(state.resolver.has_certificate());
+    }
+
+    #[test]
+    fn tls_alpn_certificate_proves_the_domain() {
+        let digest = [7u8; 32];
+        let certified_key = tls_alpn_certificate("api.example.com", &digest).unwrap();
+
+        let (_, parsed) = X509Certificate::from_der(certified_key.cert[0].as_ref()).unwrap();
+        let names: Vec<String> = parsed.subject_alternative_name().unwrap().unwrap().value.general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(name) => Some(name.to_string()),
+                _ => None
+            })
+            .collect();
+        assert_eq!(names, vec!["api.example.com".to_string()]);
+
+        // The id-pe-acmeIdentifier extension is critical and holds the DER octet string of the digest
+        let acme_identifier = Oid::from(&[1, 3, 6, 1, 5, 5, 7, 1, 31]).unwrap();
+        let extension = parsed.extensions()
+            .iter()
+            .find(|extension| extension.oid == acme_identifier)
+            .unwrap();
+        assert!(extension.critical);
+        assert_eq!(&extension.value[..2], &[0x04, 32]);
+        assert_eq!(&extension.value[2..], &digest);
+    }
+
+    #[test]
+    fn http_challenges_are_served_by_token() {
+        let dir = TempDir::new();
+        let state = state(&dir);
+
+        assert_eq!(state.get_http_challenge("token"), None);
+
+        state.http_challenges.write().unwrap().insert("token".to_string(), "token.thumbprint".to_string());
+        assert_eq!(state.get_http_challenge("token"), Some("token.thumbprint".to_string()));
+        assert_eq!(state.get_http_challenge("another"), None);
+    }
+
+    #[test]
+    fn published_challenges_are_removed_on_drop() {
+        let dir = TempDir::new();
+        let state = state(&dir);
+
+        // An order that fails after the publication leaves no proofs behind
+        {
+            let mut published = PublishedChallenges::new(&state);
+            state.http_challenges.write().unwrap().insert("token".to_string(), "token.thumbprint".to_string());
+            published.proofs.push(("api.example.com".to_string(), "token".to_string()));
+            state.resolver.set_challenge("api.example.com", tls_alpn_certificate("api.example.com", &[7u8; 32]).unwrap());
+            assert!(state.resolver.has_challenge("api.example.com"));
+        }
+
+        assert_eq!(state.get_http_challenge("token"), None);
+        assert!(!state.resolver.has_challenge("api.example.com"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+use actix_web::{HttpResponse, web};
+use rustls::ServerConfig;
+use crate::configs;
+
+pub mod acme;
+pub mod resolver;
+pub mod storage;
+
+use acme::TlsState;
+use resolver::ACME_TLS_ALPN_NAME;
+use storage::TlsStorage;
+
+/// Prepares the built-in TLS if the TLS_DOMAINS environment variable is set
+/// Returns None otherwise, then the server is expected to be behind a TLS terminating proxy
+pub fn init_tls_state() -> Option<Arc<TlsState>> {
+    let domains = configs::get_tls_domains();
+    if domains.is_empty() {
+        return None;
+    }
+
+    // Other dependencies may enable another crypto provider, so the choice is explicit
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let state = Arc::new(TlsState::new(domains, TlsStorage::new(configs::get_tls_storage_dir())));
+    state.load_stored_certificate();
+
+    Some(state)
+}
+
+/// Builds the rustls configuration that takes the certificates from the hot reloaded resolver
+/// Actix adds the "h2" and "http/1.1" ALPN protocols itself
+pub fn server_config(state: &TlsState) -> ServerConfig {
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_cert_resolver(state.resolver.clone());
+
+    config.alpn_protocols = vec![ACME_TLS_ALPN_NAME.to_vec()];
+
+    config
+}
+
+/// Returns the key authorization of the HTTP-01 challenge
+async fn http_challenge_endpoint(
+    token: web::Path<String>,
+    state: web::Data<Arc<TlsState>>
+) -> HttpResponse {
+    match state.get_http_challenge(&token) {
+        Some(key_authorization) => HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .body(key_authorization),
+        None => HttpResponse::NotFound().finish()
+    }
+}
+
+/// Adds the HTTP-01 challenge endpoint, the ACME server requests it over plain HTTP
+pub fn acme_challenge_config(state: Arc<TlsState>) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |config| {
+        config
+            .app_data(web::Data::new(state))
+            .service(
+                web::resource("/.well-known/acme-challenge/{token}")
+                    .route(web::get().to(http_challenge_endpoint))
+            );
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock}
+};
+use rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey
+};
+
+/// The ALPN protocol of the TLS-ALPN-01 validation connections
+pub const ACME_TLS_ALPN_NAME: &[u8] = b"acme-tls/1";
+
+/// Chooses the certificate for every TLS handshake
+/// The certificate is replaced without restarting the server, the new handshakes get the new one
+#[derive(Debug, Default)]
+pub struct CertResolver {
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+    /// The TLS-ALPN-01 validation certificates by the domain
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>
+}
+
+impl CertResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the certificate for the next handshakes
+    pub fn set_certificate(&self, certified_key: Arc<CertifiedKey>) {
+        *self.current.write().unwrap() = Some(certified_key);
+    }
+
+    pub fn has_certificate(&self) -> bool {
+        self.current.read().unwrap().is_some()
+    }
+
+    pub fn set_challenge(&self, domain: &str, certified_key: Arc<CertifiedKey>) {
+        self.challenges.write().unwrap().insert(domain.to_lowercase(), certified_key);
+    }
+
+    pub fn remove_challenge(&self, domain: &str) {
+        self.challenges.write().unwrap().remove(&domain.to_lowercase());
+    }
+
+    pub fn has_challenge(&self, domain: &str) -> bool {
+        self.challenges.read().unwrap().contains_key(&domain.to_lowercase())
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let is_acme_validation = client_hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|protocol| protocol == ACME_TLS_ALPN_NAME));
+
+        if is_acme_validation {
+            let domain = client_hello.server_name()?.to_lowercase();
+            return self.challenges.read().unwrap().get(&domain).cloned();
+        }
+
+        self.current.read().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rustls::{
+        ClientConfig, ClientConnection, Connection, RootCertStore, ServerConfig, ServerConnection,
+        crypto::ring::default_provider,
+        pki_types::{CertificateDer, ServerName}
+    };
+    use crate::tls::storage::{parse_certificate, tests::self_signed_pem};
+    use super::*;
+
+    fn certified_key(domain: &str) -> Arc<CertifiedKey> {
+        let (cert_pem, key_pem) = self_signed_pem(domain, NaiveDate::from_ymd_opt(2030, 3, 1).unwrap());
+
+        parse_certificate(cert_pem.as_bytes(), key_pem.as_bytes()).unwrap().certified_key
+    }
+
+    /// Moves the pending TLS records from one side to the other
+    fn transfer(from: &mut Connection, to: &mut Connection) {
+        let mut buffer = Vec::new();
+        while from.wants_write() {
+            from.write_tls(&mut buffer).unwrap();
+        }
+
+        let mut records = buffer.as_slice();
+        while !records.is_empty() {
+            to.read_tls(&mut records).unwrap();
+        }
+    }
+
+    /// Makes a handshake in memory and returns the certificate the server has chosen
+    fn handshake(resolver: Arc<CertResolver>, trusted: &CertifiedKey, domain: &str, alpn: &[u8]) -> Result<CertificateDer<'static>, rustls::Error> {
+        let provider = Arc::new(default_provider());
+
+        let mut server_config = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+        server_config.alpn_protocols = vec![ACME_TLS_ALPN_NAME.to_vec(), b"http/1.1".to_vec()];
+
+        let mut roots = RootCertStore::empty();
+        roots.add(trusted.cert[0].clone())?;
+        let mut client_config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client_config.alpn_protocols = vec![alpn.to_vec()];
+
+        let server_name = ServerName::try_from(domain.to_string()).unwrap();
+        let mut client = Connection::from(ClientConnection::new(Arc::new(client_config), server_name)?);
+        let mut server = Connection::from(ServerConnection::new(Arc::new(server_config))?);
+
+        while client.is_handshaking() || server.is_handshaking() {
+            transfer(&mut client, &mut server);
+            server.process_new_packets()?;
+            transfer(&mut server, &mut client);
+            client.process_new_packets()?;
+        }
+
+        Ok(client.peer_certificates().unwrap()[0].clone())
+    }
+
+    #[test]
+    fn serves_the_current_certificate() {
+        let resolver = Arc::new(CertResolver::new());
+        let first = certified_key("api.example.com");
+        let second = certified_key("api.example.com");
+
+        assert!(!resolver.has_certificate());
+        assert!(handshake(resolver.clone(), &first, "api.example.com", b"http/1.1").is_err());
+
+        resolver.set_certificate(first.clone());
+        assert!(resolver.has_certificate());
+        assert_eq!(handshake(resolver.clone(), &first, "api.example.com", b"http/1.1").unwrap(), first.cert[0]);
+
+        // The renewed certificate is used by the next handshakes
+        resolver.set_certificate(second.clone());
+        assert_eq!(handshake(resolver.clone(), &second, "api.example.com", b"http/1.1").unwrap(), second.cert[0]);
+    }
+
+    #[test]
+    fn serves_the_challenge_to_the_validation() {
+        let resolver = Arc::new(CertResolver::new());
+        let current = certified_key("api.example.com");
+        let challenge = certified_key("api.example.com");
+        resolver.set_certificate(current.clone());
+
+        // Without a challenge the validation connection gets nothing
+        assert!(handshake(resolver.clone(), &challenge, "api.example.com", ACME_TLS_ALPN_NAME).is_err());
+
+        resolver.set_challenge("API.example.com", challenge.clone());
+        assert_eq!(handshake(resolver.clone(), &challenge, "api.example.com", ACME_TLS_ALPN_NAME).unwrap(), challenge.cert[0]);
+        assert_eq!(handshake(resolver.clone(), &current, "api.example.com", b"http/1.1").unwrap(), current.cert[0]);
+
+        resolver.remove_challenge("api.example.com");
+        assert!(handshake(resolver.clone(), &challenge, "api.example.com", ACME_TLS_ALPN_NAME).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc
+};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use instant_acme::AccountCredentials;
+use rustls::{
+    crypto::ring::sign::any_supported_type,
+    pki_types::CertificateDer,
+    sign::CertifiedKey
+};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// A certificate chain with its private key as they are stored on disk
+pub struct StoredCertificate {
+    pub certified_key: Arc<CertifiedKey>,
+    pub expires_at: DateTime<Utc>
+}
+
+/// Keeps the ACME account and the certificate in a directory, so a restart doesn't order a new certificate
+/// The private keys are written with the owner-only permissions
+pub struct TlsStorage {
+    dir: PathBuf
+}
+
+impl TlsStorage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn account_path(&self) -> PathBuf {
+        self.dir.join("account.json")
+    }
+
+    /// The chain and the private key are in one file, so one rename replaces both of them
+    fn certificate_path(&self) -> PathBuf {
+        self.dir.join("certificate.pem")
+    }
+
+    /// Writes the file atomically, the server never reads a half-written certificate
+    fn write_private(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))?;
+        }
+
+        fs::rename(tmp_path, path)?;
+
+        Ok(())
+    }
+
+    pub fn load_account(&self) -> Result<Option<AccountCredentials>> {
+        match fs::read_to_string(self.account_path()) {
+            Ok(raw) => Ok(Some(serde_json::from_str(&raw)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    pub fn save_account(&self, credentials: &AccountCredentials) -> Result<()> {
+        self.write_private(&self.account_path(), serde_json::to_string(credentials)?.as_bytes())
+    }
+
+    /// Returns the stored certificate, or None if nothing was stored yet
+    /// A chain that doesn't match its private key is an error, then the manager orders a new certificate
+    pub fn load_certificate(&self) -> Result<Option<StoredCertificate>> {
+        match fs::read(self.certificate_path()) {
+            Ok(bundle_pem) => parse_certificate(&bundle_pem, &bundle_pem).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into())
+        }
+    }
+
+    /// Replaces the stored certificate, a crash in the middle leaves the previous one
+    pub fn save_certificate(&self, cert_pem: &str, key_pem: &str) -> Result<()> {
+        let bundle_pem = format!("{}\n{}", cert_pem.trim_end(), key_pem);
+        self.write_private(&self.certificate_path(), bundle_pem.as_bytes())
+    }
+}
+
+/// Parses the PEM chain and the private key into the form rustls signs the handshakes with
+pub fn parse_certificate(cert_pem: &[u8], key_pem: &[u8]) -> Result<StoredCertificate> {
+    let chain: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut BufReader::new(cert_pem))
+        .collect::<Result<_, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(key_pem))?
+        .ok_or(anyhow!("The private key is missing"))?;
+
+    let leaf = chain.first().ok_or(anyhow!("The certificate chain is empty"))?;
+    let (_, parsed) = X509Certificate::from_der(leaf.as_ref())
+        .map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+    let expires_at = DateTime::from_timestamp(parsed.validity().not_after.timestamp(), 0)
+        .ok_or(anyhow!("Invalid certificate expiration"))?;
+
+    let signing_key = any_supported_type(&key)?;
+    let certified_key = CertifiedKey::new(chain, signing_key);
+
+    // The handshakes would fail with a key of another certificate
+    certified_key.keys_match()
+        .map_err(|e| anyhow!("The private key doesn't match the certificate: {}", e))?;
+
+    Ok(StoredCertificate {
+        certified_key: Arc::new(certified_key),
+        expires_at
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use chrono::{Datelike, NaiveDate};
+    use rcgen::{CertificateParams, KeyPair, date_time_ymd};
+    use super::*;
+
+    /// Returns the PEM of a self-signed certificate for the domain and its private key
+    pub(crate) fn self_signed_pem(domain: &str, not_after: NaiveDate) -> (String, String) {
+        let mut params = CertificateParams::new(vec![domain.to_string()]).unwrap();
+        params.not_after = date_time_ymd(not_after.year(), not_after.month() as u8, not_after.day() as u8);
+
+        let key_pair = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key_pair).unwrap();
+
+        (cert.pem(), key_pair.serialize_pem())
+    }
+
+    /// Returns an empty directory that is removed with the storage
+    pub(crate) struct TempDir(pub PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("pupsiks-tls-{}", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn expiration(date: NaiveDate) -> DateTime<Utc> {
+        date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+    }
+
+    #[test]
+    fn certificate_round_trip() {
+        let dir = TempDir::new();
+        let storage = TlsStorage::new(dir.0.clone());
+        let not_after = NaiveDate::from_ymd_opt(2031, 5, 17).unwrap();
+        let (cert_pem, key_pem) = self_signed_pem("api.example.com", not_after);
+
+        assert!(storage.load_certificate().unwrap().is_none());
+
+        storage.save_certificate(&cert_pem, &key_pem).unwrap();
+        let stored = storage.load_certificate().unwrap().unwrap();
+        assert_eq!(stored.expires_at, expiration(not_after));
+        assert_eq!(stored.certified_key.cert.len(), 1);
+
+        // The renewal replaces the whole pair and leaves no temporary files
+        let renewed_after = NaiveDate::from_ymd_opt(2032, 1, 2).unwrap();
+        let (cert_pem, key_pem) = self_signed_pem("api.example.com", renewed_after);
+        storage.save_certificate(&cert_pem, &key_pem).unwrap();
+        assert_eq!(storage.load_certificate().unwrap().unwrap().expires_at, expiration(renewed_after));
+
+        let files: Vec<String> = fs::read_dir(&dir.0).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, vec!["certificate.pem".to_string()]);
+    }
+
+    #[test]
+    fn mismatched_key_is_rejected() {
+        let not_after = NaiveDate::from_ymd_opt(2030, 3, 1).unwrap();
+        let (cert_pem, _) = self_signed_pem("api.example.com", not_after);
+        let (_, other_key_pem) = self_signed_pem("api.example.com", not_after);
+
+        assert!(parse_certificate(cert_pem.as_bytes(), other_key_pem.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn invalid_pem_is_rejected() {
+        let (cert_pem, key_pem) = self_signed_pem("api.example.com", NaiveDate::from_ymd_opt(2030, 3, 1).unwrap());
+
+        assert!(parse_certificate(b"", key_pem.as_bytes()).is_err());
+        assert!(parse_certificate(cert_pem.as_bytes(), b"").is_err());
+        assert!(parse_certificate(b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n", key_pem.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc};
+
+/// How far the next run is looked for, a year covers every valid expression
+const MAX_LOOKAHEAD_DAYS: i64 = 366;
+
+/// A parsed five-field cron expression: minute, hour, day of month, month and day of week
+/// Supports "*", numbers, ranges ("1-5"), steps ("*/15", "0-30/10") and lists ("1,15"), times are in UTC
+#[derive(Clone, Debug, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // Cron matches any of the restricted day fields, not both
+    any_day_of_month: bool,
+    any_day_of_week: bool
+}
+
+/// Parses one field of the expression into the bit mask of the allowed values
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| anyhow!("invalid step \"{}\"", step))?),
+            None => (part, 1)
+        };
+
+        if step == 0 {
+            return Err(anyhow!("step can't be zero"));
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                start.parse::<u32>().map_err(|_| anyhow!("invalid value \"{}\"", start))?,
+                end.parse::<u32>().map_err(|_| anyhow!("invalid value \"{}\"", end))?
+            )
+        } else {
+            let value = range.parse::<u32>().map_err(|_| anyhow!("invalid value \"{}\"", range))?;
+
+            // "5/10" means from 5 to the end with the step
+            if part.contains('/') { (value, max) } else { (value, value) }
+        };
+
+        if start < min || end > max || start > end {
+            return Err(anyhow!("\"{}\" is out of the {}-{} range", part, min, max));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields.as_slice() else {
+            return Err(anyhow!("expected 5 fields in \"{}\"", expression));
+        };
+
+        let mut days_of_week_mask = parse_field(days_of_week, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if days_of_week_mask & (1 << 7) != 0 {
+            days_of_week_mask |= 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days_of_month: parse_field(days_of_month, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            days_of_week: days_of_week_mask,
+            any_day_of_month: days_of_month.starts_with('*'),
+            any_day_of_week: days_of_week.starts_with('*')
+        })
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month & (1 << time.day()) != 0;
+        let day_of_week = self.days_of_week & (1 << time.weekday().num_days_from_sunday()) != 0;
+
+        let day = match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week
+        };
+
+        day && self.months & (1 << time.month()) != 0
+    }
+
+    /// Returns the first time after the specified one that matches the schedule
+    /// Returns None if nothing matches within a year, e.g. for "0 0 31 2 *"
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        let limit = after + Duration::days(MAX_LOOKAHEAD_DAYS);
+
+        while time <= limit {
+            if !self.matches_day(&time) {
+                time = time.duration_trunc(Duration::days(1)).ok()? + Duration::days(1);
+                continue;
+            }
+
+            if self.hours & (1 << time.hour()) == 0 {
+                time = time.duration_trunc(Duration::hours(1)).ok()? + Duration::hours(1);
+                continue;
+            }
+
+            if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+                continue;
+            }
+
+            return Some(time);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn mask(values: &[u32]) -> u64 {
+        values.iter().fold(0, |mask, value| mask | 1 << value)
+    }
+
+    fn next(expression: &str, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        CronSchedule::parse(expression).unwrap().next_after(after)
+    }
+
+    #[test]
+    fn fields_are_parsed_into_masks() {
+        assert_eq!(parse_field("*", 1, 12).unwrap(), mask(&(1..=12).collect::<Vec<u32>>()));
+        assert_eq!(parse_field("7", 0, 59).unwrap(), mask(&[7]));
+        assert_eq!(parse_field("1-5", 0, 6).unwrap(), mask(&[1, 2, 3, 4, 5]));
+        assert_eq!(parse_field("*/15", 0, 59).unwrap(), mask(&[0, 15, 30, 45]));
+        assert_eq!(parse_field("0-30/10", 0, 59).unwrap(), mask(&[0, 10, 20, 30]));
+        assert_eq!(parse_field("5/20", 0, 59).unwrap(), mask(&[5, 25, 45]));
+        assert_eq!(parse_field("1,15,20-22", 1, 31).unwrap(), mask(&[1, 15, 20, 21, 22]));
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for expression in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+            "1- * * * *",
+            "*/x * * * *",
+            "1,,2 * * * *"
+        ] {
+            assert!(CronSchedule::parse(expression).is_err(), "\"{}\" was accepted", expression);
+        }
+    }
+
+    #[test]
+    fn sunday_is_both_zero_and_seven() {
+        // 2025-06-01 is Sunday
+        assert_eq!(next("0 0 * * 0", utc(2025, 5, 28, 0, 0)), Some(utc(2025, 6, 1, 0, 0)));
+        assert_eq!(next("0 0 * * 7", utc(2025, 5, 28, 0, 0)), Some(utc(2025, 6, 1, 0, 0)));
+    }
+
+    #[test]
+    fn scheduler_jobs_run_at_their_times() {
+        // Every 10 minutes, the time itself isn't the next run
+        assert_eq!(next("*/10 * * * *", utc(2025, 6, 1, 12, 3) + Duration::seconds(30)), Some(utc(2025, 6, 1, 12, 10)));
+        assert_eq!(next("*/10 * * * *", utc(2025, 6, 1, 12, 10)), Some(utc(2025, 6, 1, 12, 20)));
+        assert_eq!(next("*/10 * * * *", utc(2025, 6, 1, 23, 55)), Some(utc(2025, 6, 2, 0, 0)));
+
+        // Daily at 03:30 over the month and the year ends
+        assert_eq!(next("30 3 * * *", utc(2025, 1, 31, 4, 0)), Some(utc(2025, 2, 1, 3, 30)));
+        assert_eq!(next("30 3 * * *", utc(2025, 12, 31, 3, 30)), Some(utc(2026, 1, 1, 3, 30)));
+
+        // Daily at 09:00
+        assert_eq!(next("0 9 * * *", utc(2025, 6, 1, 8, 59)), Some(utc(2025, 6, 1, 9, 0)));
+        assert_eq!(next("0 9 * * *", utc(2025, 6, 1, 9, 0)), Some(utc(2025, 6, 2, 9, 0)));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either_day() {
+        // 2025-06-01 is Sunday, the first Friday is the 6th
+        let after = utc(2025, 6, 1, 0, 0);
+
+        assert_eq!(next("0 0 13 * *", after), Some(utc(2025, 6, 13, 0, 0)));
+        assert_eq!(next("0 0 * * 5", after), Some(utc(2025, 6, 6, 0, 0)));
+        assert_eq!(next("0 0 13 * 5", after), Some(utc(2025, 6, 6, 0, 0)));
+        assert_eq!(next("0 0 13 * 5", utc(2025, 6, 12, 0, 0)), Some(utc(2025, 6, 13, 0, 0)));
+
+        // A star with a step still leaves the day of week to decide alone
+        assert_eq!(next("0 0 */1 * 5", after), Some(utc(2025, 6, 6, 0, 0)));
+    }
+
+    #[test]
+    fn rare_and_impossible_days() {
+        assert_eq!(next("0 0 29 2 *", utc(2027, 3, 1, 0, 0)), Some(utc(2028, 2, 29, 0, 0)));
+        assert_eq!(next("0 0 31 4 *", utc(2025, 1, 1, 0, 0)), None);
+        assert_eq!(next("0 0 31 2 *", utc(2025, 1, 1, 0, 0)), None);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod log_error;
 pub mod uuid;
 pub mod smart_trim;
+pub mod cron;
+pub mod clock;
@@ -0,0 +1,1675 @@
+//! Runs the whole API against the in-memory storages, no docker is needed
+//! The time is moved by the manual clock, so the expirations are checked without waiting
+
+use std::{
+    collections::BTreeSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering}
+    }
+};
+use actix_web::{App, http::{StatusCode, header}, test};
+use chrono::{Duration, TimeZone, Utc};
+use sea_orm::{ConnectOptions, Database, DatabaseBackend, DatabaseConnection, MockDatabase};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use backend::{
+    api_v1::{
+        RateLimitCounters,
+        api_v1_scope,
+        api_v2_scope,
+        repos::{
+            CertModel,
+            CertStore,
+            ErasureRepo,
+            KvStore,
+            MemoryCertStore,
+            MemoryKvStore,
+            WebhookModel,
+            WebhookRepo
+        },
+        services::{
+            cert_cache,
+            challenge,
+            email::EMAIL_JOBS_KEY,
+            expiry,
+            kinds,
+            locale::{
+                self,
+                Language
+            },
+            personal_data,
+            webhooks::{
+                self,
+                WEBHOOK_JOBS_KEY,
+                WebhookEvent
+            }
+        },
+        types::redis::EmailTask
+    },
+    configs::ApiSettings,
+    connections::sync_database_schema,
+    utils::{
+        clock::{
+            Clock,
+            ManualClock
+        },
+        uuid::get_uuid
+    }
+};
+
+/// Every request comes from its own address, so the per-second limit of the API isn't hit
+static NEXT_PEER: AtomicU32 = AtomicU32::new(1);
+
+fn next_peer() -> SocketAddr {
+    let index = NEXT_PEER.fetch_add(1, Ordering::Relaxed);
+
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::from(0x0A00_0000 + index)), 40000)
+}
+
+struct TestEnv {
+    database: Arc<DatabaseConnection>,
+    cert_store: Arc<MemoryCertStore>,
+    kv_store: Arc<MemoryKvStore>,
+    clock: Arc<ManualClock>,
+    /// The tests change their own settings before the app is built, the process environment isn't touched
+    settings: ApiSettings,
+    rate_limit_counters: RateLimitCounters
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+
+        Self {
+            // The webhooks and the erasure receipts still go to the data base, the mock has no rows
+            database: Arc::new(MockDatabase::new(DatabaseBackend::Postgres).into_connection()),
+            cert_store: Arc::new(MemoryCertStore::new(clock.clone())),
+            kv_store: Arc::new(MemoryKvStore::new(clock.clone())),
+            clock,
+            settings: ApiSettings {
+                session_secret: None,
+                cors_allowed_origins: Vec::new(),
+                cert_kinds: kinds::load_catalogue(None).unwrap(),
+                certs_per_email_limit: 5,
+                cert_validity: None,
+                ip_allowlist: Vec::new(),
+                subnet_limit_factor: 4,
+                ipv4_limit_prefixes: vec![32, 24],
+                ipv6_limit_prefixes: vec![64, 48]
+            },
+            rate_limit_counters: RateLimitCounters::default()
+        }
+    }
+
+    /// Returns the code from the latest letter queued for the email address
+    async fn last_code(&self, email: &str) -> String {
+        let kv_store: &dyn KvStore = self.kv_store.as_ref();
+
+        kv_store.list_all(EMAIL_JOBS_KEY.to_string())
+            .await
+            .unwrap()
+            .iter()
+            .filter_map(|raw| serde_json::from_str::<EmailTask>(raw).ok())
+            .find(|task| task.email == email)
+            .and_then(|task| task.replacements.get("CERTCODE").cloned())
+            .expect("no code was sent")
+    }
+
+    /// Issues a proof-of-work challenge and solves it like the clients do
+    async fn solved_challenge(&self) -> (String, String) {
+        let kv_store: &dyn KvStore = self.kv_store.as_ref();
+        let issued = challenge::issue_challenge(kv_store, self.clock.now()).await;
+
+        let solution = (0u64..)
+            .map(|counter| counter.to_string())
+            .find(|solution| {
+                let hash = Sha256::digest(format!("{}:{}", issued.challenge, solution).as_bytes());
+                let head = u128::from_be_bytes(hash[..16].try_into().unwrap());
+
+                head.leading_zeros() >= issued.difficulty
+            })
+            .unwrap();
+
+        (issued.challenge, solution)
+    }
+
+    /// Builds POST /api/v1/send_code with a solved challenge in the body
+    async fn send_code(&self, mut body: Value) -> test::TestRequest {
+        let (challenge, solution) = self.solved_challenge().await;
+        body["challenge"] = json!(challenge);
+        body["solution"] = json!(solution);
+
+        test::TestRequest::post()
+            .uri("/api/v1/send_code")
+            .peer_addr(next_peer())
+            .set_json(body)
+    }
+}
+
+macro_rules! init_app {
+    ($env:expr) => {
+        test::init_service(
+            App::new().service(api_v1_scope(
+                $env.database.clone(),
+                $env.cert_store.clone(),
+                $env.kv_store.clone(),
+                $env.clock.clone(),
+                Arc::new($env.settings.clone()),
+                $env.rate_limit_counters.clone()
+            ))
+        ).await
+    };
+}
+
+/// Returns another valid code, so it never matches the sent one
+fn wrong_code(code: &str) -> String {
+    if code == "ZZZ999ZZZ" { "AAA000AAA".to_string() } else { "ZZZ999ZZZ".to_string() }
+}
+
+#[actix_web::test]
+async fn create_get_and_delete_cert() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let email = "owner@example.com";
+
+    // Request the creation code
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(response).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    // Create the certificate with the code from the letter
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "name": "Test Name",
+            "title": "Test Title",
+            "code": env.last_code(email).await,
+            "token": token
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(response).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // Receive the certificate
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let etag = response.headers().get(header::ETAG).unwrap().clone();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Test Name");
+    assert_eq!(body["title"], "Test Title");
+
+    // The same version isn't sent again
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", id))
+        .peer_addr(next_peer())
+        .insert_header((header::IF_NONE_MATCH, etag))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // Request the deletion code
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "delete", "id": id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(response).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    // Delete the certificate
+    let request = test::TestRequest::delete()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "code": env.last_code(email).await,
+            "token": token
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The certificate is gone, the cached copy too
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn cert_reads_are_cached() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let email = "cached@example.com";
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "name": "Cached Child",
+            "title": "Test Title",
+            "code": env.last_code(email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let cert_id = body["id"].as_str().unwrap().to_string();
+
+    // The proxy keeps the response no longer than the browsers
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(header::CACHE_CONTROL).unwrap(), "public, max-age=60");
+    let last_modified = response.headers().get(header::LAST_MODIFIED).unwrap().clone();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .insert_header((header::IF_MODIFIED_SINCE, last_modified))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // The cached copy is served until it is invalidated
+    let uuid = get_uuid(&cert_id).unwrap();
+    let cert_store: &dyn CertStore = env.cert_store.as_ref();
+    assert_eq!(cert_store.remove_cert_by_id_and_email(uuid, email.to_string()).await.unwrap(), 1);
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Cached Child");
+
+    let kv_store: &dyn KvStore = env.kv_store.as_ref();
+    cert_cache::invalidate_cert(kv_store, &uuid).await.unwrap();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn idempotent_retries() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let email = "retry@example.com";
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    let body: Value = test::read_body_json(response).await;
+    let token = body["token"].as_str().unwrap().to_string();
+    let code = env.last_code(email).await;
+
+    let create_cert = |name: &str| test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .insert_header(("Idempotency-Key", "create-retry-1"))
+        .set_json(json!({
+            "email": email,
+            "name": name,
+            "title": "Test Title",
+            "code": code,
+            "token": token
+        }))
+        .to_request();
+
+    let response = test::call_service(&app, create_cert("Test Name")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("idempotent-replayed"));
+    let first: Value = test::read_body_json(response).await;
+
+    // The code is already consumed, but the retry gets the same certificate
+    let response = test::call_service(&app, create_cert("Test Name")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("idempotent-replayed").unwrap(), "true");
+    let replayed: Value = test::read_body_json(response).await;
+    assert_eq!(replayed, first);
+
+    // The same key with another body
+    let response = test::call_service(&app, create_cert("Other Name")).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "idempotency_key_reused");
+
+    // The errors aren't stored, so a failed request can be sent again with its key
+    let request = test::TestRequest::delete()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .insert_header(("Idempotency-Key", "delete-retry-1"))
+        .set_json(json!({ "email": email, "code": "000000", "token": "unknown" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert!(response.status().is_client_error());
+
+    let request = test::TestRequest::delete()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .insert_header(("Idempotency-Key", "delete-retry-1"))
+        .set_json(json!({ "email": email, "code": "111111", "token": "unknown" }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_ne!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The stored response expires after a day
+    env.clock.advance(Duration::hours(25));
+
+    let response = test::call_service(&app, create_cert("Test Name")).await;
+    assert!(!response.headers().contains_key("idempotent-replayed"));
+}
+
+#[actix_web::test]
+async fn multiple_certs_per_email() {
+    // The quota is lower than the default one to reach it quickly
+    let mut env = TestEnv::new();
+    env.settings.certs_per_email_limit = 2;
+    let app = init_app!(env);
+    let email = "family@example.com";
+
+    let create_cert = async |name: &str| {
+        let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(response).await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/cert")
+            .peer_addr(next_peer())
+            .set_json(json!({
+                "email": email,
+                "name": name,
+                "title": "Test Title",
+                "code": env.last_code(email).await,
+                "token": body["token"]
+            }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: Value = test::read_body_json(response).await;
+        body["id"].as_str().unwrap().to_string()
+    };
+
+    let get_cert_status = async |id: &str| {
+        let request = test::TestRequest::get()
+            .uri(&format!("/api/v1/cert/{}", id))
+            .peer_addr(next_peer())
+            .to_request();
+
+        test::call_service(&app, request).await.status()
+    };
+
+    // One email address has several certificates up to the quota
+    let first_id = create_cert("First Child").await;
+    let second_id = create_cert("Second Child").await;
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "cert_quota_exceeded");
+    assert_eq!(body["limit"], 2);
+
+    // The reminder lists all certificates of the email address
+    let (challenge, solution) = env.solved_challenge().await;
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/forgot")
+        .peer_addr(next_peer())
+        .set_json(json!({ "email": email, "challenge": challenge, "solution": solution }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let kv_store: &dyn KvStore = env.kv_store.as_ref();
+    let cert_list = kv_store.list_all(EMAIL_JOBS_KEY.to_string())
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|raw| serde_json::from_str::<EmailTask>(raw).ok())
+        .find(|task| task.email == email && task.purpose == "forgot")
+        .and_then(|task| task.replacements.get("CERTLIST").cloned())
+        .unwrap();
+    assert!(cert_list.contains(&first_id));
+    assert!(cert_list.contains(&second_id));
+    assert!(cert_list.contains("Second Child"));
+
+    // The deletion code removes only the certificate it was requested for
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "delete", "id": second_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let code = kv_store.list_all(EMAIL_JOBS_KEY.to_string())
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|raw| serde_json::from_str::<EmailTask>(raw).ok())
+        .find(|task| task.email == email && task.purpose == "delete")
+        .and_then(|task| task.replacements.get("CERTCODE").cloned())
+        .unwrap();
+
+    let request = test::TestRequest::delete()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({ "email": email, "code": code, "token": body["token"] }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(get_cert_status(&first_id).await, StatusCode::OK);
+    assert_eq!(get_cert_status(&second_id).await, StatusCode::NOT_FOUND);
+
+    // The freed place can be taken again
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn cert_kinds_catalogue() {
+    // The catalogue with a capped kind, a future one and an ended one
+    let mut env = TestEnv::new();
+    let now = env.clock.now();
+    let catalogue = json!([
+        { "id": "classic", "names": { "uk": "Класичний пупсик", "en": "Classic pupsik" } },
+        { "id": "volunteer", "names": { "en": "Volunteer" }, "cap": 1, "default_titles": ["Helps every day"] },
+        { "id": "future", "names": { "en": "Future" }, "available_from": now + Duration::days(30) },
+        { "id": "ended", "names": { "en": "Ended" }, "available_until": now - Duration::days(1) }
+    ]);
+    env.settings.cert_kinds = serde_json::from_value(catalogue).unwrap();
+    let app = init_app!(env);
+
+    let list_kinds = || test::TestRequest::get()
+        .uri("/api/v1/kinds")
+        .peer_addr(next_peer())
+        .insert_header((header::ACCEPT_LANGUAGE, "en"))
+        .to_request();
+
+    // Only the kinds inside their windows are listed
+    let response = test::call_service(&app, list_kinds()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let ids: Vec<&str> = body["kinds"].as_array().unwrap().iter().map(|kind| kind["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["classic", "volunteer"]);
+    assert_eq!(body["kinds"][0]["name"], "Classic pupsik");
+    assert_eq!(body["kinds"][1]["default_titles"][0], "Helps every day");
+    assert_eq!(body["kinds"][1]["remaining"], 1);
+
+    let create_cert = async |email: &str, kind: &str| {
+        let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+        let response = test::call_service(&app, request).await;
+        let body: Value = test::read_body_json(response).await;
+
+        let request = test::TestRequest::post()
+            .uri("/api/v1/cert")
+            .peer_addr(next_peer())
+            .set_json(json!({
+                "email": email,
+                "name": "Test Name",
+                "title": "Test Title",
+                "kind": kind,
+                "code": env.last_code(email).await,
+                "token": body["token"]
+            }))
+            .to_request();
+
+        test::call_service(&app, request).await
+    };
+
+    // The kinds outside their windows can't be chosen
+    let response = create_cert("future@example.com", "future").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = create_cert("ended@example.com", "ended").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // The capped kind is issued until the cap is reached
+    let response = create_cert("volunteer-1@example.com", "volunteer").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["kind"], "volunteer");
+
+    let response = create_cert("volunteer-2@example.com", "volunteer").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "kind_unavailable");
+
+    let response = test::call_service(&app, list_kinds()).await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["kinds"][1]["remaining"], 0);
+
+    // The classic kind is the default one
+    let response = create_cert("classic@example.com", "classic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["kind"], "classic");
+}
+
+#[actix_web::test]
+async fn transfer_cert_to_another_email() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let old_email = "giver@example.com";
+    let new_email = "receiver@example.com";
+
+    let request = env.send_code(json!({ "email": old_email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": old_email,
+            "name": "Moving Child",
+            "title": "Test Title",
+            "code": env.last_code(old_email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let cert_id = body["id"].as_str().unwrap().to_string();
+
+    // The current holder confirms the transfer with their code
+    let request = env.send_code(json!({ "email": old_email, "purpose": { "type": "transfer", "id": cert_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    // The certificate can't be moved to the same email address
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/transfer")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": old_email,
+            "new_email": old_email,
+            "code": env.last_code(old_email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let transfer_code = env.last_code(old_email).await;
+    let transfer_token = body["token"].clone();
+    let transfer_request = |new_email: &str| test::TestRequest::post()
+        .uri("/api/v1/cert/transfer")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": old_email,
+            "new_email": new_email,
+            "code": transfer_code,
+            "token": transfer_token
+        }))
+        .to_request();
+
+    // The rejected transfers leave the code of the holder usable
+    let full_email = "full@example.com";
+    for index in 0..env.settings.certs_per_email_limit {
+        env.cert_store.create_cert(CertModel {
+            id: Uuid::new_v4(),
+            email: full_email.to_string(),
+            name: format!("Full Child {}", index),
+            title: "Test Title".to_string(),
+            kind: "classic".to_string(),
+            created_at: env.clock.now(),
+            is_public: false,
+            updated_at: None,
+            expires_at: None
+        }, u64::MAX, None).await.ok().unwrap();
+    }
+
+    let response = test::call_service(&app, transfer_request(full_email)).await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "cert_quota_exceeded");
+
+    // The new holder has just received their own code, so the transfer letter waits for the rate limit
+    let request = env.send_code(json!({ "email": new_email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let create_token = body["token"].clone();
+    let create_code = env.last_code(new_email).await;
+
+    let response = test::call_service(&app, transfer_request(new_email)).await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "email_rate_limit");
+
+    env.clock.advance(Duration::minutes(4));
+    let response = test::call_service(&app, transfer_request(new_email)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["email"], new_email);
+
+    // The new holder confirms the transfer with their own code
+    let confirm_body = json!({
+        "email": new_email,
+        "code": env.last_code(new_email).await,
+        "token": body["token"]
+    });
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/transfer/confirm")
+        .peer_addr(next_peer())
+        .set_json(&confirm_body)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["id"], cert_id.as_str());
+    assert_eq!(body["name"], "Moving Child");
+
+    // The serial number stays, only the new holder can manage the certificate
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = env.send_code(json!({ "email": old_email, "purpose": { "type": "delete", "id": cert_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "invalid_email");
+
+    // Both holders are notified
+    let kv_store: &dyn KvStore = env.kv_store.as_ref();
+    let tasks = kv_store.list_all(EMAIL_JOBS_KEY.to_string())
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|raw| serde_json::from_str::<EmailTask>(raw).ok())
+        .collect::<Vec<_>>();
+
+    let transfer_out = tasks.iter()
+        .find(|task| task.email == old_email && task.purpose == "transfer_out")
+        .unwrap();
+    assert_eq!(transfer_out.replacements["CERTID"], cert_id);
+    assert_eq!(transfer_out.replacements["EMAIL"], new_email);
+
+    let transfer_in = tasks.iter()
+        .find(|task| task.email == new_email && task.purpose == "transfer_in")
+        .unwrap();
+    assert_eq!(transfer_in.replacements["CERTID"], cert_id);
+
+    // The transfer code didn't replace the code the new holder requested for themselves
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": new_email,
+            "name": "Own Child",
+            "title": "Test Title",
+            "code": create_code,
+            "token": create_token
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The code of the new holder is single-use
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/transfer/confirm")
+        .peer_addr(next_peer())
+        .set_json(&confirm_body)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn cert_expiry_and_renewal() {
+    // The certificates expire in 30 days
+    let mut env = TestEnv::new();
+    env.settings.cert_validity = Some(std::time::Duration::from_secs(30 * 24 * 60 * 60));
+    let app = init_app!(env);
+    let email = "expiring@example.com";
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "name": "Expiring Child",
+            "title": "Test Title",
+            "code": env.last_code(email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let cert_id = body["id"].as_str().unwrap().to_string();
+    let expires_at = body["expires_at"].as_u64().unwrap();
+    assert_eq!(body["status"], "active");
+    assert_eq!(expires_at, (env.clock.now() + Duration::days(30)).timestamp() as u64);
+
+    // The reminder is sent once the expiration is within the reminder period
+    let kv_store: &dyn KvStore = env.kv_store.as_ref();
+    let sent = expiry::send_expiry_reminders(kv_store, env.cert_store.as_ref(), env.clock.now()).await.unwrap();
+    assert_eq!(sent, 0);
+
+    env.clock.advance(Duration::days(20));
+    let sent = expiry::send_expiry_reminders(kv_store, env.cert_store.as_ref(), env.clock.now()).await.unwrap();
+    assert_eq!(sent, 1);
+    let sent = expiry::send_expiry_reminders(kv_store, env.cert_store.as_ref(), env.clock.now()).await.unwrap();
+    assert_eq!(sent, 0);
+
+    let reminder = kv_store.list_all(EMAIL_JOBS_KEY.to_string())
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|raw| serde_json::from_str::<EmailTask>(raw).ok())
+        .find(|task| task.email == email && task.purpose == "expiry")
+        .unwrap();
+    assert_eq!(reminder.replacements["CERTID"], cert_id);
+
+    // The certificate is still served after the expiration, with the status changed
+    env.clock.advance(Duration::days(15));
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "expired");
+
+    // The renewal keeps the serial number and counts the validity period from now
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "renew", "id": cert_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let renew_body = json!({
+        "email": email,
+        "code": env.last_code(email).await,
+        "token": body["token"]
+    });
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/renew")
+        .peer_addr(next_peer())
+        .set_json(&renew_body)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["id"], cert_id.as_str());
+    assert_eq!(body["status"], "active");
+    assert_eq!(body["expires_at"], (env.clock.now() + Duration::days(30)).timestamp());
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", cert_id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "active");
+
+    // The code is single-use
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/renew")
+        .peer_addr(next_peer())
+        .set_json(&renew_body)
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_ne!(response.status(), StatusCode::OK);
+
+    // The repeated renewals don't add up, the expiration is counted from the last one
+    env.clock.advance(Duration::days(1));
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "renew", "id": cert_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/renew")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "code": env.last_code(email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["expires_at"], (env.clock.now() + Duration::days(30)).timestamp());
+
+    // The certificates issued before the validity was set never expire, the renewal doesn't change that
+    let lasting_email = "lasting@example.com";
+    let lasting_id = Uuid::new_v4();
+    env.cert_store.create_cert(CertModel {
+        id: lasting_id,
+        email: lasting_email.to_string(),
+        name: "Lasting Child".to_string(),
+        title: "Test Title".to_string(),
+        kind: "classic".to_string(),
+        created_at: env.clock.now(),
+        is_public: false,
+        updated_at: None,
+        expires_at: None
+    }, 1, None).await.ok().unwrap();
+
+    let request = env.send_code(json!({ "email": lasting_email, "purpose": { "type": "renew", "id": lasting_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/renew")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": lasting_email,
+            "code": env.last_code(lasting_email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["status"], "active");
+    assert!(body["expires_at"].is_null());
+}
+
+#[actix_web::test]
+async fn get_cert_errors() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+
+    let request = test::TestRequest::get()
+        .uri("/api/v1/cert/not-a-serial")
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::get()
+        .uri("/api/v1/cert/00000000-0000-0000-0000-000000000000")
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let request = test::TestRequest::get()
+        .uri("/api/v1/unknown")
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn lockout_expires() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let email = "lockout@example.com";
+    let send_code = || env.send_code(json!({ "email": email, "purpose": { "type": "create" } }));
+
+    let response = test::call_service(&app, send_code().await.to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(response).await;
+    let token = body["token"].as_str().unwrap().to_string();
+    let code = wrong_code(&env.last_code(email).await);
+
+    let create_cert = || test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "name": "Test Name",
+            "title": "Test Title",
+            "code": code,
+            "token": token
+        }))
+        .to_request();
+
+    // Five wrong codes are allowed
+    for _ in 0..5 {
+        let response = test::call_service(&app, create_cert()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // The sixth one blocks the email address
+    let response = test::call_service(&app, create_cert()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "900");
+
+    let response = test::call_service(&app, send_code().await.to_request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The block lasts 15 minutes
+    env.clock.advance(Duration::minutes(14));
+
+    let response = test::call_service(&app, send_code().await.to_request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    env.clock.advance(Duration::minutes(2));
+
+    let response = test::call_service(&app, send_code().await.to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn challenge_is_required_once() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+
+    // The challenge is issued by the API too
+    let request = test::TestRequest::get()
+        .uri("/api/v1/challenge")
+        .peer_addr(next_peer())
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    assert!(body["challenge"].is_string());
+    assert!(body["difficulty"].as_u64().unwrap() > 0);
+
+    // No solution
+    let request = test::TestRequest::post()
+        .uri("/api/v1/send_code")
+        .peer_addr(next_peer())
+        .set_json(json!({ "email": "first@example.com", "purpose": { "type": "create" } }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // The solution works once
+    let (challenge, solution) = env.solved_challenge().await;
+    let send_code = |email: &str| test::TestRequest::post()
+        .uri("/api/v1/send_code")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "purpose": { "type": "create" },
+            "challenge": challenge,
+            "solution": solution
+        }))
+        .to_request();
+
+    let response = test::call_service(&app, send_code("first@example.com")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = test::call_service(&app, send_code("second@example.com")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // The solution expires together with the challenge
+    let (challenge, solution) = env.solved_challenge().await;
+    env.clock.advance(Duration::minutes(6));
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/send_code")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": "second@example.com",
+            "purpose": { "type": "create" },
+            "challenge": challenge,
+            "solution": solution
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn ipv6_limit_covers_the_network() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let peer = |network: u16, host: u16| SocketAddr::new(
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0x0db8, 0, network, 0, 0, 0, host)),
+        40000
+    );
+
+    // Every address of the /64 network shares the limit
+    for host in 1..=5 {
+        let request = env.send_code(json!({ "email": format!("v6-{}@example.com", host), "purpose": { "type": "create" } }))
+            .await
+            .peer_addr(peer(1, host))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let request = env.send_code(json!({ "email": "v6-6@example.com", "purpose": { "type": "create" } }))
+        .await
+        .peer_addr(peer(1, 6))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "ip_rate_limit");
+
+    // Another /64 of the same /48 has its own limit
+    let request = env.send_code(json!({ "email": "v6-6@example.com", "purpose": { "type": "create" } }))
+        .await
+        .peer_addr(peer(2, 1))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn forwarded_headers_need_trusted_proxy() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    // The private networks aren't trusted by default, so a host of the network is a client too
+    let private_peer = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 7, 5)), 40000);
+
+    // A client that connects directly can't pretend to be another one
+    // The same peer also meets the per-second limit of the API, so the requests are spread
+    for index in 1..=5 {
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(400)).await;
+
+        let request = env.send_code(json!({ "email": format!("direct-{}@example.com", index), "purpose": { "type": "create" } }))
+            .await
+            .peer_addr(private_peer)
+            .insert_header(("X-Forwarded-For", format!("198.51.{}.1", index)))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    actix_web::rt::time::sleep(std::time::Duration::from_millis(400)).await;
+
+    let request = env.send_code(json!({ "email": "direct-6@example.com", "purpose": { "type": "create" } }))
+        .await
+        .peer_addr(private_peer)
+        .insert_header(("X-Forwarded-For", "198.51.6.1"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The loopback is a trusted proxy, so the forwarded client is counted instead of the proxy
+    let request = env.send_code(json!({ "email": "direct-6@example.com", "purpose": { "type": "create" } }))
+        .await
+        .peer_addr(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 40000))
+        .insert_header(("X-Forwarded-For", "198.51.6.1"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn security_and_cors_headers() {
+    // The other site that may call the API
+    let mut env = TestEnv::new();
+    env.settings.cors_allowed_origins = vec!["https://partner.example".to_string()];
+    let app = init_app!(env);
+
+    // Even the errors get the security headers
+    let request = test::TestRequest::get()
+        .uri("/api/v1/cert/00000000-0000-0000-0000-000000000000")
+        .peer_addr(next_peer())
+        .insert_header((header::ORIGIN, "https://partner.example"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+    assert_eq!(response.headers().get(header::REFERRER_POLICY).unwrap(), "no-referrer");
+    assert!(response.headers().contains_key(header::CONTENT_SECURITY_POLICY));
+    assert_eq!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "https://partner.example");
+
+    let preflight = |uri: &str, origin: &str| test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri(uri)
+        .peer_addr(next_peer())
+        .insert_header((header::ORIGIN, origin.to_string()))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+        .to_request();
+
+    // The allowed origin
+    let response = test::call_service(&app, preflight("/api/v1/send_code", "https://partner.example")).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "https://partner.example");
+    assert!(response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+
+    // Another origin
+    let response = test::call_service(&app, preflight("/api/v1/send_code", "https://other.example")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+    // The administrative scopes are closed for the other sites
+    let response = test::call_service(&app, preflight("/api/v1/webhooks", "https://partner.example")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    assert_eq!(response.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+
+    // The preflights don't use up the rate limit of the real requests
+    let peer = next_peer();
+    for _ in 0..5 {
+        let request = test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/api/v1/kinds")
+            .peer_addr(peer)
+            .insert_header((header::ORIGIN, "https://partner.example"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    let request = test::TestRequest::get()
+        .uri("/api/v1/kinds")
+        .peer_addr(peer)
+        .insert_header((header::ORIGIN, "https://partner.example"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn bulk_issuance_requires_admin() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+
+    // The request without the administrative token is rejected before the body is read
+    let request = test::TestRequest::post()
+        .uri("/api/v1/certs/bulk")
+        .peer_addr(next_peer())
+        .insert_header((header::CONTENT_TYPE, "text/csv"))
+        .insert_header((header::AUTHORIZATION, "Bearer wrong-token"))
+        .set_payload("email,name,title\nbulk@example.com,Test Name,Test Title\n")
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "unauthorized");
+    assert!(env.cert_store.find_certs_by_email("bulk@example.com".to_string()).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn problem_json_errors() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let missing_cert = "/api/v1/cert/00000000-0000-0000-0000-000000000000";
+
+    // The usual body by default
+    let request = test::TestRequest::get()
+        .uri(missing_cert)
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code_error"], "resource_not_found");
+    assert!(body.get("status").is_none());
+
+    // RFC 7807 if the client asks for it
+    let request = test::TestRequest::get()
+        .uri(missing_cert)
+        .peer_addr(next_peer())
+        .insert_header((header::ACCEPT, "application/problem+json, application/json;q=0.5"))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["type"], "urn:pupsiks:problem:resource_not_found");
+    assert_eq!(body["title"], "Not Found");
+    assert_eq!(body["status"], 404);
+    assert_eq!(body["instance"], missing_cert);
+    assert_eq!(body["code_error"], "resource_not_found");
+    assert!(body["detail"].as_str().is_some_and(|detail| !detail.is_empty()));
+
+    // Always RFC 7807 in /api/v2, the extension members are kept
+    let app = test::init_service(
+        App::new().service(api_v2_scope(
+            env.database.clone(),
+            env.cert_store.clone(),
+            env.kv_store.clone(),
+            env.clock.clone(),
+            Arc::new(env.settings.clone()),
+            env.rate_limit_counters.clone()
+        ))
+    ).await;
+
+    let request = test::TestRequest::get()
+        .uri("/api/v2/unknown")
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["type"], "urn:pupsiks:problem:page_not_found");
+    assert_eq!(body["instance"], "/api/v2/unknown");
+    assert!(body["endpoints"].is_array());
+    assert!(body.get("message").is_none());
+}
+
+/// Collects the subjects of the errors (the `what` and `what_invalid` literals) from the source files
+fn collect_error_subjects(path: &Path, subjects: &mut BTreeSet<String>) {
+    for entry in std::fs::read_dir(path).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            collect_error_subjects(&path, subjects);
+            continue;
+        }
+
+        if path.extension().is_none_or(|extension| extension != "rs") {
+            continue;
+        }
+
+        let source = std::fs::read_to_string(&path).unwrap();
+
+        for marker in ["what: \"", "what_invalid: \""] {
+            for (start, _) in source.match_indices(marker) {
+                let rest = &source[start + marker.len()..];
+                subjects.insert(rest[..rest.find('"').unwrap()].to_string());
+            }
+        }
+    }
+}
+
+#[actix_web::test]
+async fn every_error_subject_is_translated() {
+    let mut subjects = BTreeSet::new();
+    collect_error_subjects(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut subjects);
+    assert!(subjects.contains("field values"));
+
+    let untranslated: Vec<_> = subjects
+        .iter()
+        .filter(|subject| locale::translate_subject(Language::Uk, subject) == **subject)
+        .collect();
+    assert!(untranslated.is_empty(), "untranslated subjects: {:?}", untranslated);
+}
+
+#[actix_web::test]
+async fn manage_cert_with_session() {
+    let mut env = TestEnv::new();
+    env.settings.session_secret = Some("test_session_secret".to_string());
+    let app = init_app!(env);
+    let email = "session@example.com";
+
+    // Create the certificate
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "name": "Test Name",
+            "title": "Test Title",
+            "code": env.last_code(email).await,
+            "token": token
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    // Exchange the session code for the session
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "session", "id": id } })).await.to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/session")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "code": env.last_code(email).await,
+            "token": token
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["id"], id.as_str());
+    let authorization = format!("Bearer {}", body["session"].as_str().unwrap());
+
+    // The session shows the certificate and changes it without codes
+    let request = test::TestRequest::get()
+        .uri("/api/v1/me")
+        .peer_addr(next_peer())
+        .insert_header((header::AUTHORIZATION, authorization.clone()))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Test Name");
+    assert_eq!(body["public"], false);
+
+    let request = test::TestRequest::patch()
+        .uri("/api/v1/me/visibility")
+        .peer_addr(next_peer())
+        .insert_header((header::AUTHORIZATION, authorization.clone()))
+        .set_json(json!({ "public": true }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The session expires by itself
+    env.clock.advance(Duration::minutes(31));
+
+    let request = test::TestRequest::get()
+        .uri("/api/v1/me")
+        .peer_addr(next_peer())
+        .insert_header((header::AUTHORIZATION, authorization.clone()))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn revoked_session_is_rejected() {
+    let mut env = TestEnv::new();
+    env.settings.session_secret = Some("test_session_secret".to_string());
+    let app = init_app!(env);
+    let email = "revoke@example.com";
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "name": "Test Name",
+            "title": "Test Title",
+            "code": env.last_code(email).await,
+            "token": token
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let id = body["id"].as_str().unwrap().to_string();
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "session", "id": id } })).await.to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/session")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "code": env.last_code(email).await,
+            "token": token
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, request).await;
+    let authorization = format!("Bearer {}", body["session"].as_str().unwrap());
+
+    // Revoke the session before it expires
+    let request = test::TestRequest::delete()
+        .uri("/api/v1/session")
+        .peer_addr(next_peer())
+        .insert_header((header::AUTHORIZATION, authorization.clone()))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::delete()
+        .uri("/api/v1/me/cert")
+        .peer_addr(next_peer())
+        .insert_header((header::AUTHORIZATION, authorization))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The certificate is still there
+    let request = test::TestRequest::get()
+        .uri(&format!("/api/v1/cert/{}", id))
+        .peer_addr(next_peer())
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn rolled_up_deletions_keep_stats() {
+    let env = TestEnv::new();
+    let cert_store: &dyn CertStore = env.cert_store.as_ref();
+    let start = Utc.with_ymd_and_hms(2025, 3, 3, 10, 0, 0).unwrap();
+    env.clock.set(start);
+
+    // Three certificates created on different days, two of them deleted on the same day
+    for (index, days) in [0, 1, 2].into_iter().enumerate() {
+        let cert = CertModel {
+            id: Uuid::new_v4(),
+            email: format!("rollup{}@example.com", index),
+            name: "Rolled Child".to_string(),
+            title: "Test Title".to_string(),
+            kind: "default".to_string(),
+            created_at: start + Duration::days(days) + Duration::hours(index as i64),
+            is_public: false,
+            updated_at: None,
+            expires_at: None
+        };
+        cert_store.create_cert(cert, 1, None).await.ok().unwrap();
+    }
+
+    env.clock.set(start + Duration::days(5));
+    assert_eq!(cert_store.remove_cert_by_email("rollup0@example.com".to_string()).await.unwrap(), 1);
+    env.clock.advance(Duration::hours(3));
+    assert_eq!(cert_store.remove_cert_by_email("rollup1@example.com".to_string()).await.unwrap(), 1);
+
+    // The statistics are asked at the midnights, like the timeseries does
+    let from = Utc.with_ymd_and_hms(2025, 3, 2, 0, 0, 0).unwrap();
+    let to = start + Duration::days(10);
+    let stats = async || {
+        let mut created = cert_store.count_created_by_buckets("day", from, to).await.unwrap();
+        let mut deleted = cert_store.count_deleted_by_buckets("day", from, to).await.unwrap();
+        created.sort();
+        deleted.sort();
+        let mut existing = Vec::new();
+        for days in 0..10 {
+            existing.push(cert_store.count_existing_at(from + Duration::days(days)).await.unwrap());
+        }
+
+        (created, deleted, existing)
+    };
+
+    let before = stats().await;
+    assert_eq!(before.1.iter().map(|(_, count)| count).sum::<u64>(), 2);
+
+    // The purged records become the daily counts, the statistics don't change
+    assert_eq!(cert_store.roll_up_deletion_records_before(start + Duration::days(6)).await.unwrap(), 2);
+    assert_eq!(cert_store.roll_up_deletion_records_before(start + Duration::days(6)).await.unwrap(), 0);
+    assert_eq!(stats().await, before);
+}
+
+#[actix_web::test]
+async fn api_versions_share_rate_limit() {
+    let env = TestEnv::new();
+    let app = test::init_service(
+        App::new()
+            .service(api_v1_scope(
+                env.database.clone(),
+                env.cert_store.clone(),
+                env.kv_store.clone(),
+                env.clock.clone(),
+                Arc::new(env.settings.clone()),
+                env.rate_limit_counters.clone()
+            ))
+            .service(api_v2_scope(
+                env.database.clone(),
+                env.cert_store.clone(),
+                env.kv_store.clone(),
+                env.clock.clone(),
+                Arc::new(env.settings.clone()),
+                env.rate_limit_counters.clone()
+            ))
+    ).await;
+    let peer = next_peer();
+
+    // Three requests a second are allowed for the client whatever version it calls
+    for uri in ["/api/v1/kinds", "/api/v2/kinds", "/api/v1/kinds"] {
+        let request = test::TestRequest::get().uri(uri).peer_addr(peer).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let request = test::TestRequest::get().uri("/api/v2/kinds").peer_addr(peer).to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn erasure_removes_transfers_and_stored_responses() {
+    let env = TestEnv::new();
+    let app = init_app!(env);
+    let email = "erased@example.com";
+    let new_email = "kept@example.com";
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "create" } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert")
+        .peer_addr(next_peer())
+        .insert_header(("Idempotency-Key", "erased-create-1"))
+        .set_json(json!({
+            "email": email,
+            "name": "Erased Child",
+            "title": "Test Title",
+            "code": env.last_code(email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let cert_id = body["id"].as_str().unwrap().to_string();
+
+    let request = env.send_code(json!({ "email": email, "purpose": { "type": "transfer", "id": cert_id } })).await.to_request();
+    let response = test::call_service(&app, request).await;
+    let body: Value = test::read_body_json(response).await;
+
+    let request = test::TestRequest::post()
+        .uri("/api/v1/cert/transfer")
+        .peer_addr(next_peer())
+        .set_json(json!({
+            "email": email,
+            "new_email": new_email,
+            "code": env.last_code(email).await,
+            "token": body["token"]
+        }))
+        .to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The export shows the transfer to both sides and the response kept for the retries
+    let kv_store: &dyn KvStore = env.kv_store.as_ref();
+    let bundle = personal_data::collect_personal_data(kv_store, env.cert_store.as_ref(), email, env.clock.now()).await.unwrap();
+    assert_eq!(bundle.pending_transfers.len(), 1);
+    assert_eq!(bundle.pending_transfers[0].certificate, cert_id);
+    assert_eq!(bundle.pending_transfers[0].direction, "outgoing");
+    assert_eq!(bundle.stored_responses.len(), 1);
+    assert!(bundle.stored_responses[0].body.as_ref().unwrap().contains(&cert_id));
+
+    let bundle = personal_data::collect_personal_data(kv_store, env.cert_store.as_ref(), new_email, env.clock.now()).await.unwrap();
+    assert_eq!(bundle.pending_transfers[0].direction, "incoming");
+    assert!(bundle.stored_responses.is_empty());
+
+    // The receipts and the webhooks need the tables, so the erasure runs against an in-memory data base
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options.max_connections(1).min_connections(1);
+    let database = Arc::new(Database::connect(options).await.unwrap());
+    sync_database_schema(&database).await.unwrap();
+
+    personal_data::erase_personal_data(
+        kv_store,
+        env.cert_store.as_ref(),
+        &WebhookRepo::new(database.clone()),
+        &ErasureRepo::new(database),
+        email,
+        env.clock.now()
+    ).await.unwrap();
+
+    assert!(kv_store.scan_keys("transfer:*".to_string()).await.unwrap().is_empty());
+    assert!(kv_store.scan_keys("idempotency:*".to_string()).await.unwrap().is_empty());
+
+    let bundle = personal_data::collect_personal_data(kv_store, env.cert_store.as_ref(), new_email, env.clock.now()).await.unwrap();
+    assert!(bundle.pending_transfers.is_empty());
+}
+
+#[actix_web::test]
+async fn pending_webhook_deliveries_are_requeued() {
+    let env = TestEnv::new();
+    let kv_store: &dyn KvStore = env.kv_store.as_ref();
+
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options.max_connections(1).min_connections(1);
+    let database = Arc::new(Database::connect(options).await.unwrap());
+    sync_database_schema(&database).await.unwrap();
+    let webhook_repo = WebhookRepo::new(database);
+
+    webhook_repo.create_webhook(WebhookModel {
+        id: Uuid::new_v4(),
+        url: "https://partner.example/hook".to_string(),
+        secret: webhooks::generate_webhook_secret(),
+        events: vec![WebhookEvent::CertCreated.as_str().to_string()],
+        created_at: env.clock.now()
+    }).await.unwrap();
+    webhooks::dispatch_event(kv_store, &webhook_repo, WebhookEvent::CertCreated, json!({})).await.unwrap();
+    env.clock.advance(Duration::minutes(1));
+
+    // The worker took the delivery off the schedule and stopped before sending it
+    let taken = kv_store.take_due(WEBHOOK_JOBS_KEY.to_string(), 20).await.unwrap();
+    assert_eq!(taken.len(), 1);
+    assert!(kv_store.take_due(WEBHOOK_JOBS_KEY.to_string(), 20).await.unwrap().is_empty());
+
+    // The restarted worker schedules it again
+    let requeued = webhooks::requeue_pending_deliveries(kv_store, &webhook_repo).await.unwrap();
+    assert_eq!(requeued, 1);
+    assert_eq!(kv_store.take_due(WEBHOOK_JOBS_KEY.to_string(), 20).await.unwrap(), taken);
+}
@@ -0,0 +1,196 @@
+//! Runs the SQL of the certificates repository against an in-memory SQLite data base
+//! PostgreSQL isn't available without docker, so only the SQLite branches are checked here
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use backend::{
+    api_v1::repos::{
+        CertModel,
+        CertRepo,
+        CertStore,
+        CreationError
+    },
+    connections::sync_database_schema
+};
+
+/// Opens an empty in-memory data base, one connection keeps it from disappearing between the queries
+async fn connect() -> DatabaseConnection {
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options.max_connections(1).min_connections(1);
+
+    Database::connect(options).await.unwrap()
+}
+
+async fn synced_repo() -> CertRepo {
+    let database = connect().await;
+    sync_database_schema(&database).await.unwrap();
+
+    CertRepo::new(Arc::new(database))
+}
+
+fn utc(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+}
+
+fn day(year: i32, month: u32, day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(0, 0, 0).unwrap()
+}
+
+fn cert(email: &str, name: &str, title: &str, is_public: bool, created_at: DateTime<Utc>) -> CertModel {
+    CertModel {
+        id: Uuid::new_v4(),
+        email: email.to_string(),
+        name: name.to_string(),
+        title: title.to_string(),
+        kind: "classic".to_string(),
+        created_at,
+        is_public,
+        updated_at: None,
+        expires_at: None
+    }
+}
+
+async fn add_cert(repo: &CertRepo, cert: CertModel) -> Uuid {
+    match repo.create_cert(cert, 10, None).await {
+        Ok(id) => id,
+        Err(_) => panic!("the certificate wasn't created")
+    }
+}
+
+/// Sorts the buckets, the GROUP BY doesn't order them
+fn sorted(mut buckets: Vec<(NaiveDateTime, u64)>) -> Vec<(NaiveDateTime, u64)> {
+    buckets.sort();
+    buckets
+}
+
+#[actix_web::test]
+async fn schema_sync_is_repeatable() {
+    let database = Arc::new(connect().await);
+    sync_database_schema(&database).await.unwrap();
+
+    let repo = CertRepo::new(database.clone());
+    add_cert(&repo, cert("again@example.com", "Child", "Title", false, utc(2025, 1, 1, 10))).await;
+
+    // The restarts sync the schema again and keep the rows
+    sync_database_schema(&database).await.unwrap();
+    assert_eq!(repo.count_by_email("again@example.com".to_string()).await.unwrap(), 1);
+}
+
+#[actix_web::test]
+async fn sqlite_buckets_match_date_trunc() {
+    let repo = synced_repo().await;
+
+    // 2025-03-02 is Sunday, 2025-03-03 is Monday
+    let created = [
+        utc(2025, 2, 28, 23),
+        utc(2025, 3, 2, 0),
+        utc(2025, 3, 2, 23),
+        utc(2025, 3, 3, 0),
+        utc(2025, 3, 5, 12),
+        utc(2025, 3, 9, 23)
+    ];
+    for (index, created_at) in created.into_iter().enumerate() {
+        add_cert(&repo, cert(&format!("bucket{}@example.com", index), "Child", "Title", false, created_at)).await;
+    }
+
+    let from = utc(2025, 2, 1, 0);
+    let to = utc(2025, 4, 1, 0);
+
+    assert_eq!(
+        sorted(repo.count_created_by_buckets("day", from, to).await.unwrap()),
+        vec![(day(2025, 2, 28), 1), (day(2025, 3, 2), 2), (day(2025, 3, 3), 1), (day(2025, 3, 5), 1), (day(2025, 3, 9), 1)]
+    );
+    assert_eq!(
+        sorted(repo.count_created_by_buckets("week", from, to).await.unwrap()),
+        vec![(day(2025, 2, 24), 3), (day(2025, 3, 3), 3)]
+    );
+    assert_eq!(
+        sorted(repo.count_created_by_buckets("month", from, to).await.unwrap()),
+        vec![(day(2025, 2, 1), 1), (day(2025, 3, 1), 5)]
+    );
+
+    // The range end is excluded
+    assert_eq!(
+        sorted(repo.count_created_by_buckets("day", utc(2025, 3, 2, 0), utc(2025, 3, 3, 0)).await.unwrap()),
+        vec![(day(2025, 3, 2), 2)]
+    );
+    assert!(repo.count_created_by_buckets("year", from, to).await.is_err());
+}
+
+#[actix_web::test]
+async fn sqlite_deletion_buckets_survive_roll_up() {
+    let repo = synced_repo().await;
+
+    add_cert(&repo, cert("gone0@example.com", "Child", "Title", false, utc(2025, 3, 3, 10))).await;
+    add_cert(&repo, cert("gone1@example.com", "Child", "Title", false, utc(2025, 3, 4, 10))).await;
+    add_cert(&repo, cert("kept@example.com", "Child", "Title", false, utc(2025, 3, 4, 11))).await;
+
+    assert_eq!(repo.remove_cert_by_email("gone0@example.com".to_string()).await.unwrap(), 1);
+    assert_eq!(repo.remove_cert_by_email("gone1@example.com".to_string()).await.unwrap(), 1);
+
+    let from = utc(2025, 3, 1, 0);
+    let to = Utc::now() + Duration::days(2);
+    let at = utc(2025, 3, 5, 0);
+
+    let created = sorted(repo.count_created_by_buckets("day", from, to).await.unwrap());
+    let deleted = sorted(repo.count_deleted_by_buckets("day", from, to).await.unwrap());
+    assert_eq!(created, vec![(day(2025, 3, 3), 1), (day(2025, 3, 4), 2)]);
+    assert_eq!(deleted.iter().map(|(_, count)| count).sum::<u64>(), 2);
+    assert_eq!(repo.count_existing_at(at).await.unwrap(), 3);
+
+    // The deletion records become the daily counts, the statistics stay the same
+    assert_eq!(repo.roll_up_deletion_records_before(to).await.unwrap(), 2);
+    assert_eq!(repo.roll_up_deletion_records_before(to).await.unwrap(), 0);
+
+    assert_eq!(sorted(repo.count_created_by_buckets("day", from, to).await.unwrap()), created);
+    assert_eq!(sorted(repo.count_deleted_by_buckets("day", from, to).await.unwrap()), deleted);
+    assert_eq!(repo.count_existing_at(at).await.unwrap(), 3);
+}
+
+#[actix_web::test]
+async fn sqlite_search_ranks_public_certs() {
+    let repo = synced_repo().await;
+    let created_at = utc(2025, 3, 3, 10);
+
+    let both = add_cert(&repo, cert("search0@example.com", "Олена Коваль", "Olena Koval", true, created_at)).await;
+    let cyrillic = add_cert(&repo, cert("search1@example.com", "ОЛЕНА", "Title", true, created_at)).await;
+    let latin = add_cert(&repo, cert("search2@example.com", "Child", "For OLENA", true, created_at)).await;
+    add_cert(&repo, cert("search3@example.com", "Олена", "Olena", false, created_at)).await;
+    add_cert(&repo, cert("search4@example.com", "Other", "Title", true, created_at)).await;
+
+    // The certificates that match both variants go first, the private ones aren't found
+    let hits = repo.search_public_certs("олена", "olena", 10, 0).await.unwrap();
+    let ids: Vec<Uuid> = hits.iter().map(|hit| hit.id).collect();
+    assert_eq!(ids.len(), 3);
+    assert_eq!(ids[0], both);
+    assert_eq!(hits[0].rank, 2.0);
+    let mut rest = vec![cyrillic, latin];
+    rest.sort();
+    assert_eq!(ids[1..], rest[..]);
+
+    // The pages don't overlap
+    let page = repo.search_public_certs("олена", "olena", 2, 1).await.unwrap();
+    assert_eq!(page.iter().map(|hit| hit.id).collect::<Vec<Uuid>>(), rest);
+
+    assert!(repo.search_public_certs("nobody", "nobody", 10, 0).await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn sqlite_create_checks_kind_cap() {
+    let repo = synced_repo().await;
+    let created_at = utc(2025, 3, 3, 10);
+
+    let mut capped = cert("cap0@example.com", "Child", "Title", false, created_at);
+    capped.kind = "volunteer".to_string();
+    assert!(repo.create_cert(capped.clone(), 10, Some(1)).await.is_ok());
+
+    // The cap is counted in the creation transaction, the other kinds aren't limited by it
+    capped.id = Uuid::new_v4();
+    capped.email = "cap1@example.com".to_string();
+    assert!(matches!(repo.create_cert(capped, 10, Some(1)).await, Err(CreationError::KindCapReached)));
+    assert!(repo.create_cert(cert("cap1@example.com", "Child", "Title", false, created_at), 10, Some(1)).await.is_ok());
+    assert_eq!(repo.count_by_kind("volunteer".to_string()).await.unwrap(), 1);
+}
@@ -3,3 +3,6 @@ from os import getenv
 BASE_URL = "http://backend:8080"
 TEST_EMAIL = getenv("TEST_EMAIL")
 VALID_CODE = "AAA123BBB"
+ADMIN_TOKEN = getenv("ADMIN_TOKEN")
+WEBHOOK_RECEIVER_HOST = "tests"
+WEBHOOK_RECEIVER_PORT = 9000
@@ -71,6 +71,18 @@ func TemplateNameByPurpose(purpose string) string {
 		return "delete_cert"
 	case "forgot":
 		return "forgot_cert"
+	case "manage":
+		return "manage_cert"
+	case "export":
+		return "export_data"
+	case "ready":
+		return "cert_ready"
+	case "transfer_out":
+		return "cert_transferred_out"
+	case "transfer_in":
+		return "cert_transferred_in"
+	case "expiry":
+		return "cert_expiring"
 	default:
 		return ""
 	}
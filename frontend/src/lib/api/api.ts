@@ -87,18 +87,21 @@ export const emptyRequest = async <JsonResult, PossibleErrors extends ErrorKeys[
  * @param method - The HTTP method of the request (e.g., "POST", "DELETE", "PUT").
  * @param body - The JSON body object to send the server.
  * @param callbacks - The set of success and error callbacks.
+ * @param idempotencyKey - The key that makes the retries of the same request return the first response.
  */
 export const jsonRequest = async <JsonResult, PossibleErrors extends ErrorKeys[]>(
   uri: string, 
   method: "POST"|"DELETE"|"PUT",
   body: Record<string, any>,
-  callbacks: CallbacksSet<JsonResult, PossibleErrors>
+  callbacks: CallbacksSet<JsonResult, PossibleErrors>,
+  idempotencyKey?: string
 ) => {
   const promise = fetch(uri, {
     method: method,
     body: JSON.stringify(body),
     headers: {
       "Content-Type": "application/json",
+      ...(idempotencyKey? { "Idempotency-Key": idempotencyKey } : {}),
     },
   });
 
@@ -7,7 +7,12 @@ export const API_CREATE_CERT = joinURL(API_HOST, "/cert");
 export const API_GET_CERT = (certId: string) => joinURLs(API_HOST, "/cert", certId);
 export const API_DELETE_CERT = joinURL(API_HOST, "/cert");
 export const API_FORGOT_CERT = joinURL(API_HOST, "/cert/forgot");
+export const API_TRANSFER_CERT = joinURL(API_HOST, "/cert/transfer");
+export const API_CONFIRM_TRANSFER = joinURL(API_HOST, "/cert/transfer/confirm");
+export const API_RENEW_CERT = joinURL(API_HOST, "/cert/renew");
 export const API_SEND_CODE = joinURL(API_HOST, "/send_code");
+export const API_CHALLENGE = joinURL(API_HOST, "/challenge");
+export const API_KINDS = joinURL(API_HOST, "/kinds");
 
 // Statistics
 export const API_STATS_USERS_COUNT = joinURL(API_HOST, "/stats/users_count");
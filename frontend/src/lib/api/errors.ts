@@ -44,6 +44,11 @@ export const errors = {
   ALREADY_EXISTS: new APIError("already_exists"),
   TRIES_OUT: new APIError("tries_out"),
   INVALID_EMAIL: new APIError("invalid_email"),
+  INVALID_CHALLENGE: new APIError("invalid_challenge"),
+  IDEMPOTENCY_KEY_REUSED: new APIError("idempotency_key_reused"),
+  IDEMPOTENCY_KEY_IN_PROGRESS: new APIError("idempotency_key_in_progress"),
+  KIND_UNAVAILABLE: new APIError("kind_unavailable"),
+  CERT_QUOTA_EXCEEDED: new APIError("cert_quota_exceeded"),
   FATAL_ERROR: new APIError("fatal")
 } as const;
 
@@ -1,5 +1,6 @@
 import { emptyRequest, jsonRequest, type CallbacksSet } from "../api";
 import { API_CREATE_CERT, API_DELETE_CERT, API_FORGOT_CERT, API_GET_CERT } from "../configs";
+import { solveChallenge } from "./challenge";
 
 
 /* --------------- *
@@ -18,7 +19,19 @@ type GetCertResponse = {
   /**
    * The additional title of the person specified in the certificate.
    */
-  title: string
+  title: string,
+  /**
+   * The kind of the certificate from the catalogue.
+   */
+  kind: string,
+  /**
+   * "active" or "expired" if the validity period has passed.
+   */
+  status: "active" | "expired",
+  /**
+   * The Unix time the certificate expires at, null if it never expires.
+   */
+  expires_at: number | null
 };
 
 /**
@@ -62,7 +75,11 @@ type CreateCertRequest = {
   /**
    * The temporary token received after the code request.
    */
-  token: string
+  token: string,
+  /**
+   * The kind of the certificate from the catalogue, the classic one by default.
+   */
+  kind?: string
 };
 
 type CreateCertResponse = {
@@ -77,11 +94,24 @@ type CreateCertResponse = {
   /**
    * The additional title of the person to be specified in the certificate.
    */
-  title: string
+  title: string,
+  /**
+   * The kind of the certificate from the catalogue.
+   */
+  kind: string,
+  /**
+   * "active" or "expired" if the validity period has passed.
+   */
+  status: "active" | "expired",
+  /**
+   * The Unix time the certificate expires at, null if it never expires.
+   */
+  expires_at: number | null
 };
 
 /**
  * Creates a new certificate using the provided user data and verification code.
+ * The token is the idempotency key, so a retry after a lost connection gets the created certificate.
  *
  * @param data - The request body containing user data, code, and token.
  * @param callbacks - The set of success and error callbacks.
@@ -93,13 +123,16 @@ export const createCert = (
   "BAD_REQUEST",
   "INVALID_ROUTE",
   "INTERNAL_SERVER_ERROR",
-  "ALREADY_EXISTS",
+  "CERT_QUOTA_EXCEEDED",
   "INVALID_TOKEN",
   "RESOURCE_NOT_FOUND",
   "INVALID_CODE",
-  "TRIES_OUT"
+  "TRIES_OUT",
+  "KIND_UNAVAILABLE",
+  "IDEMPOTENCY_KEY_REUSED",
+  "IDEMPOTENCY_KEY_IN_PROGRESS"
 ]>
-) => jsonRequest(API_CREATE_CERT, "POST", data, callbacks);
+) => jsonRequest(API_CREATE_CERT, "POST", data, callbacks, `create-${data.token}`);
 
 
 /* ------------------ *
@@ -130,6 +163,7 @@ type DeleteCertResponse = {
 
 /**
  * Deletes a certificate using the provided verification data.
+ * The token is the idempotency key, so a retry after a lost connection gets the deleted certificate ID.
  *
  * @param data - The request body containing the email, code, and token.
  * @param callbacks - The set of success and error callbacks.
@@ -144,9 +178,11 @@ export const deleteCert = (
   "RESOURCE_NOT_FOUND",
   "INVALID_TOKEN",
   "INVALID_CODE",
-  "TRIES_OUT"
+  "TRIES_OUT",
+  "IDEMPOTENCY_KEY_REUSED",
+  "IDEMPOTENCY_KEY_IN_PROGRESS"
 ]>
-) => jsonRequest(API_DELETE_CERT, "DELETE", data, callbacks);
+) => jsonRequest(API_DELETE_CERT, "DELETE", data, callbacks, `delete-${data.token}`);
 
 
 /* ------------------ *
@@ -166,7 +202,7 @@ type ForgotCertResponse = {
  * @param email - The email address to send the certificate ID to.
  * @param callbacks - The set of success and error callbacks.
  */
-export const forgotCert = (
+export const forgotCert = async (
   email: string,
   callbacks: CallbacksSet<ForgotCertResponse, [
   "FATAL_ERROR",
@@ -174,6 +210,7 @@ export const forgotCert = (
   "IP_RATE_LIMIT",
   "EMAIL_RATE_LIMIT",
   "RESOURCE_NOT_FOUND",
-  "BAD_REQUEST"
+  "BAD_REQUEST",
+  "INVALID_CHALLENGE"
 ]>
-) => jsonRequest(API_FORGOT_CERT, "POST", { email: email }, callbacks);
+) => jsonRequest(API_FORGOT_CERT, "POST", { email: email, ...await solveChallenge() }, callbacks);
@@ -0,0 +1,71 @@
+import { API_CHALLENGE } from "../configs";
+
+type ChallengeResponse = {
+  /**
+   * The signed challenge that has to be sent back with the solution.
+   */
+  challenge: string,
+  /**
+   * The amount of leading zero bits the hash of the solution must have.
+   */
+  difficulty: number,
+  /**
+   * Timestamp (in seconds) indicating when the challenge expires.
+   */
+  expires_at: number
+};
+
+type SolvedChallenge = {
+  challenge: string,
+  solution: string
+};
+
+/**
+ * Counts the leading zero bits of the hash.
+ *
+ * @param hash - The SHA-256 digest.
+ */
+const leadingZeroBits = (hash: Uint8Array) => {
+  let bits = 0;
+
+  for (const byte of hash) {
+    if (byte === 0) {
+      bits += 8;
+    } else {
+      return bits + Math.clz32(byte) - 24;
+    }
+  }
+
+  return bits;
+};
+
+/**
+ * Receives a proof-of-work challenge and finds a counter that makes
+ * SHA-256 of "{challenge}:{counter}" start with enough zero bits.
+ *
+ * @returns The fields for the request body, or an empty object if the challenge can't be received.
+ * The server rejects the request without a solution, so the error is handled by the request itself.
+ */
+export const solveChallenge = async (): Promise<SolvedChallenge | {}> => {
+  try {
+    const resp = await fetch(API_CHALLENGE);
+    if (!resp.ok) {
+      return {};
+    }
+
+    const { challenge, difficulty }: ChallengeResponse = await resp.json();
+    const encoder = new TextEncoder();
+
+    for (let counter = 0; ; counter++) {
+      const solution = counter.toString();
+      const hash = await crypto.subtle.digest("SHA-256", encoder.encode(`${challenge}:${solution}`));
+
+      if (leadingZeroBits(new Uint8Array(hash)) >= difficulty) {
+        return { challenge, solution };
+      }
+    }
+  } catch (err) {
+    console.error("challenge", err);
+    return {};
+  }
+};
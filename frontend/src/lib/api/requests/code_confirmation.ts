@@ -1,5 +1,6 @@
 import { jsonRequest, type CallbacksSet } from "../api";
 import { API_SEND_CODE } from "../configs";
+import { solveChallenge } from "./challenge";
 
 type SendCodeResponse = {
   /**
@@ -35,16 +36,18 @@ export const sendCodeCertCreation = async (
   "BAD_REQUEST",
   "INTERNAL_SERVER_ERROR",
   "RESOURCE_NOT_FOUND",
-  "ALREADY_EXISTS",
+  "CERT_QUOTA_EXCEEDED",
   "INVALID_EMAIL",
   "IP_RATE_LIMIT",
-  "EMAIL_RATE_LIMIT"
+  "EMAIL_RATE_LIMIT",
+  "INVALID_CHALLENGE"
 ]>
 ) => jsonRequest(API_SEND_CODE, "POST", { 
   purpose: { 
     type: "create"
   },
-  email: email
+  email: email,
+  ...await solveChallenge()
 }, callbacks);
 
 
@@ -69,12 +72,84 @@ export const sendCodeCertDeletion = async (
   "INTERNAL_SERVER_ERROR",
   "INVALID_EMAIL",
   "IP_RATE_LIMIT",
-  "EMAIL_RATE_LIMIT"
+  "EMAIL_RATE_LIMIT",
+  "INVALID_CHALLENGE"
 ]>
 ) => jsonRequest(API_SEND_CODE, "POST", { 
   purpose: { 
     type: "delete",
     id: certId
   },
-  email: email
+  email: email,
+  ...await solveChallenge()
+}, callbacks);
+
+
+/* ------------------------------ *
+ * Send code transfer certificate *
+ * ------------------------------ */ 
+
+/**
+ * Sends a verification code to the current email address of the certificate
+ * to confirm its transfer to another email address.
+ *
+ * @param email - The current email address of the certificate.
+ * @param certId - The ID of the certificate to be transferred.
+ * @param callbacks - The set of success and error callbacks.
+ */
+export const sendCodeCertTransfer = async (
+  email: string,
+  certId: string,
+  callbacks: CallbacksSet<SendCodeResponse, [
+  "FATAL_ERROR",
+  "BAD_REQUEST",
+  "INTERNAL_SERVER_ERROR",
+  "RESOURCE_NOT_FOUND",
+  "INVALID_EMAIL",
+  "IP_RATE_LIMIT",
+  "EMAIL_RATE_LIMIT",
+  "INVALID_CHALLENGE"
+]>
+) => jsonRequest(API_SEND_CODE, "POST", { 
+  purpose: { 
+    type: "transfer",
+    id: certId
+  },
+  email: email,
+  ...await solveChallenge()
+}, callbacks);
+
+
+/* ----------------------------- *
+ * Send code renewal certificate *
+ * ----------------------------- */ 
+
+/**
+ * Sends a verification code to the email address of the certificate
+ * to confirm the extension of its validity.
+ *
+ * @param email - The email address of the certificate.
+ * @param certId - The ID of the certificate to be renewed.
+ * @param callbacks - The set of success and error callbacks.
+ */
+export const sendCodeCertRenewal = async (
+  email: string,
+  certId: string,
+  callbacks: CallbacksSet<SendCodeResponse, [
+  "FATAL_ERROR",
+  "BAD_REQUEST",
+  "INTERNAL_SERVER_ERROR",
+  "RESOURCE_NOT_FOUND",
+  "INVALID_EMAIL",
+  "IP_RATE_LIMIT",
+  "EMAIL_RATE_LIMIT",
+  "INVALID_CHALLENGE"
+]>
+) => jsonRequest(API_SEND_CODE, "POST", { 
+  purpose: { 
+    type: "renew",
+    id: certId
+  },
+  email: email,
+  ...await solveChallenge()
 }, callbacks);
@@ -0,0 +1,57 @@
+import { emptyRequest, type CallbacksSet } from "../api";
+import { API_KINDS } from "../configs";
+
+
+/* --------------------- *
+ * Get certificate kinds *
+ * --------------------- */
+
+type CertKind = {
+  /**
+   * The identifier of the kind, sent when the certificate is created.
+   */
+  id: string,
+  /**
+   * The name of the kind in the requested language.
+   */
+  name: string,
+  /**
+   * The titles suggested for the kind.
+   */
+  default_titles: string[],
+  /**
+   * The Unix time the kind became available at, if it's limited.
+   */
+  available_from: number | null,
+  /**
+   * The Unix time the kind stops being available at, if it's limited.
+   */
+  available_until: number | null,
+  /**
+   * The greatest amount of the certificates of the kind, if it's limited.
+   */
+  cap: number | null,
+  /**
+   * How many certificates of the kind can still be created, if it's limited.
+   */
+  remaining: number | null
+};
+
+type GetKindsResponse = {
+  /**
+   * The kinds that can be chosen right now.
+   */
+  kinds: CertKind[]
+};
+
+/**
+ * Fetches the certificate kinds that can be chosen right now.
+ *
+ * @param callbacks - The set of success and error callbacks.
+ */
+export const getKinds = async(
+  callbacks: CallbacksSet<GetKindsResponse, [
+  "FATAL_ERROR",
+  "INTERNAL_SERVER_ERROR"
+]>
+) => emptyRequest(API_KINDS, "GET", callbacks);
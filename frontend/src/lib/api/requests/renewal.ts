@@ -0,0 +1,69 @@
+import { jsonRequest, type CallbacksSet } from "../api";
+import { API_RENEW_CERT } from "../configs";
+
+
+/* ----------------- *
+ * Renew certificate *
+ * ----------------- */
+
+type RenewCertRequest = {
+  /**
+   * The email address associated with the certificate.
+   */
+  email: string,
+  /**
+   * The verification code received via email.
+   */
+  code: string,
+  /**
+   * The temporary token received after the code request.
+   */
+  token: string
+};
+
+type RenewCertResponse = {
+  /**
+   * The unique identifier of the certificate, it doesn't change.
+   */
+  id: string,
+  /**
+   * The name of the person specified in the certificate.
+   */
+  name: string,
+  /**
+   * The additional title of the person specified in the certificate.
+   */
+  title: string,
+  /**
+   * The identifier of the certificate kind.
+   */
+  kind: string,
+  /**
+   * The renewed certificate is always active.
+   */
+  status: "active" | "expired",
+  /**
+   * The new Unix time the certificate expires at, null if it never expires.
+   */
+  expires_at: number | null
+};
+
+/**
+ * Extends the validity of the certificate, an expired certificate becomes active again.
+ *
+ * @param data - The request body containing the email, code, and token.
+ * @param callbacks - The set of success and error callbacks.
+ */
+export const renewCert = (
+  data: RenewCertRequest,
+  callbacks: CallbacksSet<RenewCertResponse, [
+  "FATAL_ERROR",
+  "BAD_REQUEST",
+  "INVALID_ROUTE",
+  "INTERNAL_SERVER_ERROR",
+  "RESOURCE_NOT_FOUND",
+  "INVALID_TOKEN",
+  "INVALID_CODE",
+  "TRIES_OUT"
+]>
+) => jsonRequest(API_RENEW_CERT, "POST", data, callbacks);
@@ -0,0 +1,132 @@
+import { jsonRequest, type CallbacksSet } from "../api";
+import { API_CONFIRM_TRANSFER, API_TRANSFER_CERT } from "../configs";
+
+
+/* -------------------- *
+ * Transfer certificate *
+ * -------------------- */
+
+type TransferCertRequest = {
+  /**
+   * The current email address of the certificate.
+   */
+  email: string,
+  /**
+   * The email address the certificate is transferred to.
+   */
+  new_email: string,
+  /**
+   * The verification code received by the current email address.
+   */
+  code: string,
+  /**
+   * The temporary token received after the code request.
+   */
+  token: string
+};
+
+type TransferCertResponse = {
+  /**
+   * The new email address, the code to accept the transfer was sent to it.
+   */
+  email: string,
+  /**
+   * The temporary token required to accept the transfer.
+   */
+  token: string,
+  /**
+   * Timestamp (in seconds) indicating when the token expires.
+   */
+  expires_at: number
+};
+
+/**
+ * Confirms the transfer by the current holder and sends a code to the new email address.
+ * The certificate doesn't move until the new holder accepts it.
+ *
+ * @param data - The request body containing both email addresses, the code, and the token.
+ * @param callbacks - The set of success and error callbacks.
+ */
+export const transferCert = (
+  data: TransferCertRequest,
+  callbacks: CallbacksSet<TransferCertResponse, [
+  "FATAL_ERROR",
+  "BAD_REQUEST",
+  "INVALID_ROUTE",
+  "INTERNAL_SERVER_ERROR",
+  "RESOURCE_NOT_FOUND",
+  "INVALID_TOKEN",
+  "INVALID_CODE",
+  "TRIES_OUT",
+  "CERT_QUOTA_EXCEEDED",
+  "EMAIL_RATE_LIMIT"
+]>
+) => jsonRequest(API_TRANSFER_CERT, "POST", data, callbacks);
+
+
+/* --------------------------- *
+ * Accept certificate transfer *
+ * --------------------------- */
+
+type ConfirmTransferRequest = {
+  /**
+   * The new email address of the certificate.
+   */
+  email: string,
+  /**
+   * The verification code received by the new email address.
+   */
+  code: string,
+  /**
+   * The token received after the transfer was started.
+   */
+  token: string
+};
+
+type ConfirmTransferResponse = {
+  /**
+   * The unique identifier of the certificate, it doesn't change.
+   */
+  id: string,
+  /**
+   * The name of the person specified in the certificate.
+   */
+  name: string,
+  /**
+   * The additional title of the person specified in the certificate.
+   */
+  title: string,
+  /**
+   * The identifier of the certificate kind.
+   */
+  kind: string,
+  /**
+   * "active" or "expired" if the validity period has passed.
+   */
+  status: "active" | "expired",
+  /**
+   * The Unix time the certificate expires at, null if it never expires.
+   */
+  expires_at: number | null
+};
+
+/**
+ * Accepts the transfer by the new holder, both holders are notified by email.
+ *
+ * @param data - The request body containing the new email, the code, and the token.
+ * @param callbacks - The set of success and error callbacks.
+ */
+export const confirmTransfer = (
+  data: ConfirmTransferRequest,
+  callbacks: CallbacksSet<ConfirmTransferResponse, [
+  "FATAL_ERROR",
+  "BAD_REQUEST",
+  "INVALID_ROUTE",
+  "INTERNAL_SERVER_ERROR",
+  "RESOURCE_NOT_FOUND",
+  "INVALID_TOKEN",
+  "INVALID_CODE",
+  "TRIES_OUT",
+  "CERT_QUOTA_EXCEEDED"
+]>
+) => jsonRequest(API_CONFIRM_TRANSFER, "POST", data, callbacks);